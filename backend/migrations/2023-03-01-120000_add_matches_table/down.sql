@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE matches;
+DROP TYPE match_status;
\ No newline at end of file
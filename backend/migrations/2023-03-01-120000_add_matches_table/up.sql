@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TYPE match_status AS ENUM ('queued', 'running', 'finished', 'failed');
+CREATE TABLE matches (
+    id SERIAL PRIMARY KEY,
+    category_id INTEGER NOT NULL REFERENCES categories(id),
+    game_config JSONB NOT NULL,
+    seed INTEGER NOT NULL,
+    algo_version_ids INTEGER[] NOT NULL,
+    status match_status NOT NULL DEFAULT 'queued',
+    result JSONB,
+    replay JSONB,
+    error VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
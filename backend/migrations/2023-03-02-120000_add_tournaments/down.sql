@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE tournament_pairings;
+DROP TABLE tournament_entries;
+DROP TABLE tournaments;
+DROP TYPE tournament_status;
+DROP TYPE tournament_format;
\ No newline at end of file
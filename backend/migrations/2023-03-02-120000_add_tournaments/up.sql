@@ -0,0 +1,33 @@
+-- Your SQL goes here
+CREATE TYPE tournament_format AS ENUM ('round_robin', 'swiss', 'single_elimination');
+CREATE TYPE tournament_status AS ENUM ('scheduled', 'running', 'finished');
+
+CREATE TABLE tournaments (
+    id SERIAL PRIMARY KEY,
+    category_id INTEGER NOT NULL REFERENCES categories(id),
+    name VARCHAR NOT NULL,
+    format tournament_format NOT NULL,
+    rounds_count INTEGER,
+    status tournament_status NOT NULL DEFAULT 'scheduled',
+    current_round INTEGER NOT NULL DEFAULT 0,
+    starts_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE tournament_entries (
+    id SERIAL PRIMARY KEY,
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id),
+    user_id VARCHAR NOT NULL REFERENCES users(id),
+    algo_version_id INTEGER NOT NULL REFERENCES algo_version(id),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (tournament_id, user_id)
+);
+
+CREATE TABLE tournament_pairings (
+    id SERIAL PRIMARY KEY,
+    tournament_id INTEGER NOT NULL REFERENCES tournaments(id),
+    round INTEGER NOT NULL,
+    entry_ids INTEGER[] NOT NULL,
+    match_id INTEGER REFERENCES matches(id)
+);
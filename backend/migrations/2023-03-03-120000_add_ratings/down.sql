@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE rating_history;
+DROP TABLE ratings;
\ No newline at end of file
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE ratings (
+    id SERIAL PRIMARY KEY,
+    category_id INTEGER NOT NULL REFERENCES categories(id),
+    algo_version_id INTEGER NOT NULL REFERENCES algo_version(id),
+    rating DOUBLE PRECISION NOT NULL,
+    matches_played INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (category_id, algo_version_id)
+);
+
+CREATE TABLE rating_history (
+    id SERIAL PRIMARY KEY,
+    rating_id INTEGER NOT NULL REFERENCES ratings(id),
+    match_id INTEGER NOT NULL REFERENCES matches(id),
+    rating DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
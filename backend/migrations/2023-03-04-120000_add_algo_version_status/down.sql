@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE algo_version DROP COLUMN smoke_test;
+ALTER TABLE algo_version DROP COLUMN status;
+DROP TYPE algo_version_status;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+CREATE TYPE algo_version_status AS ENUM ('unverified', 'verified', 'broken');
+ALTER TABLE algo_version ADD COLUMN status algo_version_status NOT NULL DEFAULT 'unverified';
+ALTER TABLE algo_version ADD COLUMN smoke_test JSONB;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX algo_version_sha256;
+ALTER TABLE algo_version DROP COLUMN duplicate_id;
+ALTER TABLE algo_version DROP COLUMN sha256;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE algo_version ADD COLUMN sha256 VARCHAR;
+UPDATE algo_version SET sha256 = encode(sha256(file), 'hex');
+ALTER TABLE algo_version ALTER COLUMN sha256 SET NOT NULL;
+-- Files uploaded more than once before they were hashed keep every version
+-- and their real hash, the copies are told apart by their own id. Every
+-- other version has 0, so a file can only be uploaded once per algo again.
+ALTER TABLE algo_version ADD COLUMN duplicate_id INTEGER NOT NULL DEFAULT 0;
+UPDATE algo_version SET duplicate_id = id
+WHERE id NOT IN (SELECT MIN(id) FROM algo_version GROUP BY algo_id, sha256);
+CREATE UNIQUE INDEX algo_version_sha256 ON algo_version (algo_id, sha256, duplicate_id);
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE submissions (
+    id SERIAL PRIMARY KEY,
+    category_id INTEGER NOT NULL REFERENCES categories(id),
+    user_id VARCHAR NOT NULL REFERENCES users(id),
+    algo_version_id INTEGER NOT NULL REFERENCES algo_version(id),
+    late BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX submissions_category_user ON submissions (category_id, user_id, created_at);
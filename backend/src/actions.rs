@@ -68,11 +68,14 @@ pub fn get_algo_by_name(
     Ok(algo)
 }
 
+/// Stores a version of a user's algo, creating the algo if it's new. A file
+/// the algo already has isn't stored again, the version holding it is
+/// returned instead. Also returns whether the version is new.
 pub fn insert_new_algo(
     conn: &mut PgConnection,
     new_algo: models::NewAlgo,
     new_algo_version: models::NewAlgoVersion,
-) -> Result<(i32, i32), DbError> {
+) -> Result<(models::AlgoVersion, bool), DbError> {
     let new_algo_id = {
         use crate::schema::algos::dsl::*;
 
@@ -94,17 +97,27 @@ pub fn insert_new_algo(
 
     println!("wow, inserted {:?}", new_algo_id);
 
-    let algo_version_id = {
-        use crate::schema::algo_version::dsl::*;
+    use crate::schema::algo_version::dsl::*;
 
-        diesel::insert_into(algo_version)
-            .values((new_algo_version, algo_id.eq(new_algo_id)))
-            .returning(id)
-            .get_result(conn)?
+    let file_sha256 = new_algo_version.sha256.clone();
+    let inserted = diesel::insert_into(algo_version)
+        .values((new_algo_version, algo_id.eq(new_algo_id)))
+        .on_conflict((algo_id, sha256, duplicate_id))
+        .do_nothing()
+        .get_result::<models::AlgoVersion>(conn)
+        .optional()?;
+    let Some(inserted) = inserted else {
+        // The same file was uploaded at the same time
+        let existing = algo_version
+            .filter(algo_id.eq(new_algo_id))
+            .filter(sha256.eq(file_sha256))
+            .filter(duplicate_id.eq(0))
+            .first::<models::AlgoVersion>(conn)?;
+        return Ok((existing, false));
     };
 
-    println!("wow, inserted {:?} and {:?}", new_algo_id, algo_version_id);
-    Ok((new_algo_id, algo_version_id))
+    println!("wow, inserted {:?} and {:?}", new_algo_id, inserted.id);
+    Ok((inserted, true))
 }
 
 pub fn find_all_algos(conn: &mut PgConnection) -> Result<Vec<models::Algo>, DbError> {
@@ -143,17 +156,76 @@ pub fn find_algos(
     Ok(algo_versions)
 }
 
-pub fn get_algo_file(conn: &mut PgConnection, algo_verion_id: i32) -> Result<Vec<u8>, DbError> {
+/// File of an algo version and its hash.
+pub fn get_algo_file(
+    conn: &mut PgConnection,
+    algo_verion_id: i32,
+) -> Result<(Vec<u8>, String), DbError> {
     use crate::schema::algo_version::dsl::*;
 
     let algo_file = algo_version
         .filter(id.eq(algo_verion_id))
-        .select(file)
-        .first::<Vec<u8>>(conn)?;
+        .select((file, sha256))
+        .first::<(Vec<u8>, String)>(conn)?;
 
     Ok(algo_file)
 }
 
+/// Version of a user's algo that has the same file, if it was uploaded before.
+pub fn find_algo_version_by_hash(
+    conn: &mut PgConnection,
+    algo_user_id: String,
+    algo_name: String,
+    file_sha256: String,
+) -> Result<Option<models::AlgoVersion>, DbError> {
+    use crate::schema::{algo_version, algos};
+
+    let version = algo_version::table
+        .inner_join(algos::table)
+        .filter(algos::user_id.eq(algo_user_id))
+        .filter(algos::name.eq(algo_name))
+        .filter(algo_version::sha256.eq(file_sha256))
+        .filter(algo_version::duplicate_id.eq(0))
+        .select(algo_version::all_columns)
+        .first::<models::AlgoVersion>(conn)
+        .optional()?;
+
+    Ok(version)
+}
+
+/// Versions whose smoke test wasn't played yet.
+pub fn find_untested_algo_versions(conn: &mut PgConnection) -> Result<Vec<i32>, DbError> {
+    use crate::schema::algo_version::dsl::*;
+
+    let untested = algo_version
+        .filter(smoke_test.is_null())
+        .order(id)
+        .select(id)
+        .load::<i32>(conn)?;
+
+    Ok(untested)
+}
+
+pub fn update_smoke_test(
+    conn: &mut PgConnection,
+    algo_version_id: i32,
+    new_status: models::AlgoVersionStatus,
+    new_smoke_test: serde_json::Value,
+) -> Result<(), DbError> {
+    use crate::schema::algo_version::dsl::*;
+
+    diesel::update(algo_version)
+        .filter(id.eq(algo_version_id))
+        .set((
+            status.eq(new_status),
+            smoke_test.eq(new_smoke_test),
+            updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
 pub fn find_all_categories(conn: &mut PgConnection) -> Result<Vec<models::Category>, DbError> {
     use crate::schema::categories::dsl::*;
 
@@ -169,7 +241,15 @@ pub fn insert_new_category(
     use crate::schema::categories::dsl::*;
 
     let created_category = diesel::insert_into(categories)
-        .values(&new_category)
+        .values((
+            name.eq(new_category.name),
+            description.eq(new_category.description),
+            description_short.eq(new_category.description_short),
+            game_config.eq(serde_json::to_value(new_category.game_config)?),
+            max_points.eq(new_category.max_points),
+            icon.eq(new_category.icon),
+            deadline_at.eq(new_category.deadline_at),
+        ))
         .on_conflict(id)
         .do_update()
         .set((
@@ -186,6 +266,410 @@ pub fn insert_new_category(
     Ok(created_category)
 }
 
+pub fn find_category(
+    conn: &mut PgConnection,
+    category_id: i32,
+) -> Result<Option<models::Category>, DbError> {
+    use crate::schema::categories::dsl::*;
+
+    let category = categories
+        .filter(id.eq(category_id))
+        .first::<models::Category>(conn)
+        .optional()?;
+
+    Ok(category)
+}
+
+pub fn insert_new_match(
+    conn: &mut PgConnection,
+    new_match: models::NewMatch,
+) -> Result<models::Match, DbError> {
+    use crate::schema::matches::dsl::*;
+
+    let created_match = diesel::insert_into(matches)
+        .values(&new_match)
+        .get_result::<models::Match>(conn)?;
+
+    Ok(created_match)
+}
+
+pub fn find_match(
+    conn: &mut PgConnection,
+    match_id: i32,
+) -> Result<Option<models::Match>, DbError> {
+    use crate::schema::matches::dsl::*;
+
+    let found_match = matches
+        .filter(id.eq(match_id))
+        .first::<models::Match>(conn)
+        .optional()?;
+
+    Ok(found_match)
+}
+
+pub fn find_match_summaries(
+    conn: &mut PgConnection,
+    match_ids: Vec<i32>,
+) -> Result<Vec<models::MatchSummary>, DbError> {
+    use crate::schema::matches::dsl::*;
+
+    let summaries = matches
+        .filter(id.eq_any(match_ids))
+        .select((id, status, result))
+        .load::<models::MatchSummary>(conn)?;
+
+    Ok(summaries)
+}
+
+/// Ids of the matches that were queued or running when the server stopped.
+pub fn find_unfinished_matches(conn: &mut PgConnection) -> Result<Vec<i32>, DbError> {
+    use crate::schema::matches::dsl::*;
+
+    let match_ids = matches
+        .filter(status.eq_any([models::MatchStatus::Queued, models::MatchStatus::Running]))
+        .order(id)
+        .select(id)
+        .load::<i32>(conn)?;
+
+    Ok(match_ids)
+}
+
+pub fn start_match(conn: &mut PgConnection, match_id: i32) -> Result<models::Match, DbError> {
+    use crate::schema::matches::dsl::*;
+
+    let started_match = diesel::update(matches)
+        .filter(id.eq(match_id))
+        .set((
+            status.eq(models::MatchStatus::Running),
+            updated_at.eq(diesel::dsl::now),
+        ))
+        .get_result::<models::Match>(conn)?;
+
+    Ok(started_match)
+}
+
+pub fn finish_match(
+    conn: &mut PgConnection,
+    match_id: i32,
+    match_result: serde_json::Value,
+    match_replay: serde_json::Value,
+) -> Result<(), DbError> {
+    use crate::schema::matches::dsl::*;
+
+    diesel::update(matches)
+        .filter(id.eq(match_id))
+        .set((
+            status.eq(models::MatchStatus::Finished),
+            result.eq(match_result),
+            replay.eq(match_replay),
+            updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn fail_match(
+    conn: &mut PgConnection,
+    match_id: i32,
+    match_error: String,
+) -> Result<(), DbError> {
+    use crate::schema::matches::dsl::*;
+
+    diesel::update(matches)
+        .filter(id.eq(match_id))
+        .set((
+            status.eq(models::MatchStatus::Failed),
+            error.eq(match_error),
+            updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Number of queued or running matches of a category.
+pub fn count_pending_matches(
+    conn: &mut PgConnection,
+    matches_category_id: i32,
+) -> Result<i64, DbError> {
+    use crate::schema::matches::dsl::*;
+
+    let pending = matches
+        .filter(category_id.eq(matches_category_id))
+        .filter(status.eq_any([models::MatchStatus::Queued, models::MatchStatus::Running]))
+        .count()
+        .get_result::<i64>(conn)?;
+
+    Ok(pending)
+}
+
+/// Rating of an algo version in a category, created from `new_rating`
+/// if it has none yet.
+pub fn find_or_create_rating(
+    conn: &mut PgConnection,
+    new_rating: models::NewRating,
+) -> Result<models::Rating, DbError> {
+    use crate::schema::ratings::dsl::*;
+
+    diesel::insert_into(ratings)
+        .values(&new_rating)
+        .on_conflict((category_id, algo_version_id))
+        .do_nothing()
+        .execute(conn)?;
+
+    let found = ratings
+        .filter(category_id.eq(new_rating.category_id))
+        .filter(algo_version_id.eq(new_rating.algo_version_id))
+        .first::<models::Rating>(conn)?;
+
+    Ok(found)
+}
+
+/// Sets a rating after a match and records it in the rating history.
+pub fn update_rating(
+    conn: &mut PgConnection,
+    updated_rating_id: i32,
+    rated_match_id: i32,
+    new_rating: f64,
+) -> Result<(), DbError> {
+    {
+        use crate::schema::ratings::dsl::*;
+
+        diesel::update(ratings)
+            .filter(id.eq(updated_rating_id))
+            .set((
+                rating.eq(new_rating),
+                matches_played.eq(matches_played + 1),
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+    }
+
+    {
+        use crate::schema::rating_history::dsl::*;
+
+        diesel::insert_into(rating_history)
+            .values(&models::NewRatingHistory {
+                rating_id: updated_rating_id,
+                match_id: rated_match_id,
+                rating: new_rating,
+            })
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Ratings of a category from best to worst.
+pub fn find_ladder(
+    conn: &mut PgConnection,
+    ladder_category_id: i32,
+) -> Result<Vec<models::LadderEntry>, DbError> {
+    use crate::schema::{algo_version, algos, ratings};
+
+    let ladder = ratings::table
+        .inner_join(algo_version::table.inner_join(algos::table))
+        .filter(ratings::category_id.eq(ladder_category_id))
+        .order((ratings::rating.desc(), ratings::id))
+        .select((
+            ratings::all_columns,
+            algos::id,
+            algos::user_id,
+            algos::name,
+            algo_version::version,
+        ))
+        .load::<models::LadderEntry>(conn)?;
+
+    Ok(ladder)
+}
+
+pub fn find_rating_history(
+    conn: &mut PgConnection,
+    history_category_id: i32,
+    history_algo_version_id: i32,
+) -> Result<Vec<models::RatingHistory>, DbError> {
+    use crate::schema::{rating_history, ratings};
+
+    let history = rating_history::table
+        .inner_join(ratings::table)
+        .filter(ratings::category_id.eq(history_category_id))
+        .filter(ratings::algo_version_id.eq(history_algo_version_id))
+        .order(rating_history::id)
+        .select(rating_history::all_columns)
+        .load::<models::RatingHistory>(conn)?;
+
+    Ok(history)
+}
+
+/// Owner of the algo an algo version belongs to.
+pub fn find_algo_version_owner(
+    conn: &mut PgConnection,
+    version_id: i32,
+) -> Result<Option<String>, DbError> {
+    use crate::schema::{algo_version, algos};
+
+    let owner = algo_version::table
+        .inner_join(algos::table)
+        .filter(algo_version::id.eq(version_id))
+        .select(algos::user_id)
+        .first::<String>(conn)
+        .optional()?;
+
+    Ok(owner)
+}
+
+/// Owners of the algos a list of algo versions belong to.
+pub fn find_algo_version_owners(
+    conn: &mut PgConnection,
+    version_ids: Vec<i32>,
+) -> Result<Vec<String>, DbError> {
+    use crate::schema::{algo_version, algos};
+
+    let owners = algo_version::table
+        .inner_join(algos::table)
+        .filter(algo_version::id.eq_any(version_ids))
+        .select(algos::user_id)
+        .distinct()
+        .load::<String>(conn)?;
+
+    Ok(owners)
+}
+
+pub fn find_all_tournaments(conn: &mut PgConnection) -> Result<Vec<models::Tournament>, DbError> {
+    use crate::schema::tournaments::dsl::*;
+
+    let tournaments2 = tournaments
+        .order(id)
+        .limit(100)
+        .load::<models::Tournament>(conn)?;
+
+    Ok(tournaments2)
+}
+
+pub fn find_tournament(
+    conn: &mut PgConnection,
+    tournament_id: i32,
+) -> Result<Option<models::Tournament>, DbError> {
+    use crate::schema::tournaments::dsl::*;
+
+    let tournament = tournaments
+        .filter(id.eq(tournament_id))
+        .first::<models::Tournament>(conn)
+        .optional()?;
+
+    Ok(tournament)
+}
+
+/// Tournaments that are running or due to start.
+pub fn find_active_tournaments(
+    conn: &mut PgConnection,
+) -> Result<Vec<models::Tournament>, DbError> {
+    use crate::schema::tournaments::dsl::*;
+
+    let active = tournaments
+        .filter(
+            status.eq(models::TournamentStatus::Running).or(status
+                .eq(models::TournamentStatus::Scheduled)
+                .and(starts_at.le(diesel::dsl::now))),
+        )
+        .order(id)
+        .load::<models::Tournament>(conn)?;
+
+    Ok(active)
+}
+
+pub fn insert_new_tournament(
+    conn: &mut PgConnection,
+    new_tournament: models::NewTournament,
+) -> Result<models::Tournament, DbError> {
+    use crate::schema::tournaments::dsl::*;
+
+    let created_tournament = diesel::insert_into(tournaments)
+        .values(&new_tournament)
+        .get_result::<models::Tournament>(conn)?;
+
+    Ok(created_tournament)
+}
+
+pub fn update_tournament_progress(
+    conn: &mut PgConnection,
+    tournament_id: i32,
+    new_status: models::TournamentStatus,
+    new_current_round: i32,
+) -> Result<(), DbError> {
+    use crate::schema::tournaments::dsl::*;
+
+    diesel::update(tournaments)
+        .filter(id.eq(tournament_id))
+        .set((
+            status.eq(new_status),
+            current_round.eq(new_current_round),
+            updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Enters a user's algo version, replacing the version they entered before.
+pub fn upsert_tournament_entry(
+    conn: &mut PgConnection,
+    new_entry: models::NewTournamentEntry,
+) -> Result<models::TournamentEntry, DbError> {
+    use crate::schema::tournament_entries::dsl::*;
+
+    let entry = diesel::insert_into(tournament_entries)
+        .values(&new_entry)
+        .on_conflict((tournament_id, user_id))
+        .do_update()
+        .set(algo_version_id.eq(excluded(algo_version_id)))
+        .get_result::<models::TournamentEntry>(conn)?;
+
+    Ok(entry)
+}
+
+pub fn find_tournament_entries(
+    conn: &mut PgConnection,
+    entries_tournament_id: i32,
+) -> Result<Vec<models::TournamentEntry>, DbError> {
+    use crate::schema::tournament_entries::dsl::*;
+
+    let entries = tournament_entries
+        .filter(tournament_id.eq(entries_tournament_id))
+        .order(id)
+        .load::<models::TournamentEntry>(conn)?;
+
+    Ok(entries)
+}
+
+pub fn insert_tournament_pairing(
+    conn: &mut PgConnection,
+    new_pairing: models::NewTournamentPairing,
+) -> Result<models::TournamentPairing, DbError> {
+    use crate::schema::tournament_pairings::dsl::*;
+
+    let pairing = diesel::insert_into(tournament_pairings)
+        .values(&new_pairing)
+        .get_result::<models::TournamentPairing>(conn)?;
+
+    Ok(pairing)
+}
+
+pub fn find_tournament_pairings(
+    conn: &mut PgConnection,
+    pairings_tournament_id: i32,
+) -> Result<Vec<models::TournamentPairing>, DbError> {
+    use crate::schema::tournament_pairings::dsl::*;
+
+    let pairings = tournament_pairings
+        .filter(tournament_id.eq(pairings_tournament_id))
+        .order(id)
+        .load::<models::TournamentPairing>(conn)?;
+
+    Ok(pairings)
+}
+
 pub fn insert_new_user_group(
     conn: &mut PgConnection,
     new_user_group: models::NewUserGroup,
@@ -226,3 +710,32 @@ pub fn attach_user_to_user_group(
 
     Ok(u)
 }
+
+pub fn insert_submission(
+    conn: &mut PgConnection,
+    new_submission: models::NewSubmission,
+) -> Result<models::Submission, DbError> {
+    use crate::schema::submissions::dsl::*;
+
+    let submission = diesel::insert_into(submissions)
+        .values(&new_submission)
+        .get_result::<models::Submission>(conn)?;
+
+    Ok(submission)
+}
+
+/// Latest submission of every user to a category.
+pub fn find_official_submissions(
+    conn: &mut PgConnection,
+    submissions_category_id: i32,
+) -> Result<Vec<models::Submission>, DbError> {
+    use crate::schema::submissions::dsl::*;
+
+    let official = submissions
+        .filter(category_id.eq(submissions_category_id))
+        .distinct_on(user_id)
+        .order((user_id, created_at.desc(), id.desc()))
+        .load::<models::Submission>(conn)?;
+
+    Ok(official)
+}
@@ -1,8 +1,14 @@
-use crate::utils::wasm_module::LibInfo;
-use crate::{actions, models, utils, DbPool};
+use crate::utils::match_runner::{self, MatchQueue};
+use crate::utils::rate_limit::RateLimiter;
+use crate::utils::validation::ValidationError;
+use crate::{actions, categories, models, utils, DbPool};
 use actix_multipart::Multipart;
-use actix_web::error::ErrorInternalServerError;
-use actix_web::{get, post, web, Error, HttpResponse};
+use actix_web::error::{
+    ErrorBadRequest, ErrorForbidden, ErrorInternalServerError, ErrorNotFound, ErrorPayloadTooLarge,
+    ErrorTooManyRequests,
+};
+use actix_web::http::header::{ETag, EntityTag, IfNoneMatch};
+use actix_web::{get, post, web, Error, HttpMessage, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -10,12 +16,20 @@ use serde::{Deserialize, Serialize};
 pub struct AlgoJsonResult {
     pub algo_id: i32,
     pub algo_version_id: i32,
+    pub status: models::AlgoVersionStatus,
+    /// Missing until a worker played the version's smoke test
+    pub smoke_test: Option<serde_json::Value>,
+    pub sha256: String,
 }
 
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RunGamePayload {
+    pub category_id: i32,
+    /// Players in seat order, a version may play against itself
     pub algo_versions: Vec<i32>,
+    /// Defaults to the seed of the category's game config
+    pub seed: Option<i32>,
 }
 
 #[get("/")]
@@ -49,84 +63,241 @@ pub(crate) async fn get_algo_versions(
     Ok(web::Json(algo))
 }
 
+/// Queues a match, played and rated like any other. Users have to own one of
+/// the versions, admins may run any, and everyone but admins is rate limited.
 #[post("/run/")]
 pub(crate) async fn run(
-    _user: models::User,
+    user: models::User,
     pool: web::Data<DbPool>,
+    match_queue: web::Data<MatchQueue>,
+    run_limiter: web::Data<RateLimiter>,
     payload: web::Json<RunGamePayload>,
-) -> Result<web::Json<Vec<LibInfo>>, Error> {
-    let algos = payload.algo_versions.clone();
-    let algos_fetched = web::block(move || {
+) -> Result<web::Json<models::Match>, Error> {
+    let payload = payload.into_inner();
+    if payload.algo_versions.is_empty() {
+        return Err(ErrorBadRequest("a match needs at least one algo version"));
+    }
+    let is_admin = matches!(user.role, models::UserRole::Admin);
+
+    let (category, algo_versions_found, owns_a_version) = {
+        let pool = pool.clone();
+        let mut algo_version_ids = payload.algo_versions.clone();
+        algo_version_ids.sort_unstable();
+        algo_version_ids.dedup();
+        let category_id = payload.category_id;
+        let user_id = user.id.clone();
+
+        web::block(move || {
+            let mut conn = pool.get()?;
+            let category = actions::find_category(&mut conn, category_id)?;
+            let found = actions::find_algos(&mut conn, algo_version_ids.clone())?;
+            let owners = actions::find_algo_version_owners(&mut conn, algo_version_ids.clone())?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>((
+                category,
+                found.len() == algo_version_ids.len(),
+                owners.contains(&user_id),
+            ))
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?
+    };
+
+    let category = category.ok_or_else(|| ErrorNotFound("category not found"))?;
+    if !algo_versions_found {
+        return Err(ErrorBadRequest("algo version not found"));
+    }
+    if !is_admin && !owns_a_version {
+        return Err(ErrorForbidden("one of the algo versions has to be yours"));
+    }
+    if !is_admin && !run_limiter.try_hit(&user.id) {
+        return Err(ErrorTooManyRequests("too many matches, try again later"));
+    }
+    let new_match = match_runner::new_match(&category, payload.algo_versions, payload.seed)
+        .map_err(ErrorBadRequest)?;
+    let created = web::block(move || {
         let mut conn = pool.get()?;
-        actions::find_algos(&mut conn, algos)
+        actions::insert_new_match(&mut conn, new_match)
     })
     .await
     .map_err(ErrorInternalServerError)?
     .map_err(ErrorInternalServerError)?;
 
-    let lib_infos = algos_fetched
-        .iter()
-        .map(|x| utils::wasm_module::get_lib_info(&x.file).unwrap())
-        .collect();
+    match_queue.push(created.id);
+
+    Ok(web::Json(created))
+}
 
-    Ok(web::Json(lib_infos))
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadQuery {
+    /// Submits the uploaded version to this category
+    pub category_id: Option<i32>,
 }
 
 #[post("/")]
 pub(crate) async fn create_algo(
     user: models::User,
     pool: web::Data<DbPool>,
+    match_queue: web::Data<MatchQueue>,
+    query: web::Query<UploadQuery>,
     mut payload: Multipart,
 ) -> Result<web::Json<AlgoJsonResult>, Error> {
     use actix_web::{web};
     use futures::{StreamExt, TryStreamExt};
 
+    // Checked first, there's no point in testing a version that can't be
+    // submitted. The version is filled in once it's stored.
+    let submission = match query.category_id {
+        Some(category_id) => {
+            let pool = pool.clone();
+            let category = web::block(move || {
+                let mut conn = pool.get()?;
+                actions::find_category(&mut conn, category_id)
+            })
+            .await
+            .map_err(ErrorInternalServerError)?
+            .map_err(ErrorInternalServerError)?
+            .ok_or_else(|| ErrorNotFound("category not found"))?;
+            Some(categories::new_submission(
+                &user,
+                &category,
+                0,
+                Some(user.id.clone()),
+            )?)
+        }
+        None => None,
+    };
+
     if let Ok(Some(mut field)) = payload.try_next().await {
         let _content_type = field.content_disposition();
 
+        // Checked while it's read, so an oversized upload is never buffered
         let mut data = web::BytesMut::new();
         while let Some(chunk) = field.next().await {
-            data.extend_from_slice(&chunk?);
+            let chunk = chunk?;
+            if data.len() + chunk.len() > utils::wasm_module::MAX_MODULE_SIZE {
+                return Err(ErrorPayloadTooLarge(format!(
+                    "modules may be up to {} bytes",
+                    utils::wasm_module::MAX_MODULE_SIZE
+                )));
+            }
+            data.extend_from_slice(&chunk);
         }
 
         let data = data.to_vec();
 
-        let fid = web::block(move || {
-            let lib_info = utils::wasm_module::get_lib_info(&data).expect("failed to get lib info");
-
-            let new_algo = models::NewAlgo {
-                name: lib_info.name,
-                user_id: user.id.clone(),
-                language: lib_info.language,
-            };
-
-            let new_algo_version = models::NewAlgoVersion {
-                file: data,
-                version: lib_info.version,
-            };
-
-            let mut conn = pool.get()?;
-            actions::insert_new_algo(&mut conn, new_algo, new_algo_version)
+        let (data, lib_info) = web::block(move || {
+            let lib_info = utils::wasm_module::validate(&data).and_then(|()| {
+                utils::wasm_module::get_lib_info(&data).map_err(|problem| ValidationError {
+                    problems: vec![problem],
+                })
+            });
+            (data, lib_info)
         })
         .await
-        .map_err(ErrorInternalServerError)?
         .map_err(ErrorInternalServerError)?;
+        let lib_info = lib_info?;
+
+        // Uploading the same file again is a no-op
+        let sha256 = utils::wasm_module::file_hash(&data);
+        let existing = {
+            let pool = pool.clone();
+            let user_id = user.id.clone();
+            let name = lib_info.name.clone();
+            let sha256 = sha256.clone();
+            web::block(move || {
+                let mut conn = pool.get()?;
+                actions::find_algo_version_by_hash(&mut conn, user_id, name, sha256)
+            })
+            .await
+            .map_err(ErrorInternalServerError)?
+            .map_err(ErrorInternalServerError)?
+        };
+        let result = match existing {
+            Some(existing) => AlgoJsonResult {
+                algo_id: existing.algo_id,
+                algo_version_id: existing.id,
+                status: existing.status,
+                smoke_test: existing.smoke_test,
+                sha256: existing.sha256,
+            },
+            None => store_algo(user, pool.clone(), &match_queue, data, lib_info, sha256).await?,
+        };
+
+        if let Some(submission) = submission {
+            let submission = models::NewSubmission {
+                algo_version_id: result.algo_version_id,
+                ..submission
+            };
+            web::block(move || {
+                let mut conn = pool.get()?;
+                actions::insert_submission(&mut conn, submission)
+            })
+            .await
+            .map_err(ErrorInternalServerError)?
+            .map_err(ErrorInternalServerError)?;
+        }
 
-        return Ok(web::Json(AlgoJsonResult {
-            algo_id: fid.0,
-            algo_version_id: fid.1,
-        }));
+        return Ok(web::Json(result));
     }
 
     Err(ErrorInternalServerError("algo not found"))
 }
 
+/// Stores a new version and queues its smoke test. It stays unverified until
+/// a worker played it. Answers with the stored version if the same file beat
+/// it to it.
+async fn store_algo(
+    user: models::User,
+    pool: web::Data<DbPool>,
+    match_queue: &MatchQueue,
+    data: Vec<u8>,
+    lib_info: utils::wasm_module::LibInfo,
+    sha256: String,
+) -> Result<AlgoJsonResult, Error> {
+    let (stored, inserted) = web::block(move || {
+        let new_algo = models::NewAlgo {
+            name: lib_info.name,
+            user_id: user.id.clone(),
+            language: lib_info.language,
+        };
+
+        let new_algo_version = models::NewAlgoVersion {
+            file: data,
+            version: lib_info.version,
+            status: models::AlgoVersionStatus::Unverified,
+            smoke_test: None,
+            sha256,
+        };
+
+        let mut conn = pool.get()?;
+        actions::insert_new_algo(&mut conn, new_algo, new_algo_version)
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+    if inserted {
+        match_queue.push_smoke_test(stored.id);
+    }
+
+    Ok(AlgoJsonResult {
+        algo_id: stored.algo_id,
+        algo_version_id: stored.id,
+        status: stored.status,
+        smoke_test: stored.smoke_test,
+        sha256: stored.sha256,
+    })
+}
+
+/// Served with the file's SHA-256 as a strong `ETag`, files never change.
 #[get("/file/{algo_id}/")]
 pub(crate) async fn get_algo_file(
+    req: HttpRequest,
     pool: web::Data<DbPool>,
     algo_version_id: web::Path<i32>,
 ) -> Result<HttpResponse, Error> {
-    let algo_file = web::block(move || {
+    let (algo_file, sha256) = web::block(move || {
         let mut conn = pool.get()?;
         actions::get_algo_file(&mut conn, algo_version_id.into_inner())
     })
@@ -134,5 +305,18 @@ pub(crate) async fn get_algo_file(
     .map_err(ErrorInternalServerError)?
     .map_err(ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().body(algo_file))
+    let etag = EntityTag::new_strong(sha256);
+    let cached = req
+        .get_header::<IfNoneMatch>()
+        .map_or(false, |if_none_match| match if_none_match {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Items(tags) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        });
+    if cached {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(ETag(etag))
+            .finish());
+    }
+
+    Ok(HttpResponse::Ok().insert_header(ETag(etag)).body(algo_file))
 }
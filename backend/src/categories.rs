@@ -1,6 +1,41 @@
+use crate::utils::validation::ValidationError;
 use crate::{actions, models, DbPool};
-use actix_web::error::{ErrorForbidden, ErrorInternalServerError};
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound};
 use actix_web::{get, post, web, Error};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmitPayload {
+    pub algo_version_id: i32,
+}
+
+/// Checks that `user` may submit a version owned by `owner` to `category`
+/// now. Admins may submit anyone's version, even after the deadline.
+pub(crate) fn new_submission(
+    user: &models::User,
+    category: &models::Category,
+    algo_version_id: i32,
+    owner: Option<String>,
+) -> Result<models::NewSubmission, Error> {
+    let is_admin = matches!(user.role, models::UserRole::Admin);
+    let owner = match owner {
+        Some(owner) if owner == user.id || is_admin => owner,
+        Some(_) => return Err(ErrorForbidden("algo version belongs to another user")),
+        None => return Err(ErrorNotFound("algo version not found")),
+    };
+    let late = category.is_past_deadline(chrono::Utc::now().naive_utc());
+    if late && !is_admin {
+        return Err(ErrorForbidden("the deadline of this category has passed"));
+    }
+
+    Ok(models::NewSubmission {
+        category_id: category.id,
+        user_id: owner,
+        algo_version_id,
+        late,
+    })
+}
 
 #[get("/")]
 pub(crate) async fn get_categories(
@@ -31,9 +66,15 @@ pub(crate) async fn create_category(
         _ => return Err(ErrorForbidden("not authorized")),
     }
 
+    let new_category = payload.into_inner();
+    let problems = new_category.game_config.validate();
+    if !problems.is_empty() {
+        return Err(ValidationError { problems }.into());
+    }
+
     let category = web::block(move || {
         let mut conn = pool.get()?;
-        actions::insert_new_category(&mut conn, payload.into_inner())
+        actions::insert_new_category(&mut conn, new_category)
     })
     .await
     .map_err(ErrorInternalServerError)?
@@ -44,3 +85,59 @@ pub(crate) async fn create_category(
         Err(err) => Err(ErrorInternalServerError(err)),
     }
 }
+
+/// Official entries of a category, the latest submission of every user.
+#[get("/{category_id}/submissions/")]
+pub(crate) async fn get_submissions(
+    pool: web::Data<DbPool>,
+    category_id: web::Path<i32>,
+) -> Result<web::Json<Vec<models::Submission>>, Error> {
+    let submissions = web::block(move || {
+        let mut conn = pool.get()?;
+        actions::find_official_submissions(&mut conn, category_id.into_inner())
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    Ok(web::Json(submissions))
+}
+
+#[post("/{category_id}/submissions/")]
+pub(crate) async fn submit(
+    user: models::User,
+    pool: web::Data<DbPool>,
+    category_id: web::Path<i32>,
+    payload: web::Json<SubmitPayload>,
+) -> Result<web::Json<models::Submission>, Error> {
+    let category_id = category_id.into_inner();
+    let algo_version_id = payload.algo_version_id;
+
+    let (category, owner) = {
+        let pool = pool.clone();
+        web::block(move || {
+            let mut conn = pool.get()?;
+            let category = actions::find_category(&mut conn, category_id)?;
+            let owner = actions::find_algo_version_owner(&mut conn, algo_version_id)?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>((category, owner))
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?
+    };
+
+    let Some(category) = category else {
+        return Err(ErrorNotFound("category not found"));
+    };
+    let new_submission = new_submission(&user, &category, algo_version_id, owner)?;
+
+    let submission = web::block(move || {
+        let mut conn = pool.get()?;
+        actions::insert_submission(&mut conn, new_submission)
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    Ok(web::Json(submission))
+}
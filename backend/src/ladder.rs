@@ -0,0 +1,98 @@
+use crate::utils::rating::INITIAL_RATING;
+use crate::{actions, models, DbPool};
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound};
+use actix_web::{get, post, web, Error};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoinLadderPayload {
+    pub algo_version_id: i32,
+}
+
+#[get("/{category_id}/")]
+pub(crate) async fn get_ladder(
+    pool: web::Data<DbPool>,
+    category_id: web::Path<i32>,
+) -> Result<web::Json<Vec<models::LadderEntry>>, Error> {
+    let ladder = web::block(move || {
+        let mut conn = pool.get()?;
+        actions::find_ladder(&mut conn, category_id.into_inner())
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    Ok(web::Json(ladder))
+}
+
+/// Puts an algo version on the ladder, matchmaking then plays it.
+#[post("/{category_id}/")]
+pub(crate) async fn join_ladder(
+    user: models::User,
+    pool: web::Data<DbPool>,
+    category_id: web::Path<i32>,
+    payload: web::Json<JoinLadderPayload>,
+) -> Result<web::Json<models::Rating>, Error> {
+    let category_id = category_id.into_inner();
+    let algo_version_id = payload.algo_version_id;
+
+    let (category, owner) = {
+        let pool = pool.clone();
+        web::block(move || {
+            let mut conn = pool.get()?;
+            let category = actions::find_category(&mut conn, category_id)?;
+            let owner = actions::find_algo_version_owner(&mut conn, algo_version_id)?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>((category, owner))
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?
+    };
+
+    let Some(category) = category else {
+        return Err(ErrorNotFound("category not found"));
+    };
+    if category.is_past_deadline(chrono::Utc::now().naive_utc()) {
+        return Err(ErrorForbidden("the deadline of this category has passed"));
+    }
+    match owner {
+        Some(owner) if owner == user.id => (),
+        Some(_) => return Err(ErrorForbidden("algo version belongs to another user")),
+        None => return Err(ErrorNotFound("algo version not found")),
+    }
+
+    let rating = web::block(move || {
+        let mut conn = pool.get()?;
+        actions::find_or_create_rating(
+            &mut conn,
+            models::NewRating {
+                category_id,
+                algo_version_id,
+                rating: INITIAL_RATING,
+            },
+        )
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    Ok(web::Json(rating))
+}
+
+#[get("/{category_id}/{algo_version_id}/history/")]
+pub(crate) async fn get_rating_history(
+    pool: web::Data<DbPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<web::Json<Vec<models::RatingHistory>>, Error> {
+    let (category_id, algo_version_id) = path.into_inner();
+    let history = web::block(move || {
+        let mut conn = pool.get()?;
+        actions::find_rating_history(&mut conn, category_id, algo_version_id)
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    Ok(web::Json(history))
+}
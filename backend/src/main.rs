@@ -20,9 +20,12 @@ mod algos;
 mod auth;
 mod categories;
 mod db;
+mod ladder;
+mod matches;
 mod models;
 mod schema;
 mod tests;
+mod tournaments;
 mod user_groups;
 mod users;
 mod utils;
@@ -46,10 +49,16 @@ async fn main() -> std::io::Result<()> {
         17, 133, 254, 252,
     ];
     let key = Key::from(&k);
+    let match_queue = web::Data::new(utils::match_runner::MatchQueue::start(pool.clone()));
+    utils::tournament::start_scheduler(pool.clone(), match_queue.clone());
+    utils::rating::start_matchmaking(pool.clone(), match_queue.clone());
+    let run_limiter = web::Data::new(utils::rate_limit::RateLimiter::runs_from_env());
 
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(pool.clone()))
+            .app_data(match_queue.clone())
+            .app_data(run_limiter.clone())
             .wrap(Logger::default())
             // TODO real bad
             .wrap(Cors::permissive())
@@ -67,7 +76,9 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::scope("categories")
                     .service(categories::get_categories)
-                    .service(categories::create_category),
+                    .service(categories::create_category)
+                    .service(categories::get_submissions)
+                    .service(categories::submit),
             )
             .service(
                 web::scope("algos")
@@ -77,6 +88,24 @@ async fn main() -> std::io::Result<()> {
                     .service(algos::get_algos)
                     .service(algos::get_algo_file),
             )
+            .service(
+                web::scope("matches")
+                    .service(matches::get_match)
+                    .service(matches::get_match_replay),
+            )
+            .service(
+                web::scope("ladder")
+                    .service(ladder::get_ladder)
+                    .service(ladder::join_ladder)
+                    .service(ladder::get_rating_history),
+            )
+            .service(
+                web::scope("tournaments")
+                    .service(tournaments::get_tournaments)
+                    .service(tournaments::create_tournament)
+                    .service(tournaments::get_tournament)
+                    .service(tournaments::enter_tournament),
+            )
             .service(
                 web::scope("auth")
                     .service(auth::login)
@@ -0,0 +1,41 @@
+use crate::{actions, models, DbPool};
+use actix_web::error::{ErrorInternalServerError, ErrorNotFound};
+use actix_web::{get, web, Error};
+
+#[get("/{match_id}/")]
+pub(crate) async fn get_match(
+    pool: web::Data<DbPool>,
+    match_id: web::Path<i32>,
+) -> Result<web::Json<models::Match>, Error> {
+    let found = web::block(move || {
+        let mut conn = pool.get()?;
+        actions::find_match(&mut conn, match_id.into_inner())
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    match found {
+        Some(found) => Ok(web::Json(found)),
+        None => Err(ErrorNotFound("match not found")),
+    }
+}
+
+#[get("/{match_id}/replay/")]
+pub(crate) async fn get_match_replay(
+    pool: web::Data<DbPool>,
+    match_id: web::Path<i32>,
+) -> Result<web::Json<serde_json::Value>, Error> {
+    let found = web::block(move || {
+        let mut conn = pool.get()?;
+        actions::find_match(&mut conn, match_id.into_inner())
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    match found.and_then(|found| found.replay) {
+        Some(replay) => Ok(web::Json(replay)),
+        None => Err(ErrorNotFound("replay not found")),
+    }
+}
@@ -1,10 +1,18 @@
 use chrono;
 use diesel_derive_enum;
+use runner::config::MatchConfig;
 use serde::{Deserialize, Serialize};
 
 use crate::schema::algo_version;
 use crate::schema::algos;
 use crate::schema::categories;
+use crate::schema::matches;
+use crate::schema::rating_history;
+use crate::schema::ratings;
+use crate::schema::submissions;
+use crate::schema::tournament_entries;
+use crate::schema::tournament_pairings;
+use crate::schema::tournaments;
 use crate::schema::user_groups;
 use crate::schema::users;
 
@@ -55,6 +63,18 @@ pub struct NewAlgo {
     pub language: String,
 }
 
+/// Outcome of the game a version plays against an idle bot when it's uploaded.
+#[derive(diesel_derive_enum::DbEnum, Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[ExistingTypePath = "crate::schema::sql_types::AlgoVersionStatus"]
+pub enum AlgoVersionStatus {
+    /// The test game wasn't played yet, or couldn't be played because of the
+    /// server
+    Unverified,
+    Verified,
+    Broken,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable, Identifiable)]
 #[table_name = "algo_version"]
 #[serde(rename_all = "camelCase")]
@@ -66,6 +86,14 @@ pub struct AlgoVersion {
     pub file: Vec<u8>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    pub status: AlgoVersionStatus,
+    pub smoke_test: Option<serde_json::Value>,
+    /// Hex SHA-256 of the file, unique per algo except for `duplicate_id`.
+    pub sha256: String,
+    /// 0, or the version's own id for files uploaded again before versions
+    /// were hashed. Only versions with 0 are found by their hash.
+    #[serde(skip)]
+    pub duplicate_id: i32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
@@ -74,6 +102,9 @@ pub struct AlgoVersion {
 pub struct NewAlgoVersion {
     pub version: String,
     pub file: Vec<u8>,
+    pub status: AlgoVersionStatus,
+    pub smoke_test: Option<serde_json::Value>,
+    pub sha256: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable, Identifiable)]
@@ -92,19 +123,246 @@ pub struct Category {
     pub deadline_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+/// Category posted by an admin. The game config is stored as JSON with the
+/// defaults filled in, so games don't depend on them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-#[table_name = "categories"]
 pub struct NewCategory {
     pub name: String,
     pub description: String,
     pub description_short: String,
-    pub game_config: serde_json::Value,
+    pub game_config: MatchConfig,
     pub max_points: i32,
     pub icon: CategoryIcon,
     pub deadline_at: Option<chrono::NaiveDateTime>,
 }
 
+impl Category {
+    /// Whether submissions are closed, only admins may still submit.
+    pub fn is_past_deadline(&self, now: chrono::NaiveDateTime) -> bool {
+        self.deadline_at.map_or(false, |deadline| deadline < now)
+    }
+}
+
+/// An algo version submitted to a category. A user's latest submission is
+/// their official entry, and since only admins can submit after the deadline,
+/// the entry is frozen once it passed.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "submissions"]
+pub struct Submission {
+    pub id: i32,
+    pub category_id: i32,
+    pub user_id: String,
+    pub algo_version_id: i32,
+    /// Submitted by an admin after the deadline
+    pub late: bool,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "submissions"]
+pub struct NewSubmission {
+    pub category_id: i32,
+    pub user_id: String,
+    pub algo_version_id: i32,
+    pub late: bool,
+}
+
+#[derive(diesel_derive_enum::DbEnum, Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[ExistingTypePath = "crate::schema::sql_types::MatchStatus"]
+pub enum MatchStatus {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "matches"]
+pub struct Match {
+    pub id: i32,
+    pub category_id: i32,
+    pub game_config: serde_json::Value,
+    pub seed: i32,
+    /// Players in seat order
+    pub algo_version_ids: Vec<i32>,
+    pub status: MatchStatus,
+    pub result: Option<serde_json::Value>,
+    /// Served separately, it's much bigger than the rest
+    #[serde(skip_serializing)]
+    pub replay: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+/// Status and result of a match, without its config and replay.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchSummary {
+    pub id: i32,
+    pub status: MatchStatus,
+    pub result: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "matches"]
+pub struct NewMatch {
+    pub category_id: i32,
+    pub game_config: serde_json::Value,
+    pub seed: i32,
+    pub algo_version_ids: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "ratings"]
+pub struct Rating {
+    pub id: i32,
+    pub category_id: i32,
+    pub algo_version_id: i32,
+    pub rating: f64,
+    pub matches_played: i32,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "ratings"]
+pub struct NewRating {
+    pub category_id: i32,
+    pub algo_version_id: i32,
+    pub rating: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "rating_history"]
+pub struct RatingHistory {
+    pub id: i32,
+    pub rating_id: i32,
+    pub match_id: i32,
+    pub rating: f64,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "rating_history"]
+pub struct NewRatingHistory {
+    pub rating_id: i32,
+    pub match_id: i32,
+    pub rating: f64,
+}
+
+/// A rating with the algo version it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable)]
+#[serde(rename_all = "camelCase")]
+pub struct LadderEntry {
+    #[serde(flatten)]
+    pub rating: Rating,
+    pub algo_id: i32,
+    pub user_id: String,
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(diesel_derive_enum::DbEnum, Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[ExistingTypePath = "crate::schema::sql_types::TournamentFormat"]
+pub enum TournamentFormat {
+    RoundRobin,
+    Swiss,
+    SingleElimination,
+}
+
+#[derive(diesel_derive_enum::DbEnum, Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[ExistingTypePath = "crate::schema::sql_types::TournamentStatus"]
+pub enum TournamentStatus {
+    Scheduled,
+    Running,
+    Finished,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "tournaments"]
+pub struct Tournament {
+    pub id: i32,
+    pub category_id: i32,
+    pub name: String,
+    pub format: TournamentFormat,
+    /// Rounds of a Swiss tournament, other formats ignore it
+    pub rounds_count: Option<i32>,
+    pub status: TournamentStatus,
+    /// Latest round with pairings, 0 before the tournament started
+    pub current_round: i32,
+    pub starts_at: chrono::NaiveDateTime,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "tournaments"]
+pub struct NewTournament {
+    pub category_id: i32,
+    pub name: String,
+    pub format: TournamentFormat,
+    pub rounds_count: Option<i32>,
+    /// Entries are accepted until then
+    pub starts_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "tournament_entries"]
+pub struct TournamentEntry {
+    pub id: i32,
+    pub tournament_id: i32,
+    pub user_id: String,
+    pub algo_version_id: i32,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "tournament_entries"]
+pub struct NewTournamentEntry {
+    pub tournament_id: i32,
+    pub user_id: String,
+    pub algo_version_id: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "tournament_pairings"]
+pub struct TournamentPairing {
+    pub id: i32,
+    pub tournament_id: i32,
+    pub round: i32,
+    /// Entries in seat order, a single entry is a bye
+    pub entry_ids: Vec<i32>,
+    pub match_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "tournament_pairings"]
+pub struct NewTournamentPairing {
+    pub tournament_id: i32,
+    pub round: i32,
+    pub entry_ids: Vec<i32>,
+    pub match_id: Option<i32>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable, Identifiable)]
 #[serde(rename_all = "camelCase")]
 #[table_name = "user_groups"]
@@ -1,16 +1,35 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "algo_version_status"))]
+    pub struct AlgoVersionStatus;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "category_icon"))]
     pub struct CategoryIcon;
 
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "match_status"))]
+    pub struct MatchStatus;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tournament_format"))]
+    pub struct TournamentFormat;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tournament_status"))]
+    pub struct TournamentStatus;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "user_role"))]
     pub struct UserRole;
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::AlgoVersionStatus;
+
     algo_version (id) {
         id -> Int4,
         algo_id -> Int4,
@@ -18,6 +37,10 @@ diesel::table! {
         file -> Bytea,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        status -> AlgoVersionStatus,
+        smoke_test -> Nullable<Jsonb>,
+        sha256 -> Varchar,
+        duplicate_id -> Int4,
     }
 }
 
@@ -48,6 +71,97 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::MatchStatus;
+
+    matches (id) {
+        id -> Int4,
+        category_id -> Int4,
+        game_config -> Jsonb,
+        seed -> Int4,
+        algo_version_ids -> Array<Int4>,
+        status -> MatchStatus,
+        result -> Nullable<Jsonb>,
+        replay -> Nullable<Jsonb>,
+        error -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    rating_history (id) {
+        id -> Int4,
+        rating_id -> Int4,
+        match_id -> Int4,
+        rating -> Float8,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    ratings (id) {
+        id -> Int4,
+        category_id -> Int4,
+        algo_version_id -> Int4,
+        rating -> Float8,
+        matches_played -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tournament_entries (id) {
+        id -> Int4,
+        tournament_id -> Int4,
+        user_id -> Varchar,
+        algo_version_id -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    submissions (id) {
+        id -> Int4,
+        category_id -> Int4,
+        user_id -> Varchar,
+        algo_version_id -> Int4,
+        late -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tournament_pairings (id) {
+        id -> Int4,
+        tournament_id -> Int4,
+        round -> Int4,
+        entry_ids -> Array<Int4>,
+        match_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::TournamentFormat;
+    use super::sql_types::TournamentStatus;
+
+    tournaments (id) {
+        id -> Int4,
+        category_id -> Int4,
+        name -> Varchar,
+        format -> TournamentFormat,
+        rounds_count -> Nullable<Int4>,
+        status -> TournamentStatus,
+        current_round -> Int4,
+        starts_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     user_groups (id) {
         id -> Int4,
@@ -72,12 +186,33 @@ diesel::table! {
 
 diesel::joinable!(algo_version -> algos (algo_id));
 diesel::joinable!(algos -> users (user_id));
+diesel::joinable!(matches -> categories (category_id));
+diesel::joinable!(rating_history -> matches (match_id));
+diesel::joinable!(rating_history -> ratings (rating_id));
+diesel::joinable!(ratings -> algo_version (algo_version_id));
+diesel::joinable!(ratings -> categories (category_id));
+diesel::joinable!(submissions -> algo_version (algo_version_id));
+diesel::joinable!(submissions -> categories (category_id));
+diesel::joinable!(submissions -> users (user_id));
+diesel::joinable!(tournament_entries -> algo_version (algo_version_id));
+diesel::joinable!(tournament_entries -> tournaments (tournament_id));
+diesel::joinable!(tournament_entries -> users (user_id));
+diesel::joinable!(tournament_pairings -> matches (match_id));
+diesel::joinable!(tournament_pairings -> tournaments (tournament_id));
+diesel::joinable!(tournaments -> categories (category_id));
 diesel::joinable!(users -> user_groups (user_group_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     algo_version,
     algos,
     categories,
+    matches,
+    rating_history,
+    ratings,
+    submissions,
+    tournament_entries,
+    tournament_pairings,
+    tournaments,
     user_groups,
     users,
 );
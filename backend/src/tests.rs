@@ -1,9 +1,24 @@
 #[cfg(test)]
 mod tests {
     use crate::models::{
-        Algo, Category, CategoryIcon, NewCategory, NewUserGroup, UserGroup, UserRole,
+        Algo, AlgoVersionStatus, Category, CategoryIcon, LadderEntry, Match, MatchStatus, NewAlgo,
+        NewAlgoVersion, NewCategory, NewTournament, NewUserGroup, Submission, Tournament,
+        TournamentEntry, TournamentFormat, TournamentPairing, TournamentStatus, UserGroup,
+        UserRole,
+    };
+    use crate::utils::match_runner::{self, MatchQueue};
+    use crate::utils::rate_limit::RateLimiter;
+    use crate::categories::SubmitPayload;
+    use crate::ladder::JoinLadderPayload;
+    use crate::tournaments::{EnterTournamentPayload, TournamentDetails};
+    use crate::utils::rating::elo_updates;
+    use crate::utils::tournament::{next_rounds, PairingOutcome};
+    use crate::utils::wasm_module;
+    use runner::config::MatchConfig;
+    use runner::ffi::{Position, Robot};
+    use crate::{
+        actions, algos, auth, categories, ladder, matches, tournaments, user_groups, users, DbPool,
     };
-    use crate::{algos, auth, categories, user_groups, users, DbPool};
     use actix_cors::Cors;
     use actix_identity::IdentityMiddleware;
     
@@ -12,7 +27,7 @@ mod tests {
     use std::{env, fs};
 
     
-    use actix_http::{header, HttpMessage, Request};
+    use actix_http::{header, HttpMessage, Request, StatusCode};
     
     use actix_session::{storage, SessionMiddleware};
     use actix_web::body::MessageBody;
@@ -23,7 +38,6 @@ mod tests {
     use serde_json::json;
 
     use crate::algos::{AlgoJsonResult, RunGamePayload};
-    use crate::utils::wasm_module::LibInfo;
     use actix_web::test::TestRequest;
     use actix_web::{dev as ax_dev, Error as AxError};
     use diesel::{sql_query, Connection, PgConnection, RunQueryDsl};
@@ -66,6 +80,8 @@ mod tests {
         let key = Key::from(&k);
         App::new()
                 .app_data(web::Data::new($pool.clone()))
+                .app_data(web::Data::new(MatchQueue::start($pool.clone())))
+                .app_data(web::Data::new(RateLimiter::runs_from_env()))
                 .wrap(Logger::default())
                 // TODO real bad
                 .wrap(Cors::permissive())
@@ -83,7 +99,9 @@ mod tests {
                 .service(
                     web::scope("categories")
                         .service(categories::get_categories)
-                        .service(categories::create_category),
+                        .service(categories::create_category)
+                        .service(categories::get_submissions)
+                        .service(categories::submit),
                 )
                 .service(
                     web::scope("algos")
@@ -93,6 +111,24 @@ mod tests {
                         .service(algos::get_algos)
                         .service(algos::get_algo_file),
                 )
+                .service(
+                    web::scope("matches")
+                        .service(matches::get_match)
+                        .service(matches::get_match_replay),
+                )
+                .service(
+                    web::scope("ladder")
+                        .service(ladder::get_ladder)
+                        .service(ladder::join_ladder)
+                        .service(ladder::get_rating_history),
+                )
+                .service(
+                    web::scope("tournaments")
+                        .service(tournaments::get_tournaments)
+                        .service(tournaments::create_tournament)
+                        .service(tournaments::get_tournament)
+                        .service(tournaments::enter_tournament),
+                )
                 .service(
                     web::scope("auth")
                         .service(auth::login)
@@ -137,6 +173,51 @@ mod tests {
         cookie.into_owned()
     }
 
+    async fn create_category(
+        app: &impl ax_dev::Service<
+            Request,
+            Response = actix_web::dev::ServiceResponse<impl MessageBody>,
+            Error = AxError,
+        >,
+        cookie: Cookie<'static>,
+    ) -> Category {
+        let new_category = NewCategory {
+            name: "test".to_string(),
+            description: "test".to_string(),
+            game_config: MatchConfig::default(),
+            deadline_at: None,
+            description_short: "test".to_string(),
+            icon: CategoryIcon::Crown,
+            max_points: 100,
+        };
+        let req = TestRequest::post()
+            .uri("/categories")
+            .set_json(new_category)
+            .cookie(cookie)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "Failed to create category");
+        test::read_body_json(resp).await
+    }
+
+    /// Category whose deadline passed a day ago.
+    fn closed_category(dead_drop: &DeadDrop) -> Category {
+        let mut conn = dead_drop.pool.get().unwrap();
+        actions::insert_new_category(
+            &mut conn,
+            NewCategory {
+                name: "closed".to_string(),
+                description: "closed".to_string(),
+                game_config: MatchConfig::default(),
+                deadline_at: Some(chrono::Utc::now().naive_utc() - chrono::Duration::days(1)),
+                description_short: "closed".to_string(),
+                icon: CategoryIcon::Crown,
+                max_points: 100,
+            },
+        )
+        .unwrap()
+    }
+
     pub async fn init_test() -> (
         impl ax_dev::Service<
             Request,
@@ -166,7 +247,7 @@ mod tests {
         let new_category = NewCategory {
             name: "test".to_string(),
             description: "test".to_string(),
-            game_config: json!({}),
+            game_config: MatchConfig::default(),
             deadline_at: None,
             description_short: "test".to_string(),
             icon: CategoryIcon::Crown,
@@ -198,7 +279,7 @@ mod tests {
         let new_category = NewCategory {
             name: "test".to_string(),
             description: "test".to_string(),
-            game_config: json!({}),
+            game_config: MatchConfig::default(),
             deadline_at: None,
             description_short: "test".to_string(),
             icon: CategoryIcon::Crown,
@@ -446,6 +527,12 @@ mod tests {
         println!("{:?}", resp);
         assert_eq!(resp.algo_id, 1, "Algo id should be 1");
         assert_eq!(resp.algo_version_id, 1, "Algo version id should be 1");
+        assert_eq!(
+            resp.status,
+            AlgoVersionStatus::Unverified,
+            "Version should wait for its smoke test"
+        );
+        assert!(resp.smoke_test.is_none(), "Smoke test should be queued");
     }
 
     #[actix_web::test]
@@ -512,7 +599,7 @@ mod tests {
     }
 
     #[actix_web::test]
-    async fn cant_upload_same_algo_version() {
+    async fn uploading_same_file_returns_existing_version() {
         let (app, _dead_drop) = init_test().await;
         let cookie = login(&app, UserRole::User).await;
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -565,8 +652,19 @@ mod tests {
             .to_request();
         let resp = test::call_service(&app, req).await;
         assert!(
-            !resp.status().is_success(),
-            "Should not upload algo successfully"
+            resp.status().is_success(),
+            "Should accept the same file again"
+        );
+        let resp: AlgoJsonResult = test::read_body_json(resp).await;
+        assert_eq!(resp.algo_id, 1, "Algo id should be 1");
+        assert_eq!(
+            resp.algo_version_id, 1,
+            "Should return the existing version instead of a new one"
+        );
+        assert_eq!(
+            resp.sha256,
+            wasm_module::file_hash(&file),
+            "Should return the hash of the file"
         );
     }
 
@@ -594,16 +692,89 @@ mod tests {
             ))
             .to_request();
         let resp = test::call_service(&app, req).await;
-        assert!(
-            !resp.status().is_success(),
+        assert_eq!(
+            resp.status(),
+            StatusCode::BAD_REQUEST,
             "Should not upload algo successfully"
         );
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(
+            body["problems"]
+                .as_array()
+                .unwrap()
+                .contains(&json!("missing export `get_lib_info`")),
+            "Should list the missing export"
+        );
     }
 
     #[actix_web::test]
-    async fn can_run_algo() {
+    async fn cant_upload_invalid_module() {
+        let (app, _dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::User).await;
+
+        let file_with_multipart = [
+            "------WebKitFormBoundaryMdWtRXJ0UHe7KQZL\r\nContent-Disposition: form-data; name=\"file\"; filename=\"bot.wasm\"\r\nContent-Type: application/wasm\r\n\r\n".as_bytes(),
+            "not a wasm module".as_bytes(),
+            "\r\n------WebKitFormBoundaryMdWtRXJ0UHe7KQZL--\r\n".as_bytes()
+        ].concat();
+
+        let req = TestRequest::post()
+            .uri("/algos/")
+            .cookie(cookie.clone())
+            .set_payload(file_with_multipart)
+            .append_header((
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=----WebKitFormBoundaryMdWtRXJ0UHe7KQZL",
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::BAD_REQUEST,
+            "Should not upload an invalid module"
+        );
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["problems"].as_array().map(|problems| problems.len()),
+            Some(1),
+            "Should explain why the module is invalid"
+        );
+    }
+
+    #[actix_web::test]
+    async fn cant_upload_oversized_module() {
         let (app, _dead_drop) = init_test().await;
         let cookie = login(&app, UserRole::User).await;
+        let file = vec![0u8; wasm_module::MAX_MODULE_SIZE + 1];
+
+        let file_with_multipart = [
+            "------WebKitFormBoundaryMdWtRXJ0UHe7KQZL\r\nContent-Disposition: form-data; name=\"file\"; filename=\"bot.wasm\"\r\nContent-Type: application/wasm\r\n\r\n".as_bytes(),
+            file.as_slice(),
+            "\r\n------WebKitFormBoundaryMdWtRXJ0UHe7KQZL--\r\n".as_bytes()
+        ].concat();
+
+        let req = TestRequest::post()
+            .uri("/algos/")
+            .cookie(cookie.clone())
+            .set_payload(file_with_multipart)
+            .append_header((
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=----WebKitFormBoundaryMdWtRXJ0UHe7KQZL",
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Should not upload a module over the size limit"
+        );
+    }
+
+    #[actix_web::test]
+    async fn can_run_algo() {
+        let (app, _dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::Admin).await;
+        create_category(&app, cookie.clone()).await;
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         d.push("__mock__/csharp.wasm");
         let file = fs::read(d).unwrap();
@@ -634,7 +805,9 @@ mod tests {
         assert_eq!(resp.algo_version_id, 1, "Algo version id should be 1");
 
         let run_game_payload = RunGamePayload {
+            category_id: 1,
             algo_versions: vec![resp.algo_version_id],
+            seed: None,
         };
         let req = TestRequest::post()
             .uri("/algos/run")
@@ -643,18 +816,88 @@ mod tests {
             .to_request();
         let resp = test::call_service(&app, req).await;
         assert!(resp.status().is_success(), "Should run algo successfully");
-        let resp: Vec<LibInfo> = test::read_body_json(resp).await;
-        assert_eq!(resp.len(), 1, "Should return 1 lib info");
-        assert_eq!(resp[0].name, "My Library", "Should return correct lib info");
-        assert_eq!(resp[0].version, "1.2.5", "Should return correct lib info");
-        assert_eq!(resp[0].language, "csharp", "Should return correct lib info");
+        let resp: Match = test::read_body_json(resp).await;
+        assert_eq!(resp.id, 1, "Match id should be 1");
+        assert_eq!(resp.status, MatchStatus::Queued, "Match should be queued");
+        assert_eq!(resp.algo_version_ids, vec![1], "Match should have 1 player");
+        assert_eq!(resp.seed, 123, "Seed should come from the game config");
+
+        let req = TestRequest::default().uri("/matches/1/").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "Failed to retrieve match");
+        let resp: Match = test::read_body_json(resp).await;
+        assert_eq!(resp.category_id, 1, "Match should belong to category 1");
+    }
+
+    #[actix_web::test]
+    async fn user_cant_run_others_algos() {
+        let (app, dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::User).await;
+
+        let mut conn = dead_drop.pool.get().unwrap();
+        actions::insert_new_category(
+            &mut conn,
+            NewCategory {
+                name: "test".to_string(),
+                description: "test".to_string(),
+                game_config: MatchConfig::default(),
+                deadline_at: None,
+                description_short: "test".to_string(),
+                icon: CategoryIcon::Crown,
+                max_points: 100,
+            },
+        )
+        .unwrap();
+        actions::insert_new_user(
+            &mut conn,
+            "2".to_string(),
+            "https://google.com".to_string(),
+            "other".to_string(),
+            None,
+        )
+        .unwrap();
+        let (algo_version, _) = actions::insert_new_algo(
+            &mut conn,
+            NewAlgo {
+                user_id: "2".to_string(),
+                name: "other".to_string(),
+                language: "csharp".to_string(),
+            },
+            NewAlgoVersion {
+                version: "1.0.0".to_string(),
+                file: vec![],
+                status: AlgoVersionStatus::Verified,
+                smoke_test: None,
+                sha256: wasm_module::file_hash(&[]),
+            },
+        )
+        .unwrap();
+
+        let run_game_payload = RunGamePayload {
+            category_id: 1,
+            algo_versions: vec![algo_version.id],
+            seed: None,
+        };
+        let req = TestRequest::post()
+            .uri("/algos/run")
+            .cookie(cookie)
+            .set_json(run_game_payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::FORBIDDEN,
+            "User should only run matches with their own versions"
+        );
     }
 
     #[actix_web::test]
     async fn cant_run_non_existent_algo() {
         let (app, _dead_drop) = init_test().await;
         let run_game_payload = RunGamePayload {
+            category_id: 1,
             algo_versions: vec![1],
+            seed: None,
         };
 
         let req = TestRequest::post()
@@ -668,7 +911,8 @@ mod tests {
     #[actix_web::test]
     async fn can_run_multiple_algos() {
         let (app, _dead_drop) = init_test().await;
-        let cookie = login(&app, UserRole::User).await;
+        let cookie = login(&app, UserRole::Admin).await;
+        create_category(&app, cookie.clone()).await;
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         d.push("__mock__/csharp.wasm");
         let file = fs::read(d).unwrap();
@@ -726,7 +970,9 @@ mod tests {
         assert_eq!(resp.algo_version_id, 2, "Algo version id should be 1");
 
         let run_game_payload = RunGamePayload {
+            category_id: 1,
             algo_versions: vec![1, 2],
+            seed: Some(42),
         };
         let req = TestRequest::post()
             .uri("/algos/run")
@@ -735,14 +981,378 @@ mod tests {
             .to_request();
         let resp = test::call_service(&app, req).await;
         assert!(resp.status().is_success(), "Should run algo successfully");
-        let resp: Vec<LibInfo> = test::read_body_json(resp).await;
-        assert_eq!(resp.len(), 2, "Should return 2 lib infos");
-        assert_eq!(resp[0].name, "My Library", "Should return correct lib info");
-        assert_eq!(resp[0].version, "1.2.5", "Should return correct lib info");
-        assert_eq!(resp[0].language, "csharp", "Should return correct lib info");
-        assert_eq!(resp[1].name, "My Library", "Should return correct lib info");
-        assert_eq!(resp[1].version, "1.3.5", "Should return correct lib info");
-        assert_eq!(resp[1].language, "csharp", "Should return correct lib info");
+        let resp: Match = test::read_body_json(resp).await;
+        assert_eq!(resp.algo_version_ids, vec![1, 2], "Match should have 2 players");
+        assert_eq!(resp.seed, 42, "Seed should come from the payload");
+    }
+
+    #[actix_web::test]
+    async fn cant_run_in_non_existent_category() {
+        let (app, _dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::User).await;
+        let run_game_payload = RunGamePayload {
+            category_id: 1,
+            algo_versions: vec![1],
+            seed: None,
+        };
+
+        let req = TestRequest::post()
+            .uri("/algos/run")
+            .cookie(cookie.clone())
+            .set_json(run_game_payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404, "Should not run without a category");
+    }
+
+    #[actix_web::test]
+    async fn admin_can_create_tournament() {
+        let (app, _dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::Admin).await;
+        create_category(&app, cookie.clone()).await;
+
+        let new_tournament = NewTournament {
+            category_id: 1,
+            name: "test".to_string(),
+            format: TournamentFormat::Swiss,
+            rounds_count: Some(3),
+            starts_at: chrono::Utc::now().naive_utc() + chrono::Duration::days(1),
+        };
+        let req = TestRequest::post()
+            .uri("/tournaments/")
+            .cookie(cookie.clone())
+            .set_json(new_tournament)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "Failed to create tournament");
+        let resp: Tournament = test::read_body_json(resp).await;
+        assert_eq!(resp.id, 1, "Tournament id should be 1");
+        assert_eq!(resp.status, TournamentStatus::Scheduled, "Tournament should be scheduled");
+
+        let req = TestRequest::default().uri("/tournaments/1/").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "Failed to retrieve tournament");
+        let resp: TournamentDetails = test::read_body_json(resp).await;
+        assert_eq!(resp.tournament.format, TournamentFormat::Swiss, "Format should be swiss");
+        assert!(resp.entries.is_empty(), "Tournament should have no entries");
+        assert!(resp.standings.is_empty(), "Standings should be empty");
+    }
+
+    #[actix_web::test]
+    async fn user_cant_create_tournament() {
+        let (app, _dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::User).await;
+
+        let new_tournament = NewTournament {
+            category_id: 1,
+            name: "test".to_string(),
+            format: TournamentFormat::RoundRobin,
+            rounds_count: None,
+            starts_at: chrono::Utc::now().naive_utc() + chrono::Duration::days(1),
+        };
+        let req = TestRequest::post()
+            .uri("/tournaments/")
+            .cookie(cookie.clone())
+            .set_json(new_tournament)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(
+            !resp.status().is_success(),
+            "User should not be able to create tournament"
+        );
+    }
+
+    #[actix_web::test]
+    async fn cant_enter_non_existent_tournament() {
+        let (app, _dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::User).await;
+
+        let req = TestRequest::post()
+            .uri("/tournaments/1/entries/")
+            .cookie(cookie.clone())
+            .set_json(EnterTournamentPayload { algo_version_id: 1 })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404, "Tournament should not exist");
+    }
+
+    #[actix_web::test]
+    async fn ladder_should_be_empty() {
+        let (app, _dead_drop) = init_test().await;
+
+        let req = TestRequest::default().uri("/ladder/1/").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "Failed to retrieve ladder");
+        let resp: Vec<LadderEntry> = test::read_body_json(resp).await;
+        assert!(resp.is_empty(), "Ladder should be empty");
+    }
+
+    #[actix_web::test]
+    async fn cant_join_ladder_of_non_existent_category() {
+        let (app, _dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::User).await;
+
+        let req = TestRequest::post()
+            .uri("/ladder/1/")
+            .cookie(cookie.clone())
+            .set_json(JoinLadderPayload { algo_version_id: 1 })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404, "Category should not exist");
+    }
+
+    #[actix_web::test]
+    async fn cant_create_category_with_invalid_game_config() {
+        let (app, _dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::Admin).await;
+
+        let new_category = json!({
+            "name": "test",
+            "description": "test",
+            "descriptionShort": "test",
+            "gameConfig": { "widht": 16, "roundsCount": 0, "playersCount": 0 },
+            "maxPoints": 100,
+            "icon": "Crown",
+            "deadlineAt": null,
+        });
+        let req = TestRequest::post()
+            .uri("/categories")
+            .set_json(new_category)
+            .cookie(cookie)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400, "Should reject an invalid game config");
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["problems"].as_array().map(|problems| problems.len()),
+            Some(3),
+            "Should list the typo and both values out of bounds"
+        );
+    }
+
+    #[actix_web::test]
+    async fn can_play_category_with_scenario() {
+        let (app, _dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::Admin).await;
+
+        let scenario = "robot 0 0 0 100\nrobot 1 2 0 100\nstation 0 2 200 5\n";
+        let new_category = NewCategory {
+            name: "test".to_string(),
+            description: "test".to_string(),
+            game_config: MatchConfig {
+                scenario: Some(scenario.to_string()),
+                ..MatchConfig::default()
+            },
+            deadline_at: None,
+            description_short: "test".to_string(),
+            icon: CategoryIcon::Crown,
+            max_points: 100,
+        };
+        let req = TestRequest::post()
+            .uri("/categories")
+            .set_json(new_category)
+            .cookie(cookie)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "Should store the scenario");
+        let category: Category = test::read_body_json(resp).await;
+        assert_eq!(category.game_config["scenario"], scenario);
+
+        // Played the way the match workers play it, with the stored config
+        let config: MatchConfig = serde_json::from_value(category.game_config).unwrap();
+        let core = fs::read(match_runner::core_wasm_path())
+            .expect("CORE_WASM_PATH should point at a built core");
+        let idle_bot = runner::IDLE_BOT.as_bytes().to_vec();
+        let outcome = runner::run_match(&core, &[idle_bot.clone(), idle_bot], &config).unwrap();
+
+        let initial_map = &outcome.replay.initial_map;
+        assert_eq!(
+            initial_map.robots,
+            vec![
+                Robot {
+                    position: Position { q: 0, r: 0 },
+                    energy: 100,
+                    owner: 0
+                },
+                Robot {
+                    position: Position { q: 2, r: 0 },
+                    energy: 100,
+                    owner: 1
+                },
+            ],
+            "Robots should come from the scenario"
+        );
+        assert_eq!(initial_map.energy_stations.len(), 1);
+        assert_eq!(
+            initial_map.energy_stations[0].position,
+            Position { q: 0, r: 2 }
+        );
+    }
+
+    #[actix_web::test]
+    async fn cant_submit_to_non_existent_category() {
+        let (app, _dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::User).await;
+
+        let req = TestRequest::post()
+            .uri("/categories/1/submissions/")
+            .cookie(cookie.clone())
+            .set_json(SubmitPayload { algo_version_id: 1 })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404, "Category should not exist");
+    }
+
+    #[actix_web::test]
+    async fn cant_upload_after_deadline() {
+        let (app, dead_drop) = init_test().await;
+        // Inserted directly, a user keeps the role they first logged in with
+        let category = closed_category(&dead_drop);
+
+        let req = TestRequest::get()
+            .uri(&format!("/categories/{}/submissions/", category.id))
+            .to_request();
+        let resp: Vec<Submission> = test::call_and_read_body_json(&app, req).await;
+        assert!(resp.is_empty(), "Category should have no submissions");
+
+        let cookie = login(&app, UserRole::User).await;
+        let req = TestRequest::post()
+            .uri(&format!("/algos/?categoryId={}", category.id))
+            .cookie(cookie.clone())
+            .set_payload("invalid")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.status(),
+            403,
+            "Should not accept submissions after the deadline"
+        );
+    }
+
+    #[actix_web::test]
+    async fn cant_enter_tournament_after_deadline_without_submission() {
+        let (app, dead_drop) = init_test().await;
+        let cookie = login(&app, UserRole::User).await;
+        let category = closed_category(&dead_drop);
+
+        let mut conn = dead_drop.pool.get().unwrap();
+        let tournament = actions::insert_new_tournament(
+            &mut conn,
+            NewTournament {
+                category_id: category.id,
+                name: "test".to_string(),
+                format: TournamentFormat::RoundRobin,
+                rounds_count: None,
+                starts_at: chrono::Utc::now().naive_utc() + chrono::Duration::days(1),
+            },
+        )
+        .unwrap();
+        let (algo_version, _) = actions::insert_new_algo(
+            &mut conn,
+            NewAlgo {
+                user_id: "1".to_string(),
+                name: "test".to_string(),
+                language: "csharp".to_string(),
+            },
+            NewAlgoVersion {
+                version: "1.0.0".to_string(),
+                file: vec![],
+                status: AlgoVersionStatus::Verified,
+                smoke_test: None,
+                sha256: wasm_module::file_hash(&[]),
+            },
+        )
+        .unwrap();
+
+        let req = TestRequest::post()
+            .uri(&format!("/tournaments/{}/entries/", tournament.id))
+            .cookie(cookie)
+            .set_json(EnterTournamentPayload {
+                algo_version_id: algo_version.id,
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::FORBIDDEN,
+            "Only the submitted version should enter after the deadline"
+        );
+    }
+
+    #[test]
+    fn elo_should_reward_the_winner() {
+        let ratings = elo_updates(&[1500.0, 1500.0], &[100, 50]);
+        assert_eq!(ratings, vec![1516.0, 1484.0], "Winner should gain what the loser lost");
+
+        let ratings = elo_updates(&[1500.0, 1500.0, 1500.0], &[10, 10, 10]);
+        assert_eq!(ratings, vec![1500.0; 3], "Draws between equals should change nothing");
+    }
+
+    #[test]
+    fn elimination_bracket_keeps_top_seeds_apart_until_the_final() {
+        let now = chrono::Utc::now().naive_utc();
+        let mut tournament = Tournament {
+            id: 1,
+            category_id: 1,
+            name: "test".to_string(),
+            format: TournamentFormat::SingleElimination,
+            rounds_count: None,
+            status: TournamentStatus::Running,
+            current_round: 0,
+            starts_at: now,
+            created_at: now,
+            updated_at: now,
+        };
+        let entries: Vec<TournamentEntry> = (1..=8)
+            .map(|id| TournamentEntry {
+                id,
+                tournament_id: 1,
+                user_id: id.to_string(),
+                algo_version_id: id,
+                created_at: now,
+            })
+            .collect();
+        let mut pairings: Vec<TournamentPairing> = Vec::new();
+        let mut outcomes: Vec<PairingOutcome> = Vec::new();
+
+        let expected_rounds = [
+            vec![vec![1, 8], vec![4, 5], vec![3, 6], vec![2, 7]],
+            vec![vec![1, 4], vec![3, 2]],
+            vec![vec![1, 2]],
+        ];
+        for (number, expected) in (1..).zip(expected_rounds) {
+            let rounds = next_rounds(&tournament, &entries, &pairings, &outcomes);
+            assert_eq!(rounds, vec![(number, expected.clone())], "Round {}", number);
+
+            for entry_ids in expected {
+                // The better seed always wins
+                outcomes.push(PairingOutcome::Win {
+                    winner_id: entry_ids[0].min(entry_ids[1]),
+                    loser_id: entry_ids[0].max(entry_ids[1]),
+                });
+                pairings.push(TournamentPairing {
+                    id: pairings.len() as i32 + 1,
+                    tournament_id: 1,
+                    round: number,
+                    entry_ids,
+                    match_id: None,
+                });
+            }
+            tournament.current_round = number;
+        }
+        assert!(
+            next_rounds(&tournament, &entries, &pairings, &outcomes).is_empty(),
+            "The tournament should end with the final"
+        );
+    }
+
+    #[actix_web::test]
+    async fn non_existent_match_should_return_404() {
+        let (app, _dead_drop) = init_test().await;
+
+        let req = TestRequest::default().uri("/matches/1/").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404, "Match should not exist");
     }
 
     #[actix_web::test]
@@ -787,7 +1397,25 @@ mod tests {
             resp.status().is_success(),
             "Should retrieve algo file successfully"
         );
+        let etag = resp.headers().get(header::ETAG).cloned();
         let resp = test::read_body(resp).await;
         assert_eq!(resp, file, "Should retrieve correct algo file");
+        assert_eq!(
+            etag.as_ref().and_then(|etag| etag.to_str().ok()),
+            Some(format!("\"{}\"", wasm_module::file_hash(&file)).as_str()),
+            "Should tag the file with its hash"
+        );
+
+        let req = TestRequest::get()
+            .uri("/algos/file/1")
+            .cookie(cookie.clone())
+            .insert_header((header::IF_NONE_MATCH, etag.unwrap()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::NOT_MODIFIED,
+            "Should not send an unchanged file again"
+        );
     }
 }
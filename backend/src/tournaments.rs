@@ -0,0 +1,202 @@
+use crate::utils::tournament::{self, PairingOutcome, Standing};
+use crate::{actions, models, DbPool};
+use actix_web::error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError, ErrorNotFound};
+use actix_web::{get, post, web, Error};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnterTournamentPayload {
+    pub algo_version_id: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingDetails {
+    #[serde(flatten)]
+    pub pairing: models::TournamentPairing,
+    pub match_status: Option<models::MatchStatus>,
+    /// Missing while the match is being played
+    pub outcome: Option<PairingOutcome>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TournamentDetails {
+    #[serde(flatten)]
+    pub tournament: models::Tournament,
+    pub entries: Vec<models::TournamentEntry>,
+    pub pairings: Vec<PairingDetails>,
+    pub standings: Vec<Standing>,
+}
+
+#[get("/")]
+pub(crate) async fn get_tournaments(
+    pool: web::Data<DbPool>,
+) -> Result<web::Json<Vec<models::Tournament>>, Error> {
+    let tournaments = web::block(move || {
+        let mut conn = pool.get()?;
+        actions::find_all_tournaments(&mut conn)
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    Ok(web::Json(tournaments))
+}
+
+#[post("/")]
+pub(crate) async fn create_tournament(
+    user: models::User,
+    pool: web::Data<DbPool>,
+    payload: web::Json<models::NewTournament>,
+) -> Result<web::Json<models::Tournament>, Error> {
+    match user.role {
+        models::UserRole::Admin => (),
+        _ => return Err(ErrorForbidden("not authorized")),
+    }
+    if matches!(payload.rounds_count, Some(rounds_count) if rounds_count < 1) {
+        return Err(ErrorBadRequest("a tournament needs at least one round"));
+    }
+
+    let tournament = web::block(move || {
+        let mut conn = pool.get()?;
+        if actions::find_category(&mut conn, payload.category_id)?.is_none() {
+            return Ok(None);
+        }
+        actions::insert_new_tournament(&mut conn, payload.into_inner()).map(Some)
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    match tournament {
+        Some(tournament) => Ok(web::Json(tournament)),
+        None => Err(ErrorNotFound("category not found")),
+    }
+}
+
+#[get("/{tournament_id}/")]
+pub(crate) async fn get_tournament(
+    pool: web::Data<DbPool>,
+    tournament_id: web::Path<i32>,
+) -> Result<web::Json<TournamentDetails>, Error> {
+    let tournament_id = tournament_id.into_inner();
+    let found = web::block(move || {
+        let mut conn = pool.get()?;
+        let Some(found) = actions::find_tournament(&mut conn, tournament_id)? else {
+            return Ok(None);
+        };
+        let entries = actions::find_tournament_entries(&mut conn, tournament_id)?;
+        let pairings = actions::find_tournament_pairings(&mut conn, tournament_id)?;
+        let match_ids = pairings.iter().filter_map(|pairing| pairing.match_id).collect();
+        let summaries = actions::find_match_summaries(&mut conn, match_ids)?;
+        Ok::<_, Box<dyn std::error::Error + Send + Sync>>(Some((
+            found, entries, pairings, summaries,
+        )))
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    let Some((found, entries, pairings, summaries)) = found else {
+        return Err(ErrorNotFound("tournament not found"));
+    };
+
+    let outcomes = tournament::pairing_outcomes(&pairings, &summaries);
+    let finished: Vec<PairingOutcome> = outcomes.iter().flatten().copied().collect();
+    let standings = tournament::standings(&entries, &finished);
+    let pairings = pairings
+        .into_iter()
+        .zip(outcomes)
+        .map(|(pairing, outcome)| PairingDetails {
+            match_status: pairing.match_id.and_then(|match_id| {
+                summaries
+                    .iter()
+                    .find(|summary| summary.id == match_id)
+                    .map(|summary| summary.status)
+            }),
+            pairing,
+            outcome,
+        })
+        .collect();
+
+    Ok(web::Json(TournamentDetails {
+        tournament: found,
+        entries,
+        pairings,
+        standings,
+    }))
+}
+
+/// Enters one of the user's versions. Past the deadline of the tournament's
+/// category only their official submission may enter, the one frozen by the
+/// deadline, unless they're an admin.
+#[post("/{tournament_id}/entries/")]
+pub(crate) async fn enter_tournament(
+    user: models::User,
+    pool: web::Data<DbPool>,
+    tournament_id: web::Path<i32>,
+    payload: web::Json<EnterTournamentPayload>,
+) -> Result<web::Json<models::TournamentEntry>, Error> {
+    let tournament_id = tournament_id.into_inner();
+    let algo_version_id = payload.algo_version_id;
+
+    let (found, category, submitted, owner) = {
+        let pool = pool.clone();
+        let user_id = user.id.clone();
+        web::block(move || {
+            let mut conn = pool.get()?;
+            let Some(found) = actions::find_tournament(&mut conn, tournament_id)? else {
+                return Ok(None);
+            };
+            let category = actions::find_category(&mut conn, found.category_id)?
+                .ok_or("category of the tournament doesn't exist")?;
+            let submitted = actions::find_official_submissions(&mut conn, category.id)?
+                .into_iter()
+                .find(|submission| submission.user_id == user_id)
+                .map(|submission| submission.algo_version_id);
+            let owner = actions::find_algo_version_owner(&mut conn, algo_version_id)?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(Some((
+                found, category, submitted, owner,
+            )))
+        })
+        .await
+        .map_err(ErrorInternalServerError)?
+        .map_err(ErrorInternalServerError)?
+        .ok_or_else(|| ErrorNotFound("tournament not found"))?
+    };
+
+    if found.status != models::TournamentStatus::Scheduled {
+        return Err(ErrorBadRequest("tournament already started"));
+    }
+    match owner {
+        Some(owner) if owner == user.id => (),
+        Some(_) => return Err(ErrorForbidden("algo version belongs to another user")),
+        None => return Err(ErrorNotFound("algo version not found")),
+    }
+    let is_admin = matches!(user.role, models::UserRole::Admin);
+    let late = category.is_past_deadline(chrono::Utc::now().naive_utc());
+    if late && !is_admin && submitted != Some(algo_version_id) {
+        return Err(ErrorForbidden(
+            "the deadline of this category has passed, only your submitted version may enter",
+        ));
+    }
+
+    let entry = web::block(move || {
+        let mut conn = pool.get()?;
+        actions::upsert_tournament_entry(
+            &mut conn,
+            models::NewTournamentEntry {
+                tournament_id,
+                user_id: user.id,
+                algo_version_id,
+            },
+        )
+    })
+    .await
+    .map_err(ErrorInternalServerError)?
+    .map_err(ErrorInternalServerError)?;
+
+    Ok(web::Json(entry))
+}
@@ -0,0 +1,326 @@
+use crate::utils::{rating, wasm_module};
+use crate::{actions, models, DbPool};
+use runner::config::MatchConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::env;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+type RunError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Matches are played by this many threads unless `MATCH_WORKERS` is set.
+const DEFAULT_WORKERS: usize = 2;
+
+/// Wasm operators a bot may run per step unless the category's config sets
+/// `fuel`. Grading by fuel rather than wall time makes a match play out the
+/// same on any server.
+const DEFAULT_STEP_FUEL: u64 = 100_000_000;
+
+/// With fuel metering the wall clock timeout only catches a stalled host, so
+/// it shouldn't be shorter than this many milliseconds.
+const BACKSTOP_TIMEOUT: u64 = 10_000;
+
+/// What the workers do, in the order it was queued.
+enum Job {
+    Match(i32),
+    /// Smoke test of an algo version
+    SmokeTest(i32),
+}
+
+/// Queue of matches and smoke tests played by a pool of worker threads.
+pub struct MatchQueue {
+    sender: Mutex<Sender<Job>>,
+}
+
+impl MatchQueue {
+    /// Starts the workers and queues the matches and smoke tests a previous
+    /// run didn't finish.
+    pub fn start(pool: DbPool) -> MatchQueue {
+        let workers = env::var("MATCH_WORKERS")
+            .ok()
+            .and_then(|workers| workers.parse().ok())
+            .unwrap_or(DEFAULT_WORKERS);
+
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers {
+            let receiver = receiver.clone();
+            let pool = pool.clone();
+            thread::spawn(move || loop {
+                // Only ever held to receive, a poisoned lock is still usable
+                let next = receiver
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .recv();
+                match next {
+                    Ok(job) => work(&pool, job),
+                    Err(_) => break,
+                }
+            });
+        }
+
+        let unfinished = pool.get().map_err(RunError::from).and_then(|mut conn| {
+            Ok((
+                actions::find_unfinished_matches(&mut conn)?,
+                actions::find_untested_algo_versions(&mut conn)?,
+            ))
+        });
+        match unfinished {
+            Ok((match_ids, algo_version_ids)) => {
+                for match_id in match_ids {
+                    let _ = sender.send(Job::Match(match_id));
+                }
+                for algo_version_id in algo_version_ids {
+                    let _ = sender.send(Job::SmokeTest(algo_version_id));
+                }
+            }
+            Err(e) => log::error!("Can't requeue unfinished jobs: {}", e),
+        }
+
+        MatchQueue {
+            sender: Mutex::new(sender),
+        }
+    }
+
+    pub fn push(&self, match_id: i32) {
+        self.send(Job::Match(match_id));
+    }
+
+    /// Queues the smoke test of a new version, see `smoke_test`.
+    pub fn push_smoke_test(&self, algo_version_id: i32) {
+        self.send(Job::SmokeTest(algo_version_id));
+    }
+
+    fn send(&self, job: Job) {
+        let _ = self
+            .sender
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .send(job);
+    }
+}
+
+/// Match between `algo_version_ids`, in seat order, played with the
+/// category's game config. The seed defaults to the config's.
+pub fn new_match(
+    category: &models::Category,
+    algo_version_ids: Vec<i32>,
+    seed: Option<i32>,
+) -> Result<models::NewMatch, String> {
+    let config = serde_json::from_value::<MatchConfig>(category.game_config.clone())
+        .map_err(|e| format!("invalid game config: {}", e))?;
+
+    Ok(models::NewMatch {
+        category_id: category.id,
+        game_config: category.game_config.clone(),
+        seed: seed.unwrap_or(config.game.rng_seed as i32),
+        algo_version_ids,
+    })
+}
+
+/// Fuel, timeout and sandbox limits every game on the server is played with.
+fn apply_server_limits(config: &mut MatchConfig) {
+    config.fuel.get_or_insert(DEFAULT_STEP_FUEL);
+    config.timeout = config.timeout.max(BACKSTOP_TIMEOUT);
+    // The server's limits, categories can't loosen them
+    config.limits = wasm_module::sandbox_limits();
+}
+
+/// Rounds of the game a new version plays against the idle bot.
+const SMOKE_TEST_ROUNDS: u32 = 3;
+
+/// How a new version did in its game against the idle bot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmokeTest {
+    /// Whether the game could be played. It can't when the server fails, so
+    /// the version's status stays unverified instead of being held against it.
+    pub played: bool,
+    /// Initialized and finished every step in time
+    pub passed: bool,
+    pub steps: u32,
+    pub timeouts: u32,
+    /// Everything the bot printed, and why it failed
+    pub log: String,
+}
+
+impl SmokeTest {
+    pub fn status(&self) -> models::AlgoVersionStatus {
+        match (self.played, self.passed) {
+            (false, _) => models::AlgoVersionStatus::Unverified,
+            (true, true) => models::AlgoVersionStatus::Verified,
+            (true, false) => models::AlgoVersionStatus::Broken,
+        }
+    }
+}
+
+/// Plays a short game of `bot` against the idle bot, with the limits of a
+/// real match. A bot that traps or times out, including in `init_game`, is
+/// restarted and never gets to finish a step, so it has to have finished at
+/// least one step without a timeout to pass.
+pub fn smoke_test(bot: &[u8]) -> SmokeTest {
+    let core = match fs::read(core_wasm_path()) {
+        Ok(core) => core,
+        Err(e) => {
+            log::error!("Can't read the core from {}: {}", core_wasm_path(), e);
+            return SmokeTest {
+                played: false,
+                passed: false,
+                steps: 0,
+                timeouts: 0,
+                log: String::new(),
+            };
+        }
+    };
+
+    let mut config = MatchConfig::default();
+    config.game.rounds_count = SMOKE_TEST_ROUNDS;
+    config.max_timeouts_count = 1;
+    apply_server_limits(&mut config);
+
+    let players = [bot.to_vec(), runner::IDLE_BOT.as_bytes().to_vec()];
+    match runner::run_match(&core, &players, &config) {
+        Ok(outcome) => {
+            let summary = &outcome.players[0];
+            SmokeTest {
+                played: true,
+                passed: summary.timeouts == 0 && summary.steps > 0,
+                steps: summary.steps,
+                timeouts: summary.timeouts,
+                log: summary.log.clone(),
+            }
+        }
+        // The core or the runner failed, or the bot couldn't be loaded, which
+        // the upload's validation already ruled out
+        Err(e) => {
+            log::error!("Smoke test couldn't be played: {}", e);
+            SmokeTest {
+                played: false,
+                passed: false,
+                steps: 0,
+                timeouts: 0,
+                log: e,
+            }
+        }
+    }
+}
+
+/// Plays the smoke test of a stored version and records how it went.
+fn test(pool: &DbPool, algo_version_id: i32) -> Result<(), RunError> {
+    let (file, _) = {
+        let mut conn = pool.get()?;
+        actions::get_algo_file(&mut conn, algo_version_id)?
+    };
+
+    let smoke_test = smoke_test(&file);
+    let mut conn = pool.get()?;
+    actions::update_smoke_test(
+        &mut conn,
+        algo_version_id,
+        smoke_test.status(),
+        serde_json::to_value(&smoke_test)?,
+    )
+}
+
+/// Core the matches are played with, built from core/.
+pub(crate) fn core_wasm_path() -> String {
+    env::var("CORE_WASM_PATH").unwrap_or_else(|_| "core.wasm".to_string())
+}
+
+/// Runs each game under `catch_unwind`, a panicking one shouldn't take its
+/// worker down with it.
+fn work(pool: &DbPool, job: Job) {
+    match job {
+        Job::Match(match_id) => {
+            if panic::catch_unwind(AssertUnwindSafe(|| run(pool, match_id))).is_err() {
+                fail(pool, match_id, "the match runner panicked");
+            }
+        }
+        Job::SmokeTest(algo_version_id) => {
+            match panic::catch_unwind(AssertUnwindSafe(|| test(pool, algo_version_id))) {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    log::error!("Can't smoke test algo version {}: {}", algo_version_id, e)
+                }
+                Err(_) => log::error!("Smoke test of algo version {} panicked", algo_version_id),
+            }
+        }
+    }
+}
+
+fn run(pool: &DbPool, match_id: i32) {
+    if let Err(e) = play(pool, match_id) {
+        fail(pool, match_id, &e.to_string());
+    }
+}
+
+fn fail(pool: &DbPool, match_id: i32, error: &str) {
+    log::error!("Match {} failed: {}", match_id, error);
+    let failed = pool
+        .get()
+        .map_err(RunError::from)
+        .and_then(|mut conn| actions::fail_match(&mut conn, match_id, error.to_string()));
+    if let Err(e) = failed {
+        log::error!("Can't mark match {} as failed: {}", match_id, e);
+    }
+}
+
+fn play(pool: &DbPool, match_id: i32) -> Result<(), RunError> {
+    let (started, config, players) = {
+        let mut conn = pool.get()?;
+        let started = actions::start_match(&mut conn, match_id)?;
+        let versions = actions::find_algos(&mut conn, started.algo_version_ids.clone())?;
+
+        let players = started
+            .algo_version_ids
+            .iter()
+            .map(|version_id| {
+                versions
+                    .iter()
+                    .find(|version| version.id == *version_id)
+                    .map(|version| version.file.clone())
+                    .ok_or_else(|| format!("algo version {} doesn't exist", version_id))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut config: MatchConfig = serde_json::from_value(started.game_config.clone())?;
+        config.game.rng_seed = started.seed as u32;
+        apply_server_limits(&mut config);
+
+        (started, config, players)
+    };
+
+    // Played without holding a connection, a match can take minutes
+    let core = fs::read(core_wasm_path())
+        .map_err(|e| format!("can't read the core from {}: {}", core_wasm_path(), e))?;
+    let outcome = runner::run_match(&core, &players, &config)?;
+
+    let mut conn = pool.get()?;
+    actions::finish_match(
+        &mut conn,
+        match_id,
+        json!({
+            "result": outcome.result,
+            "players": outcome.players,
+        }),
+        serde_json::to_value(&outcome.replay)?,
+    )?;
+
+    // The match itself is done, a rating problem shouldn't mark it as failed
+    let rated = rating::record_match(
+        &mut conn,
+        match_id,
+        started.category_id,
+        &started.algo_version_ids,
+        &outcome.result.energy,
+    );
+    if let Err(e) = rated {
+        log::error!("Can't rate match {}: {}", match_id, e);
+    }
+
+    Ok(())
+}
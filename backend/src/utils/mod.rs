@@ -1,2 +1,7 @@
 pub mod auth;
+pub mod match_runner;
+pub mod rate_limit;
+pub mod rating;
+pub mod tournament;
+pub mod validation;
 pub mod wasm_module;
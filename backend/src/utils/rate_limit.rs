@@ -0,0 +1,65 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Matches a user may queue by hand per window unless `RUN_RATE_LIMIT` is set.
+const DEFAULT_LIMIT: usize = 10;
+
+/// Length of the window in seconds unless `RUN_RATE_WINDOW` is set.
+const DEFAULT_WINDOW: u64 = 60;
+
+/// Sliding window limit on how often each user may do something, kept in
+/// memory so it resets with the server.
+pub struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: usize, window: Duration) -> RateLimiter {
+        RateLimiter {
+            limit,
+            window,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limit on the matches users queue through `/algos/run`.
+    pub fn runs_from_env() -> RateLimiter {
+        let limit = env::var("RUN_RATE_LIMIT")
+            .ok()
+            .and_then(|limit| limit.parse().ok())
+            .unwrap_or(DEFAULT_LIMIT);
+        let window = env::var("RUN_RATE_WINDOW")
+            .ok()
+            .and_then(|window| window.parse().ok())
+            .unwrap_or(DEFAULT_WINDOW);
+        RateLimiter::new(limit, Duration::from_secs(window))
+    }
+
+    /// Records a hit for `user_id`, false if it went over the limit. Hits
+    /// over the limit aren't recorded, so retrying doesn't extend the wait.
+    pub fn try_hit(&self, user_id: &str) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap_or_else(PoisonError::into_inner);
+        // Forget users whose hits all expired, the map would only grow otherwise
+        hits.retain(|_, user_hits| {
+            while user_hits
+                .front()
+                .map_or(false, |hit| now.duration_since(*hit) >= self.window)
+            {
+                user_hits.pop_front();
+            }
+            !user_hits.is_empty()
+        });
+
+        let user_hits = hits.entry(user_id.to_string()).or_default();
+        if user_hits.len() >= self.limit {
+            return false;
+        }
+        user_hits.push_back(now);
+        true
+    }
+}
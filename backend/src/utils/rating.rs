@@ -0,0 +1,162 @@
+use crate::utils::match_runner::{self, MatchQueue};
+use crate::{actions, models, DbPool};
+use actix_web::web;
+use diesel::{Connection, PgConnection};
+use std::env;
+use std::thread;
+use std::time::Duration;
+
+type RunError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Rating of an algo version before its first match in a category.
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// Most a rating changes after a single match.
+const K_FACTOR: f64 = 32.0;
+
+/// Seconds between two matchmaking rounds unless `MATCHMAKING_TICK` is set.
+const DEFAULT_MATCHMAKING_TICK: u64 = 10;
+
+/// Matches the matchmaking keeps queued or running per category unless
+/// `MATCHMAKING_MAX_PENDING` is set.
+const DEFAULT_MAX_PENDING_MATCHES: i64 = 4;
+
+/// Pairwise Elo: each player plays a virtual game against every other one,
+/// won by whoever ended with more energy. The K factor is split between the
+/// opponents, so a match with many players moves ratings as much as a duel.
+pub fn elo_updates(ratings: &[f64], energy: &[u64]) -> Vec<f64> {
+    let opponents = ratings.len().saturating_sub(1).max(1) as f64;
+
+    ratings
+        .iter()
+        .enumerate()
+        .map(|(i, rating)| {
+            let change: f64 = (0..ratings.len())
+                .filter(|&j| j != i)
+                .map(|j| {
+                    let expected = 1.0 / (1.0 + 10f64.powf((ratings[j] - rating) / 400.0));
+                    let score = match energy[i].cmp(&energy[j]) {
+                        std::cmp::Ordering::Greater => 1.0,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Less => 0.0,
+                    };
+                    score - expected
+                })
+                .sum();
+            rating + K_FACTOR * change / opponents
+        })
+        .collect()
+}
+
+/// Updates the ratings of the versions that played a finished match.
+///
+/// Matches where a version plays itself say nothing about its strength and
+/// are left out.
+pub fn record_match(
+    conn: &mut PgConnection,
+    match_id: i32,
+    category_id: i32,
+    algo_version_ids: &[i32],
+    energy: &[u64],
+) -> Result<(), RunError> {
+    let mut distinct = algo_version_ids.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+    if distinct.len() < 2 || distinct.len() != algo_version_ids.len() {
+        return Ok(());
+    }
+
+    conn.transaction::<_, RunError, _>(|conn| {
+        let ratings = algo_version_ids
+            .iter()
+            .map(|&algo_version_id| {
+                actions::find_or_create_rating(
+                    conn,
+                    models::NewRating {
+                        category_id,
+                        algo_version_id,
+                        rating: INITIAL_RATING,
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let current: Vec<f64> = ratings.iter().map(|rating| rating.rating).collect();
+        for (rating, new_rating) in ratings.iter().zip(elo_updates(&current, energy)) {
+            actions::update_rating(conn, rating.id, match_id, new_rating)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Starts the thread that keeps the ladders moving: it pairs the versions that
+/// played the least with the closest rated version of another algo.
+pub fn start_matchmaking(pool: DbPool, match_queue: web::Data<MatchQueue>) {
+    fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+        env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    }
+
+    let tick = Duration::from_secs(var("MATCHMAKING_TICK", DEFAULT_MATCHMAKING_TICK));
+    let max_pending = var("MATCHMAKING_MAX_PENDING", DEFAULT_MAX_PENDING_MATCHES);
+    thread::spawn(move || loop {
+        if let Err(e) = matchmake(&pool, &match_queue, max_pending) {
+            log::error!("Matchmaking failed: {}", e);
+        }
+        thread::sleep(tick);
+    });
+}
+
+/// Tops up every open category to `max_pending` queued or running matches.
+fn matchmake(pool: &DbPool, match_queue: &MatchQueue, max_pending: i64) -> Result<(), RunError> {
+    let mut conn = pool.get()?;
+    let now = chrono::Utc::now().naive_utc();
+
+    for category in actions::find_all_categories(&mut conn)? {
+        // Ratings don't matter anymore once the category is closed
+        if category.is_past_deadline(now) {
+            continue;
+        }
+        let pending = actions::count_pending_matches(&mut conn, category.id)?;
+        let free = usize::try_from(max_pending - pending).unwrap_or(0);
+        if free == 0 {
+            continue;
+        }
+
+        let ladder = actions::find_ladder(&mut conn, category.id)?;
+        let mut least_played: Vec<&models::LadderEntry> = ladder.iter().collect();
+        least_played.sort_by_key(|entry| (entry.rating.matches_played, entry.rating.id));
+        for first in least_played.into_iter().take(free) {
+            let Some(second) = ladder
+                .iter()
+                .filter(|entry| entry.algo_id != first.algo_id)
+                .min_by(|a, b| {
+                    let distance = |entry: &models::LadderEntry| {
+                        (entry.rating.rating - first.rating.rating).abs()
+                    };
+                    distance(a).total_cmp(&distance(b))
+                })
+            else {
+                continue;
+            };
+
+            let new_match = match_runner::new_match(
+                &category,
+                vec![first.rating.algo_version_id, second.rating.algo_version_id],
+                None,
+            )?;
+            // Replays of the same pairing would all be the same game otherwise
+            let new_match = models::NewMatch {
+                seed: new_match.seed.wrapping_add(first.rating.matches_played),
+                ..new_match
+            };
+            let created = actions::insert_new_match(&mut conn, new_match)?;
+            match_queue.push(created.id);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,446 @@
+use crate::models::{
+    MatchStatus, MatchSummary, Tournament, TournamentEntry, TournamentFormat, TournamentPairing,
+    TournamentStatus,
+};
+use crate::utils::match_runner::{self, MatchQueue};
+use crate::{actions, models, DbPool};
+use actix_web::web;
+use diesel::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::thread;
+use std::time::Duration;
+
+type RunError = Box<dyn std::error::Error + Send + Sync>;
+
+/// How often the scheduler starts due tournaments and pairs finished rounds.
+const SCHEDULER_TICK: Duration = Duration::from_secs(10);
+
+const WIN_POINTS: i32 = 3;
+const DRAW_POINTS: i32 = 1;
+
+/// What a pairing ended with, `None` while its match is still being played.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PairingOutcome {
+    #[serde(rename_all = "camelCase")]
+    Bye { entry_id: i32 },
+    #[serde(rename_all = "camelCase")]
+    Win { winner_id: i32, loser_id: i32 },
+    #[serde(rename_all = "camelCase")]
+    Draw { entry_ids: [i32; 2] },
+}
+
+/// A tie or a failed match is a draw.
+pub fn pairing_outcome(
+    pairing: &TournamentPairing,
+    summary: Option<&MatchSummary>,
+) -> Option<PairingOutcome> {
+    let (first, second) = match pairing.entry_ids[..] {
+        [entry_id] => return Some(PairingOutcome::Bye { entry_id }),
+        [first, second] => (first, second),
+        _ => return None,
+    };
+
+    let summary = summary?;
+    match summary.status {
+        MatchStatus::Queued | MatchStatus::Running => None,
+        MatchStatus::Failed => Some(PairingOutcome::Draw {
+            entry_ids: [first, second],
+        }),
+        MatchStatus::Finished => {
+            let winner = summary
+                .result
+                .as_ref()
+                .and_then(|result| result["result"]["winner"].as_u64());
+            Some(match winner {
+                Some(0) => PairingOutcome::Win {
+                    winner_id: first,
+                    loser_id: second,
+                },
+                Some(1) => PairingOutcome::Win {
+                    winner_id: second,
+                    loser_id: first,
+                },
+                _ => PairingOutcome::Draw {
+                    entry_ids: [first, second],
+                },
+            })
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Standing {
+    pub entry_id: i32,
+    pub user_id: String,
+    pub algo_version_id: i32,
+    pub points: i32,
+    pub wins: i32,
+    pub draws: i32,
+    pub losses: i32,
+    pub byes: i32,
+}
+
+/// Standings ordered from first to last place. Byes are worth a win, ties
+/// are broken by wins and then by entry order.
+pub fn standings(entries: &[TournamentEntry], outcomes: &[PairingOutcome]) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = entries
+        .iter()
+        .map(|entry| Standing {
+            entry_id: entry.id,
+            user_id: entry.user_id.clone(),
+            algo_version_id: entry.algo_version_id,
+            points: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            byes: 0,
+        })
+        .collect();
+    let index: HashMap<i32, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| (entry.id, i))
+        .collect();
+
+    let standing = |entry_id: &i32| index.get(entry_id).copied();
+
+    for outcome in outcomes {
+        match *outcome {
+            PairingOutcome::Bye { entry_id } => {
+                if let Some(i) = standing(&entry_id) {
+                    standings[i].byes += 1;
+                    standings[i].points += WIN_POINTS;
+                }
+            }
+            PairingOutcome::Win {
+                winner_id,
+                loser_id,
+            } => {
+                if let Some(i) = standing(&winner_id) {
+                    standings[i].wins += 1;
+                    standings[i].points += WIN_POINTS;
+                }
+                if let Some(i) = standing(&loser_id) {
+                    standings[i].losses += 1;
+                }
+            }
+            PairingOutcome::Draw { entry_ids } => {
+                for entry_id in &entry_ids {
+                    if let Some(i) = standing(entry_id) {
+                        standings[i].draws += 1;
+                        standings[i].points += DRAW_POINTS;
+                    }
+                }
+            }
+        }
+    }
+
+    // Stable, so equal standings keep the entry order
+    standings.sort_by(|a, b| b.points.cmp(&a.points).then(b.wins.cmp(&a.wins)));
+    standings
+}
+
+/// Every round of a round-robin, using the circle method. With an odd number
+/// of entries one of them sits out each round.
+fn round_robin_rounds(entry_ids: &[i32]) -> Vec<Vec<Vec<i32>>> {
+    let mut circle: Vec<Option<i32>> = entry_ids.iter().copied().map(Some).collect();
+    if circle.len() % 2 == 1 {
+        circle.push(None);
+    }
+    let n = circle.len();
+    if n < 2 {
+        return Vec::new();
+    }
+
+    (0..n - 1)
+        .map(|round| {
+            let round_pairings = (0..n / 2)
+                .filter_map(|i| match (circle[i], circle[n - 1 - i]) {
+                    // Alternate seats so nobody always plays first
+                    (Some(a), Some(b)) if round % 2 == 0 => Some(vec![a, b]),
+                    (Some(a), Some(b)) => Some(vec![b, a]),
+                    _ => None,
+                })
+                .collect();
+            circle[1..].rotate_right(1);
+            round_pairings
+        })
+        .collect()
+}
+
+/// Pairs entries with similar points that didn't meet yet. With an odd number
+/// of entries the lowest ranked one without a bye gets one.
+fn swiss_round(standings: &[Standing], pairings: &[TournamentPairing]) -> Vec<Vec<i32>> {
+    let met: HashSet<(i32, i32)> = pairings
+        .iter()
+        .filter(|pairing| pairing.entry_ids.len() == 2)
+        .flat_map(|pairing| {
+            let (a, b) = (pairing.entry_ids[0], pairing.entry_ids[1]);
+            [(a, b), (b, a)]
+        })
+        .collect();
+
+    let mut unpaired: Vec<&Standing> = standings.iter().collect();
+    let mut round = Vec::new();
+
+    if unpaired.len() % 2 == 1 {
+        let bye = unpaired
+            .iter()
+            .rposition(|standing| standing.byes == 0)
+            .unwrap_or(unpaired.len() - 1);
+        round.push(vec![unpaired.remove(bye).entry_id]);
+    }
+
+    while !unpaired.is_empty() {
+        let first = unpaired.remove(0);
+        // Everyone left may have met already, then the closest one is a rematch
+        let opponent = unpaired
+            .iter()
+            .position(|other| !met.contains(&(first.entry_id, other.entry_id)))
+            .unwrap_or(0);
+        let second = unpaired.remove(opponent);
+        round.push(vec![first.entry_id, second.entry_id]);
+    }
+
+    round
+}
+
+/// Seeds of a bracket of `size` entries, a power of two, in the order they
+/// are placed: neighbours play each other first and the better seed of two
+/// can only meet the other one as late as possible, the top two in the final.
+fn bracket_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let last_seed = order.len() * 2 - 1;
+        order = order
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &seed)| match i % 2 {
+                0 => [seed, last_seed - seed],
+                _ => [last_seed - seed, seed],
+            })
+            .collect();
+    }
+    order
+}
+
+/// Winners of the last round play each other in bracket order. The first
+/// round seeds entries by entry order and gives byes to the top seeds.
+fn elimination_round(
+    entry_ids: &[i32],
+    last_round: &[(&TournamentPairing, PairingOutcome)],
+) -> Vec<Vec<i32>> {
+    if last_round.is_empty() {
+        if entry_ids.len() < 2 {
+            return Vec::new();
+        }
+        return bracket_order(entry_ids.len().next_power_of_two())
+            .chunks(2)
+            .map(|pair| {
+                // The better seed sits first, it goes through a tie
+                let (seed, opponent) = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+                match entry_ids.get(opponent) {
+                    Some(&opponent) => vec![entry_ids[seed], opponent],
+                    None => vec![entry_ids[seed]],
+                }
+            })
+            .collect();
+    }
+
+    let winners: Vec<i32> = last_round
+        .iter()
+        .map(|(pairing, outcome)| match *outcome {
+            PairingOutcome::Bye { entry_id } => entry_id,
+            PairingOutcome::Win { winner_id, .. } => winner_id,
+            // The higher seed goes through a tie
+            PairingOutcome::Draw { .. } => pairing.entry_ids[0],
+        })
+        .collect();
+
+    winners.chunks(2).map(|pair| pair.to_vec()).collect()
+}
+
+/// Pairings of the next rounds, each with its round number. Empty once the
+/// tournament is over.
+pub fn next_rounds(
+    tournament: &Tournament,
+    entries: &[TournamentEntry],
+    pairings: &[TournamentPairing],
+    outcomes: &[PairingOutcome],
+) -> Vec<(i32, Vec<Vec<i32>>)> {
+    let entry_ids: Vec<i32> = entries.iter().map(|entry| entry.id).collect();
+    let next_round = tournament.current_round + 1;
+
+    match tournament.format {
+        TournamentFormat::RoundRobin => {
+            if tournament.current_round > 0 {
+                return Vec::new();
+            }
+            round_robin_rounds(&entry_ids)
+                .into_iter()
+                .zip(1..)
+                .map(|(round, number)| (number, round))
+                .collect()
+        }
+        TournamentFormat::Swiss => {
+            let rounds_count = tournament
+                .rounds_count
+                .unwrap_or_else(|| default_swiss_rounds(entry_ids.len()));
+            if entry_ids.len() < 2 || tournament.current_round >= rounds_count {
+                return Vec::new();
+            }
+            let standings = standings(entries, outcomes);
+            vec![(next_round, swiss_round(&standings, pairings))]
+        }
+        TournamentFormat::SingleElimination => {
+            let last_round: Vec<_> = pairings
+                .iter()
+                .zip(outcomes)
+                .filter(|(pairing, _)| pairing.round == tournament.current_round)
+                .map(|(pairing, outcome)| (pairing, *outcome))
+                .collect();
+            let round = elimination_round(&entry_ids, &last_round);
+            // Done once a single entry is left
+            if entry_ids.len() < 2 || (round.len() == 1 && round[0].len() == 1) {
+                return Vec::new();
+            }
+            vec![(next_round, round)]
+        }
+    }
+}
+
+/// Enough rounds to separate the winner when nobody draws.
+fn default_swiss_rounds(entries_count: usize) -> i32 {
+    entries_count.next_power_of_two().trailing_zeros() as i32
+}
+
+/// Starts the thread that runs the tournaments: it pairs the first round once
+/// a tournament is due and the next one once every match of a round ended.
+pub fn start_scheduler(pool: DbPool, match_queue: web::Data<MatchQueue>) {
+    thread::spawn(move || loop {
+        if let Err(e) = tick(&pool, &match_queue) {
+            log::error!("Tournament scheduler failed: {}", e);
+        }
+        thread::sleep(SCHEDULER_TICK);
+    });
+}
+
+fn tick(pool: &DbPool, match_queue: &MatchQueue) -> Result<(), RunError> {
+    let mut conn = pool.get()?;
+    for tournament in actions::find_active_tournaments(&mut conn)? {
+        if let Err(e) = advance(&mut conn, match_queue, &tournament) {
+            log::error!("Tournament {} can't advance: {}", tournament.id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Outcome of every pairing, in pairing order. `None` if a match is still
+/// being played.
+pub fn pairing_outcomes(
+    pairings: &[TournamentPairing],
+    summaries: &[MatchSummary],
+) -> Vec<Option<PairingOutcome>> {
+    let summaries: HashMap<i32, &MatchSummary> =
+        summaries.iter().map(|summary| (summary.id, summary)).collect();
+
+    pairings
+        .iter()
+        .map(|pairing| {
+            let summary = pairing.match_id.and_then(|id| summaries.get(&id).copied());
+            pairing_outcome(pairing, summary)
+        })
+        .collect()
+}
+
+fn advance(
+    conn: &mut diesel::PgConnection,
+    match_queue: &MatchQueue,
+    tournament: &Tournament,
+) -> Result<(), RunError> {
+    let pairings = actions::find_tournament_pairings(conn, tournament.id)?;
+    let match_ids = pairings.iter().filter_map(|pairing| pairing.match_id).collect();
+    let summaries = actions::find_match_summaries(conn, match_ids)?;
+    let Some(outcomes) = pairing_outcomes(&pairings, &summaries)
+        .into_iter()
+        .collect::<Option<Vec<_>>>()
+    else {
+        // The current round is still being played
+        return Ok(());
+    };
+
+    let entries = actions::find_tournament_entries(conn, tournament.id)?;
+    // Stays open for entries until there's someone to play against
+    if tournament.status == TournamentStatus::Scheduled && entries.len() < 2 {
+        return Ok(());
+    }
+    let rounds = next_rounds(tournament, &entries, &pairings, &outcomes);
+    if rounds.is_empty() {
+        return actions::update_tournament_progress(
+            conn,
+            tournament.id,
+            TournamentStatus::Finished,
+            tournament.current_round,
+        );
+    }
+
+    let category = actions::find_category(conn, tournament.category_id)?
+        .ok_or("category of the tournament doesn't exist")?;
+    let algo_versions: HashMap<i32, i32> = entries
+        .iter()
+        .map(|entry| (entry.id, entry.algo_version_id))
+        .collect();
+
+    // A round is paired all at once or not at all, and its matches are only
+    // queued once they're stored
+    let queued = conn.transaction::<_, RunError, _>(|conn| {
+        let mut queued = Vec::new();
+        let mut current_round = tournament.current_round;
+        for (round, round_pairings) in rounds {
+            for entry_ids in round_pairings {
+                let match_id = if entry_ids.len() == 2 {
+                    let algo_version_ids = entry_ids.iter().map(|id| algo_versions[id]).collect();
+                    let new_match = match_runner::new_match(&category, algo_version_ids, None)?;
+                    // Every pairing of a round plays on the same map
+                    let new_match = models::NewMatch {
+                        seed: new_match.seed.wrapping_add(round),
+                        ..new_match
+                    };
+                    Some(actions::insert_new_match(conn, new_match)?.id)
+                } else {
+                    None
+                };
+
+                actions::insert_tournament_pairing(
+                    conn,
+                    models::NewTournamentPairing {
+                        tournament_id: tournament.id,
+                        round,
+                        entry_ids,
+                        match_id,
+                    },
+                )?;
+                queued.extend(match_id);
+            }
+            current_round = round;
+        }
+
+        actions::update_tournament_progress(
+            conn,
+            tournament.id,
+            TournamentStatus::Running,
+            current_round,
+        )?;
+        Ok(queued)
+    })?;
+
+    for match_id in queued {
+        match_queue.push(match_id);
+    }
+
+    Ok(())
+}
@@ -1,19 +1,41 @@
 extern crate wasmer_types;
 
+use crate::utils::validation::ValidationError;
+use robotchallenge_types::host::InternalLibInfo;
+use robotchallenge_types::ABI_VERSION;
+use runner::sandbox::{self, InstancePermit, Limits, Sandbox, Wasi};
 use serde::{Deserialize, Serialize};
-use wasmer::{
-    imports, Function, Instance, Module, Store,
-    WasmPtr,
-};
-use wasmer_derive::ValueType;
-use wasmer_wasi::WasiState;
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueType)]
-#[repr(C)]
-pub struct InternalLibInfo {
-    pub name: WasmPtr<u8>,
-    pub language: WasmPtr<u8>,
-    pub version: WasmPtr<u8>,
+use sha2::{Digest, Sha256};
+use std::env;
+use wasmer::{imports, ExternType, Function, FunctionType, Instance, Module, Type, WasmPtr};
+use wasmer_wasi::Pipe;
+
+/// Hex SHA-256 of a module, identifies a file across uploads.
+pub fn file_hash(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Wasm operators a module may run while its library info is read.
+const LIB_INFO_FUEL: u64 = 10_000_000;
+
+/// Resources uploaded modules may use, here and in matches. `BOT_MEMORY_PAGES`,
+/// `BOT_TABLE_ELEMENTS`, `BOT_STACK_SIZE` and `BOT_INSTANCES` override the
+/// defaults.
+pub fn sandbox_limits() -> Limits {
+    fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+        env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    }
+
+    let defaults = Limits::default();
+    Limits {
+        memory_pages: var("BOT_MEMORY_PAGES", defaults.memory_pages),
+        table_elements: var("BOT_TABLE_ELEMENTS", defaults.table_elements),
+        stack_size: var("BOT_STACK_SIZE", defaults.stack_size),
+        instances: var("BOT_INSTANCES", defaults.instances),
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -23,53 +45,200 @@ pub struct LibInfo {
     pub version: String,
 }
 
-pub fn get_lib_info(data: &Vec<u8>) -> Result<LibInfo, ()> {
-    let mut store = Store::default();
-    let module = Module::new(&store, data).unwrap();
-    let wasi_env = WasiState::new("robotchallenge")
-        .finalize(&mut store)
-        .unwrap();
+/// Reads the library info of a module. Fails if the module was built for
+/// another ABI version or doesn't fit the sandbox's limits.
+pub fn get_lib_info(data: &Vec<u8>) -> Result<LibInfo, String> {
+    let sandbox = Sandbox {
+        limits: sandbox_limits(),
+        fuel: Some(LIB_INFO_FUEL),
+        seed: 0,
+    };
+    let _permit = InstancePermit::acquire(&sandbox.limits)?;
+    let mut store = sandbox::store(&sandbox);
+    let module = Module::new(&store, data).map_err(|e| e.to_string())?;
+    let wasi = Wasi::new(&mut store, &sandbox, Pipe::new(), Pipe::new())?;
 
     let mut import_object = imports! {
         "robotchallenge" => {
-            "move_robot" => Function::new_typed(&mut store, move |q: i32, r: i32| {
-                println!("move_robot({}, {})", q, r);
-            }),
-            "collect_energy" => Function::new_typed(&mut store, move || {
-                println!("collect_energy()");
-            }),
-            "clone_robot" => Function::new_typed(&mut store, move |new_bot_energy: u32| {
-                println!("clone_robot({})", new_bot_energy);
-            }),
+            "move_robot" => Function::new_typed(&mut store, move |_q: i32, _r: i32| {}),
+            "collect_energy" => Function::new_typed(&mut store, move || {}),
+            "clone_robot" => Function::new_typed(&mut store, move |_new_bot_energy: u32| {}),
+            "transfer_energy" => Function::new_typed(&mut store, move |_target_robot: u32, _amount: u32| {}),
+            "get_config" => Function::new_typed(&mut store, move |_out: u32| {}),
+            "get_my_robots" => Function::new_typed(&mut store, move |_out: u32, _capacity: u32| -> u32 { 0 }),
+            "is_cell_free" => Function::new_typed(&mut store, move |_q: i32, _r: i32| -> u32 { 0 }),
+            "move_cost" => Function::new_typed(&mut store, move |_from_q: i32, _from_r: i32, _to_q: i32, _to_r: i32| -> u32 { 0 }),
+            "stations_in_range" => Function::new_typed(&mut store, move |_q: i32, _r: i32, _out: u32, _capacity: u32| -> u32 { 0 }),
         }
     };
-    let wasi_imports = wasi_env.import_object(&mut store, &module).unwrap();
+    let wasi_imports = wasi.imports(&mut store, &module)?;
     import_object.extend(wasi_imports.into_iter());
 
-    let instance = Instance::new(&mut store, &module, &import_object).unwrap();
+    let instance =
+        Instance::new(&mut store, &module, &import_object).map_err(|e| e.to_string())?;
 
-    let memory = instance.exports.get_memory("memory").unwrap();
-    wasi_env.data_mut(&mut store).set_memory(memory.clone());
+    let memory = instance
+        .exports
+        .get_memory("memory")
+        .map_err(|e| e.to_string())?;
+    wasi.set_memory(&mut store, memory);
 
-    let get_lib_info = instance.exports.get_function("get_lib_info").unwrap();
-    let get_lib_info_typed = get_lib_info
-        .typed::<(), WasmPtr<InternalLibInfo>>(&mut store)
-        .unwrap();
-    let result = get_lib_info_typed.call(&mut store).unwrap();
-    let internal_lib_info = result.read(&memory.view(&mut store)).unwrap();
+    let abi_version = match instance
+        .exports
+        .get_typed_function::<(), u32>(&store, "abi_version")
+    {
+        Ok(abi_version) => abi_version.call(&mut store).map_err(|e| e.to_string())?,
+        Err(_) => {
+            return Err(format!(
+                "module doesn't export `abi_version`, rebuild it with a library supporting ABI version {}",
+                ABI_VERSION
+            ))
+        }
+    };
+    if abi_version != ABI_VERSION {
+        return Err(format!(
+            "module was built for ABI version {}, but the server supports version {}",
+            abi_version, ABI_VERSION
+        ));
+    }
+
+    let get_lib_info_typed = instance
+        .exports
+        .get_typed_function::<(), WasmPtr<InternalLibInfo>>(&store, "get_lib_info")
+        .map_err(|e| format!("`get_lib_info`: {}", e))?;
+    let result = get_lib_info_typed
+        .call(&mut store)
+        .map_err(|e| format!("`get_lib_info` trapped: {}", e))?;
+    let view = memory.view(&store);
+    let internal_lib_info = result
+        .read(&view)
+        .map_err(|e| format!("can't read the library info: {}", e))?;
+    let read = |ptr: WasmPtr<u8>, field: &str| {
+        ptr.read_utf8_string_with_nul(&view)
+            .map_err(|e| format!("can't read the library's {}: {}", field, e))
+    };
 
     Ok(LibInfo {
-        name: internal_lib_info
-            .name
-            .read_utf8_string_with_nul(&memory.view(&mut store))
-            .unwrap(),
-        version: internal_lib_info
-            .version
-            .read_utf8_string_with_nul(&memory.view(&mut store))
-            .unwrap(),
-        language: internal_lib_info
-            .language
-            .read_utf8_string_with_nul(&memory.view(&mut store))
-            .unwrap(),
+        name: read(internal_lib_info.name, "name")?,
+        version: read(internal_lib_info.version, "version")?,
+        language: read(internal_lib_info.language, "language")?,
     })
 }
+
+/// Function imports a bot may use from the `robotchallenge` module.
+const BOT_IMPORTS: [(&str, &[Type], &[Type]); 9] = [
+    ("move_robot", &[Type::I32, Type::I32], &[]),
+    ("collect_energy", &[], &[]),
+    ("clone_robot", &[Type::I32], &[]),
+    ("transfer_energy", &[Type::I32, Type::I32], &[]),
+    ("get_config", &[Type::I32], &[]),
+    ("get_my_robots", &[Type::I32, Type::I32], &[Type::I32]),
+    ("is_cell_free", &[Type::I32, Type::I32], &[Type::I32]),
+    ("move_cost", &[Type::I32, Type::I32, Type::I32, Type::I32], &[Type::I32]),
+    ("stations_in_range", &[Type::I32, Type::I32, Type::I32, Type::I32], &[Type::I32]),
+];
+
+/// Functions a bot has to export, besides `memory`.
+const BOT_EXPORTS: [(&str, &[Type], &[Type]); 6] = [
+    ("abi_version", &[], &[Type::I32]),
+    ("get_lib_info", &[], &[Type::I32]),
+    ("init_game", &[Type::I32, Type::I32], &[]),
+    ("do_step_ffi", &[Type::I32, Type::I32, Type::I32], &[]),
+    ("allocate", &[Type::I32], &[Type::I32]),
+    ("deallocate", &[Type::I32, Type::I32], &[]),
+];
+
+/// Uploads are rejected above this many bytes.
+pub const MAX_MODULE_SIZE: usize = 16 * 1024 * 1024;
+
+fn check_function(
+    problems: &mut Vec<String>,
+    what: &str,
+    ty: &ExternType,
+    params: &[Type],
+    results: &[Type],
+) {
+    let expected = FunctionType::new(params, results);
+    match ty {
+        ExternType::Function(ty) if *ty == expected => {}
+        ExternType::Function(ty) => problems.push(format!(
+            "{} has the signature {}, expected {}",
+            what, ty, expected
+        )),
+        _ => problems.push(format!("{} should be a function", what)),
+    }
+}
+
+/// Checks that a module parses, fits in `MAX_MODULE_SIZE`, imports only what
+/// the game provides and exports everything the game calls, with the right
+/// signatures. Lists every problem found, so they can all be fixed at once.
+pub fn validate(data: &[u8]) -> Result<(), ValidationError> {
+    if data.len() > MAX_MODULE_SIZE {
+        return Err(ValidationError {
+            problems: vec![format!(
+                "module is {} bytes, modules may be up to {} bytes",
+                data.len(),
+                MAX_MODULE_SIZE
+            )],
+        });
+    }
+
+    // Only compiled, but with the limits it would be instantiated with
+    let store = sandbox::store(&Sandbox {
+        limits: sandbox_limits(),
+        fuel: None,
+        seed: 0,
+    });
+    let module = Module::new(&store, data).map_err(|e| ValidationError {
+        problems: vec![format!("not a valid wasm module: {}", e)],
+    })?;
+
+    let mut problems = Vec::new();
+    for import in module.imports() {
+        let what = format!("import `{}.{}`", import.module(), import.name());
+        match import.module() {
+            "robotchallenge" => {
+                match BOT_IMPORTS
+                    .iter()
+                    .find(|(name, _, _)| *name == import.name())
+                {
+                    Some((_, params, results)) => {
+                        check_function(&mut problems, &what, import.ty(), params, results)
+                    }
+                    None => problems.push(format!("{} doesn't exist", what)),
+                }
+            }
+            "wasi_snapshot_preview1" => {}
+            _ => problems.push(format!(
+                "{} isn't allowed, modules may only import from `robotchallenge` and `wasi_snapshot_preview1`",
+                what
+            )),
+        }
+    }
+
+    let exports: Vec<_> = module.exports().collect();
+    let find_export = |name: &str| exports.iter().find(|export| export.name() == name);
+    match find_export("memory").map(|export| export.ty()) {
+        Some(ExternType::Memory(_)) => {}
+        Some(_) => problems.push("export `memory` should be a memory".to_string()),
+        None => problems.push("missing export `memory`".to_string()),
+    }
+    for (name, params, results) in BOT_EXPORTS {
+        match find_export(name) {
+            Some(export) => check_function(
+                &mut problems,
+                &format!("export `{}`", name),
+                export.ty(),
+                params,
+                results,
+            ),
+            None => problems.push(format!("missing export `{}`", name)),
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError { problems })
+    }
+}
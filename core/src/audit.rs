@@ -0,0 +1,179 @@
+use crate::board::Board;
+use crate::{EnergyStation, Position, Robot};
+use std::collections::{HashMap, HashSet};
+
+/// Robot id used for violations that aren't caused by a single robot.
+pub const NO_ROBOT: usize = usize::MAX;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AuditViolationKind {
+    /// Total energy on the map doesn't match the ledger
+    EnergyNotConserved,
+    /// An energy amount didn't fit into `u32`
+    EnergyOverflow,
+    /// A robot stands outside of the board
+    InvalidPosition,
+    /// Two robots or stations share a cell
+    OverlappingPositions,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct AuditViolation {
+    pub round: u32,
+    pub robot_id: usize,
+    pub kind: AuditViolationKind,
+    pub position: Position,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+/// Energy that entered and left the map during a single round.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct EnergyLedger {
+    /// Energy stations recovered at the end of the round (source)
+    pub recovered: u64,
+    /// Moved from stations to robots
+    pub collected: u64,
+    /// Given by robots to the robots they cloned
+    pub cloned: u64,
+    /// Lost by moving (sink)
+    pub move_loss: u64,
+    /// Lost by cloning on top of the energy given to the clone (sink)
+    pub clone_loss: u64,
+    /// Given by robots to adjacent robots of the same player
+    pub transferred: u64,
+    /// Lost as transfer fees (sink)
+    pub transfer_loss: u64,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum LedgerEntry {
+    Recovered(u64),
+    Collected(u64),
+    Cloned(u64),
+    MoveLoss(u64),
+    CloneLoss(u64),
+    Transferred(u64),
+    TransferLoss(u64),
+}
+
+#[derive(Debug)]
+pub struct Audit {
+    expected_total: u64,
+    ledgers: HashMap<u32, EnergyLedger>,
+    violations: Vec<AuditViolation>,
+}
+
+impl Audit {
+    pub fn new(total_energy: u64) -> Audit {
+        Audit {
+            expected_total: total_energy,
+            ledgers: HashMap::new(),
+            violations: vec![],
+        }
+    }
+
+    pub fn ledger(&self, round: u32) -> EnergyLedger {
+        self.ledgers.get(&round).copied().unwrap_or_default()
+    }
+
+    pub fn violations(&self) -> &[AuditViolation] {
+        &self.violations
+    }
+
+    pub fn record(&mut self, round: u32, entry: LedgerEntry) {
+        let ledger = self.ledgers.entry(round).or_default();
+        match entry {
+            LedgerEntry::Recovered(amount) => {
+                ledger.recovered += amount;
+                self.expected_total += amount;
+            }
+            LedgerEntry::Collected(amount) => ledger.collected += amount,
+            LedgerEntry::Cloned(amount) => ledger.cloned += amount,
+            LedgerEntry::MoveLoss(amount) => {
+                ledger.move_loss += amount;
+                self.expected_total = self.expected_total.saturating_sub(amount);
+            }
+            LedgerEntry::CloneLoss(amount) => {
+                ledger.clone_loss += amount;
+                self.expected_total = self.expected_total.saturating_sub(amount);
+            }
+            LedgerEntry::Transferred(amount) => ledger.transferred += amount,
+            LedgerEntry::TransferLoss(amount) => {
+                ledger.transfer_loss += amount;
+                self.expected_total = self.expected_total.saturating_sub(amount);
+            }
+        }
+    }
+
+    /// Forgets the ledgers and violations of `round` and later rounds, the
+    /// map has `total_energy` again.
+    pub fn truncate(&mut self, round: u32, total_energy: u64) {
+        self.ledgers.retain(|r, _| *r < round);
+        self.violations.retain(|violation| violation.round < round);
+        self.expected_total = total_energy;
+    }
+
+    pub fn report(&mut self, violation: AuditViolation) {
+        eprintln!("[core] audit violation: {:?}", violation);
+        self.violations.push(violation);
+    }
+
+    /// Checks that energy is conserved and that every entity stands on its
+    /// own valid cell.
+    pub fn check(
+        &mut self,
+        round: u32,
+        robot_id: usize,
+        board: &Board,
+        robots: &[Robot],
+        energy_stations: &[EnergyStation],
+    ) {
+        let total = robots.iter().map(|r| r.energy as u64).sum::<u64>()
+            + energy_stations.iter().map(|e| e.energy as u64).sum::<u64>();
+        if total != self.expected_total {
+            self.report(AuditViolation {
+                round,
+                robot_id,
+                kind: AuditViolationKind::EnergyNotConserved,
+                position: Position { q: 0, r: 0 },
+                expected: self.expected_total,
+                actual: total,
+            });
+            // Report every discrepancy only once
+            self.expected_total = total;
+        }
+
+        let mut occupied = HashSet::new();
+        let positions = robots
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (i, r.position))
+            .chain(energy_stations.iter().map(|e| (NO_ROBOT, e.position)));
+        for (id, position) in positions {
+            if id != NO_ROBOT && !board.is_valid_position(position) {
+                self.report(AuditViolation {
+                    round,
+                    robot_id: id,
+                    kind: AuditViolationKind::InvalidPosition,
+                    position,
+                    expected: 0,
+                    actual: 0,
+                });
+            }
+            if !occupied.insert((position.q, position.r)) {
+                self.report(AuditViolation {
+                    round,
+                    robot_id: id,
+                    kind: AuditViolationKind::OverlappingPositions,
+                    position,
+                    expected: 0,
+                    actual: 0,
+                });
+            }
+        }
+    }
+}
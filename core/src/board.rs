@@ -0,0 +1,244 @@
+use crate::{GameConfig, Position};
+use rand::Rng;
+
+pub use robotchallenge_types::{BOARD_SHAPE_HEXAGON, BOARD_SHAPE_RECTANGLE, BOARD_SHAPE_TORUS};
+
+fn axial_distance(a: Position, b: Position) -> i32 {
+    ((a.q - b.q).abs() + (a.q + a.r - b.q - b.r).abs() + (a.r - b.r).abs()) / 2
+}
+
+/// Converts axial coordinates to "odd-r" offset coordinates (column, row).
+fn axial_to_offset(hex: Position) -> (i32, i32) {
+    (hex.q + hex.r.div_euclid(2), hex.r)
+}
+
+fn offset_to_axial(col: i32, row: i32) -> Position {
+    Position {
+        q: col - row.div_euclid(2),
+        r: row,
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BoardShape {
+    /// Hexagon of radius `width` around (0, 0).
+    Hexagon,
+    /// `width` x `height` rectangle of hexes centered around (0, 0).
+    Rectangle,
+    /// Same as `Rectangle`, but the edges wrap around.
+    Torus,
+}
+
+impl BoardShape {
+    fn from_config(board_shape: u32) -> BoardShape {
+        match board_shape {
+            BOARD_SHAPE_HEXAGON => BoardShape::Hexagon,
+            BOARD_SHAPE_RECTANGLE => BoardShape::Rectangle,
+            BOARD_SHAPE_TORUS => BoardShape::Torus,
+            // Unknown shapes fall back to the classic board
+            _ => BoardShape::Hexagon,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Board {
+    shape: BoardShape,
+    width: i32,
+    height: i32,
+}
+
+impl Board {
+    pub fn new(config: &GameConfig) -> Board {
+        let shape = BoardShape::from_config(config.board_shape);
+        let height = if config.height > 0 {
+            config.height
+        } else {
+            config.width
+        };
+
+        // Wrapping rows of an "odd-r" layout only lines up with an even number
+        // of rows, configs with an odd one are rejected before they get here
+        Board {
+            shape,
+            width: config.width,
+            height,
+        }
+    }
+
+    fn min_col(&self) -> i32 {
+        -(self.width / 2)
+    }
+
+    fn min_row(&self) -> i32 {
+        -(self.height / 2)
+    }
+
+    /// Maps a position onto the board. On a torus every position is moved
+    /// inside the board's bounds, other shapes leave positions untouched.
+    pub fn normalize(&self, hex: Position) -> Position {
+        if self.shape != BoardShape::Torus {
+            return hex;
+        }
+
+        let (col, row) = axial_to_offset(hex);
+        offset_to_axial(
+            (col - self.min_col()).rem_euclid(self.width) + self.min_col(),
+            (row - self.min_row()).rem_euclid(self.height) + self.min_row(),
+        )
+    }
+
+    pub fn is_valid_position(&self, hex: Position) -> bool {
+        match self.shape {
+            BoardShape::Hexagon => axial_distance(hex, Position { q: 0, r: 0 }) < self.width,
+            BoardShape::Rectangle | BoardShape::Torus => {
+                let (col, row) = axial_to_offset(hex);
+                col >= self.min_col()
+                    && col < self.min_col() + self.width
+                    && row >= self.min_row()
+                    && row < self.min_row() + self.height
+            }
+        }
+    }
+
+    pub fn distance(&self, a: Position, b: Position) -> i32 {
+        if self.shape != BoardShape::Torus {
+            return axial_distance(a, b);
+        }
+
+        let a = self.normalize(a);
+        let (col, row) = axial_to_offset(self.normalize(b));
+        let mut distance = i32::MAX;
+        for d_col in [-self.width, 0, self.width] {
+            for d_row in [-self.height, 0, self.height] {
+                distance = distance.min(axial_distance(
+                    a,
+                    offset_to_axial(col + d_col, row + d_row),
+                ));
+            }
+        }
+
+        distance
+    }
+
+    /// Picks a random position to place a robot or a station on. The returned
+    /// position isn't guaranteed to be valid for hexagon boards.
+    pub fn random_position<R: Rng>(&self, rng: &mut R) -> Position {
+        match self.shape {
+            BoardShape::Hexagon => {
+                let q = rng.gen_range(-self.width..self.width);
+                let r = rng.gen_range(-self.width..self.width);
+                Position { q, r }
+            }
+            BoardShape::Rectangle | BoardShape::Torus => {
+                let col = rng.gen_range(self.min_col()..self.min_col() + self.width);
+                let row = rng.gen_range(self.min_row()..self.min_row() + self.height);
+                offset_to_axial(col, row)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIRECTIONS: [Position; 6] = [
+        Position { q: 1, r: 0 },
+        Position { q: 1, r: -1 },
+        Position { q: 0, r: -1 },
+        Position { q: -1, r: 0 },
+        Position { q: -1, r: 1 },
+        Position { q: 0, r: 1 },
+    ];
+
+    fn board(board_shape: u32, width: i32, height: i32) -> Board {
+        Board::new(&GameConfig {
+            board_shape,
+            width,
+            height,
+            ..GameConfig::default()
+        })
+    }
+
+    fn positions() -> impl Iterator<Item = Position> {
+        (-10..10).flat_map(|q| (-10..10).map(move |r| Position { q, r }))
+    }
+
+    fn valid_positions(board: &Board) -> usize {
+        positions()
+            .filter(|hex| board.is_valid_position(*hex))
+            .count()
+    }
+
+    #[test]
+    fn hexagon_contains_cells_closer_than_its_width() {
+        let board = board(BOARD_SHAPE_HEXAGON, 3, 0);
+        assert!(board.is_valid_position(Position { q: 0, r: 0 }));
+        assert!(board.is_valid_position(Position { q: 2, r: -2 }));
+        assert!(!board.is_valid_position(Position { q: 3, r: 0 }));
+        assert!(!board.is_valid_position(Position { q: 2, r: 1 }));
+        assert_eq!(valid_positions(&board), 19);
+    }
+
+    #[test]
+    fn rectangle_contains_its_rows_and_columns() {
+        let board = board(BOARD_SHAPE_RECTANGLE, 4, 2);
+        // Columns -2..=1, rows -1..=0
+        assert!(board.is_valid_position(Position { q: -2, r: 0 }));
+        assert!(board.is_valid_position(Position { q: 1, r: 0 }));
+        assert!(!board.is_valid_position(Position { q: 2, r: 0 }));
+        assert!(!board.is_valid_position(Position { q: 0, r: 1 }));
+        assert_eq!(valid_positions(&board), 8);
+        assert_eq!(
+            board.normalize(Position { q: 5, r: 5 }),
+            Position { q: 5, r: 5 }
+        );
+    }
+
+    #[test]
+    fn torus_wraps_columns_and_rows() {
+        let board = board(BOARD_SHAPE_TORUS, 4, 4);
+        assert_eq!(
+            board.normalize(Position { q: 2, r: 0 }),
+            Position { q: -2, r: 0 }
+        );
+        assert_eq!(
+            board.normalize(Position { q: 0, r: 2 }),
+            Position { q: 2, r: -2 }
+        );
+        for hex in positions() {
+            let normalized = board.normalize(hex);
+            assert!(board.is_valid_position(normalized), "{:?}", hex);
+            assert_eq!(board.normalize(normalized), normalized);
+        }
+    }
+
+    #[test]
+    fn torus_distance_goes_over_the_edges() {
+        let board = board(BOARD_SHAPE_TORUS, 4, 4);
+        let left = Position { q: -2, r: 0 };
+        let right = Position { q: 1, r: 0 };
+        assert_eq!(board.distance(left, right), 1);
+        assert_eq!(board.distance(right, left), 1);
+        assert_eq!(axial_distance(left, right), 3);
+
+        for hex in positions().map(|hex| board.normalize(hex)) {
+            assert_eq!(board.distance(hex, hex), 0);
+            for direction in DIRECTIONS {
+                let neighbor = board.normalize(Position {
+                    q: hex.q + direction.q,
+                    r: hex.r + direction.r,
+                });
+                assert_eq!(board.distance(hex, neighbor), 1, "{:?} {:?}", hex, neighbor);
+            }
+        }
+    }
+
+    #[test]
+    fn height_defaults_to_width() {
+        let board = board(BOARD_SHAPE_RECTANGLE, 4, 0);
+        assert!(board.is_valid_position(Position { q: 1, r: 1 }));
+        assert!(!board.is_valid_position(Position { q: 0, r: 2 }));
+    }
+}
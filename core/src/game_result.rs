@@ -0,0 +1,132 @@
+use crate::{EnergyStation, GameConfig, Robot};
+use std::collections::HashSet;
+
+/// Player id used when nobody won the game.
+pub const NO_PLAYER: u32 = u32::MAX;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameEndReason {
+    /// All `rounds_count` rounds were played
+    RoundsLimit,
+    /// Only one player has robots with energy left
+    LastPlayerStanding,
+    /// A player's robots have `target_energy` energy in total
+    TargetEnergy,
+    /// Every energy station is empty and doesn't recover
+    StationsExhausted,
+    /// No robot changed for `stale_rounds_limit` rounds
+    NoStateChange,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct GameResult {
+    /// Number of rounds played
+    pub rounds_played: u32,
+    pub reason: GameEndReason,
+    /// Player with the most energy, `NO_PLAYER` on a draw
+    pub winner: u32,
+}
+
+/// Total energy of every player's robots, indexed by owner.
+pub fn players_energy(config: &GameConfig, robots: &[Robot]) -> Vec<u64> {
+    let mut energy = vec![0u64; config.players_count as usize];
+    for robot in robots {
+        if let Some(total) = energy.get_mut(robot.owner as usize) {
+            *total += robot.energy as u64;
+        }
+    }
+    energy
+}
+
+fn winner(scores: &[u64]) -> u32 {
+    let Some(max) = scores.iter().max() else {
+        return NO_PLAYER;
+    };
+
+    let mut leaders = scores.iter().enumerate().filter(|(_, s)| *s == max);
+    match (leaders.next(), leaders.next()) {
+        (Some((owner, _)), None) => owner as u32,
+        _ => NO_PLAYER,
+    }
+}
+
+/// Checks the end conditions enabled in `config` once a round is finished,
+/// the player with the best score wins. Returns `None` if the game goes on.
+pub fn check_end(
+    config: &GameConfig,
+    rounds_played: u32,
+    unchanged_rounds: u32,
+    robots: &[Robot],
+    energy_stations: &[EnergyStation],
+    scores: &[u64],
+) -> Option<GameResult> {
+    let players_energy = players_energy(config, robots);
+    // Robots are never removed, a player is out once none of theirs has energy
+    let players_left = robots
+        .iter()
+        .filter(|robot| robot.owner < config.players_count && robot.energy > 0)
+        .map(|robot| robot.owner)
+        .collect::<HashSet<_>>()
+        .len();
+
+    let reason = if config.end_when_last_player_standing != 0
+        && config.players_count > 1
+        && players_left <= 1
+    {
+        GameEndReason::LastPlayerStanding
+    } else if config.target_energy != 0
+        && players_energy.iter().any(|e| *e >= config.target_energy as u64)
+    {
+        GameEndReason::TargetEnergy
+    } else if config.end_when_stations_exhausted != 0
+        // Stations have already recovered at this point, so an empty one never will
+        && energy_stations.iter().all(|e| e.energy == 0)
+    {
+        GameEndReason::StationsExhausted
+    } else if config.stale_rounds_limit != 0 && unchanged_rounds >= config.stale_rounds_limit {
+        GameEndReason::NoStateChange
+    } else if rounds_played >= config.rounds_count {
+        GameEndReason::RoundsLimit
+    } else {
+        return None;
+    };
+
+    Some(GameResult {
+        rounds_played,
+        reason,
+        winner: winner(scores),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn robot(owner: u32, energy: u32) -> Robot {
+        Robot {
+            position: Position { q: owner as i32, r: 0 },
+            energy,
+            owner,
+        }
+    }
+
+    #[test]
+    fn last_player_standing_ignores_robots_without_energy() {
+        let config = GameConfig {
+            players_count: 2,
+            end_when_last_player_standing: 1,
+            ..GameConfig::default()
+        };
+
+        let robots = [robot(0, 10), robot(1, 5)];
+        assert!(check_end(&config, 1, 0, &robots, &[], &[10, 5]).is_none());
+
+        let robots = [robot(0, 10), robot(1, 0)];
+        let result = check_end(&config, 1, 0, &robots, &[], &[10, 0]).unwrap();
+        assert_eq!(result.reason, GameEndReason::LastPlayerStanding);
+        assert_eq!(result.winner, 0);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{EnergyStation, Robot};
+
+/// Changes made to the map during a single round.
+#[derive(Debug)]
+struct RoundDelta {
+    /// Robots that changed or were cloned, by index
+    robots: Vec<(usize, Robot)>,
+    /// New energy of the stations that changed, by index
+    energy_stations: Vec<(usize, u32)>,
+    /// RNG word positions at the end of the round, see `GameRngs::word_positions`
+    rng_word_positions: Vec<u128>,
+}
+
+/// Compact history of the game allowing to rebuild the map of any finished round.
+#[derive(Debug)]
+pub struct History {
+    initial_robots: Vec<Robot>,
+    initial_energy_stations: Vec<EnergyStation>,
+    initial_rng_word_positions: Vec<u128>,
+    deltas: Vec<RoundDelta>,
+    last_robots: Vec<Robot>,
+    last_energy_stations: Vec<EnergyStation>,
+}
+
+fn robot_changed(a: &Robot, b: &Robot) -> bool {
+    a.energy != b.energy
+        || a.owner != b.owner
+        || a.position.q != b.position.q
+        || a.position.r != b.position.r
+}
+
+impl History {
+    pub fn new(
+        robots: &[Robot],
+        energy_stations: &[EnergyStation],
+        rng_word_positions: Vec<u128>,
+    ) -> History {
+        History {
+            initial_robots: robots.to_vec(),
+            initial_energy_stations: energy_stations.to_vec(),
+            initial_rng_word_positions: rng_word_positions,
+            deltas: vec![],
+            last_robots: robots.to_vec(),
+            last_energy_stations: energy_stations.to_vec(),
+        }
+    }
+
+    /// Number of finished rounds stored.
+    pub fn rounds(&self) -> u32 {
+        self.deltas.len() as u32
+    }
+
+    /// Number of the last finished rounds in which no robot changed.
+    pub fn unchanged_rounds(&self) -> u32 {
+        self.deltas
+            .iter()
+            .rev()
+            .take_while(|delta| delta.robots.is_empty())
+            .count() as u32
+    }
+
+    pub fn push_round(
+        &mut self,
+        robots: &[Robot],
+        energy_stations: &[EnergyStation],
+        rng_word_positions: Vec<u128>,
+    ) {
+        let changed_robots = robots
+            .iter()
+            .enumerate()
+            .filter(|(i, robot)| match self.last_robots.get(*i) {
+                Some(last) => robot_changed(last, robot),
+                None => true,
+            })
+            .map(|(i, robot)| (i, *robot))
+            .collect();
+        let changed_energy_stations = energy_stations
+            .iter()
+            .enumerate()
+            .filter(|(i, e)| self.last_energy_stations[*i].energy != e.energy)
+            .map(|(i, e)| (i, e.energy))
+            .collect();
+
+        self.deltas.push(RoundDelta {
+            robots: changed_robots,
+            energy_stations: changed_energy_stations,
+            rng_word_positions,
+        });
+        self.last_robots = robots.to_vec();
+        self.last_energy_stations = energy_stations.to_vec();
+    }
+
+    /// Rebuilds the map as it was after `round` rounds were finished.
+    pub fn map_at(&self, round: u32) -> Option<(Vec<Robot>, Vec<EnergyStation>, Vec<u128>)> {
+        if round > self.rounds() {
+            return None;
+        }
+
+        let mut robots = self.initial_robots.clone();
+        let mut energy_stations = self.initial_energy_stations.clone();
+        let mut rng_word_positions = self.initial_rng_word_positions.clone();
+        for delta in &self.deltas[..round as usize] {
+            for (i, robot) in &delta.robots {
+                if *i < robots.len() {
+                    robots[*i] = *robot;
+                } else {
+                    robots.push(*robot);
+                }
+            }
+            for (i, energy) in &delta.energy_stations {
+                energy_stations[*i].energy = *energy;
+            }
+            rng_word_positions = delta.rng_word_positions.clone();
+        }
+
+        Some((robots, energy_stations, rng_word_positions))
+    }
+
+    /// Forgets everything after `round` so the game can continue from there.
+    pub fn truncate(&mut self, round: u32) {
+        if let Some((robots, energy_stations, _)) = self.map_at(round) {
+            self.deltas.truncate(round as usize);
+            self.last_robots = robots;
+            self.last_energy_stations = energy_stations;
+        }
+    }
+}
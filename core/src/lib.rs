@@ -1,49 +1,29 @@
+mod audit;
+mod board;
+mod game_result;
+mod history;
+mod rng;
+mod rules;
+mod scenario;
+mod turn_order;
+
+use audit::{Audit, AuditViolation, EnergyLedger, LedgerEntry, NO_ROBOT};
+use board::Board;
+use game_result::GameResult;
+use history::History;
 use lazy_static::lazy_static;
 use rand::prelude::*;
-use rand_chacha::ChaCha8Rng;
+use rng::GameRngs;
+use rules::{Action, GameRules};
+use scenario::Scenario;
+use turn_order::TurnOrder;
 use std::collections::HashMap;
-use std::os::raw::c_void;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
 use std::panic;
 use std::sync::RwLock;
 
-#[derive(Debug, Copy, Clone)]
-#[repr(C)]
-pub struct Robot {
-    position: Position,
-    energy: u32,
-    owner: u32,
-}
-
-#[derive(Debug)]
-#[repr(C)]
-pub struct EnergyStation {
-    position: Position,
-    recovery_rate: u32,
-    energy: u32,
-}
-
-#[repr(C)]
-pub struct MapFFI {
-    robots_len: usize,
-    robots: *const Robot,
-    energy_stations_len: usize,
-    energy_stations: *const EnergyStation,
-}
-
-#[repr(C)]
-#[derive(Debug, Clone)]
-pub struct GameConfig {
-    width: i32,
-    rounds_count: u32,
-    players_count: u32,
-    initial_robots_count: u32,
-    start_energy: u32,
-    rng_seed: u32,
-    energy_stations_per_robot: u32,
-    energy_loss_to_clone_robot: u32,
-    max_robots_count: u32,
-    energy_collect_distance: i32,
-}
+pub use robotchallenge_types::{EnergyStation, GameConfig, MapFFI, Position, Robot, ABI_VERSION};
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -92,6 +72,22 @@ pub struct Timeout {
     is_timeout_too_much: bool,
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TransferEnergy {
+    robot_id: usize,
+    target_robot_id: usize,
+    amount: u32,
+    fee: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TransferEnergyFailed {
+    robot_id: usize,
+    target_robot_id: usize,
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub enum PlayerActions {
@@ -102,6 +98,45 @@ pub enum PlayerActions {
     CollectEnergy(CollectEnergy),
     CollectEnergyFailed(CollectEnergyFailed),
     Timeout(Timeout),
+    TransferEnergy(TransferEnergy),
+    TransferEnergyFailed(TransferEnergyFailed),
+}
+
+#[derive(Debug, Copy, Clone)]
+enum ActionKind {
+    Move,
+    CollectEnergy,
+    CloneRobot,
+    TransferEnergy,
+}
+
+/// Action point rules, see `GameConfig::action_points`.
+trait ActionPoints {
+    fn turn_action_points(&self) -> u32;
+    fn action_cost(&self, kind: ActionKind) -> u32;
+}
+
+impl ActionPoints for GameConfig {
+    fn turn_action_points(&self) -> u32 {
+        match self.action_points {
+            0 => 1,
+            action_points => action_points,
+        }
+    }
+
+    fn action_cost(&self, kind: ActionKind) -> u32 {
+        if self.action_points == 0 {
+            return 1;
+        }
+
+        let cost = match kind {
+            ActionKind::Move => self.move_action_points,
+            ActionKind::CollectEnergy => self.collect_energy_action_points,
+            ActionKind::CloneRobot => self.clone_robot_action_points,
+            ActionKind::TransferEnergy => self.transfer_energy_action_points,
+        };
+        cost.max(1)
+    }
 }
 
 #[derive(Debug)]
@@ -110,10 +145,21 @@ struct GameState {
     robots: Vec<Robot>,
     energy_stations: Vec<EnergyStation>,
     config: GameConfig,
-    rng: ChaCha8Rng,
+    rules: &'static dyn GameRules,
+    board: Board,
+    rngs: GameRngs,
     current_robot_index: usize,
-    current_robot_done_action: bool,
+    /// Indices of the robots in the order they act this round
+    turn_order: Vec<usize>,
+    turn_index: usize,
+    current_robot_action_points: u32,
     player_actions: HashMap<u32, Vec<PlayerActions>>,
+    audit: Option<Audit>,
+    history: History,
+    /// Set once the game is over, see `get_game_result`
+    result: Option<GameResult>,
+    /// Map returned by the last `get_map_at` call, kept alive for the caller
+    past_map: (Vec<Robot>, Vec<EnergyStation>),
 }
 
 #[repr(C)]
@@ -122,62 +168,213 @@ pub struct PlayerActionsFFI {
     player_actions_values: *const PlayerActions,
 }
 
-#[derive(Debug, Copy, Clone)]
 #[repr(C)]
-pub struct Position {
-    q: i32,
-    r: i32,
+pub struct AuditViolationsFFI {
+    audit_violations_len: usize,
+    audit_violations_values: *const AuditViolation,
 }
 
 fn console_error_panic_hook(info: &panic::PanicInfo) {
     eprintln!("{}", info);
 }
 
-const AXIAL_DIRECTION_VECTORS: [Position; 6] = [
-    Position { q: 1, r: 0 },
-    Position { q: 1, r: -1 },
-    Position { q: 0, r: -1 },
-    Position { q: -1, r: 0 },
-    Position { q: -1, r: 1 },
-    Position { q: 0, r: 1 },
-];
-
-fn axial_direction(direction: usize) -> Position {
-    AXIAL_DIRECTION_VECTORS[direction]
-}
-
-fn axial_add(hex: Position, vec: Position) -> Position {
-    Position {
-        q: hex.q + vec.q,
-        r: hex.r + vec.r,
-    }
-}
-
-fn axial_neighbor(hex: Position, direction: usize) -> Position {
-    axial_add(hex, axial_direction(direction))
-}
-
-fn axial_distance(a: Position, b: Position) -> i32 {
-    (((a.q - b.q).abs() + (a.q + a.r - b.q - b.r).abs() + (a.r - b.r).abs()) / 2)
-        .try_into()
-        .unwrap()
-}
-
 impl GameState {
     fn new(config: GameConfig) -> GameState {
         GameState {
             round: 0,
             robots: vec![],
             energy_stations: vec![],
-            rng: ChaCha8Rng::seed_from_u64(config.rng_seed as u64),
+            rngs: GameRngs::new(config.rng_seed),
+            board: Board::new(&config),
+            rules: rules::rules_for_mode(config.game_mode),
+            current_robot_action_points: config.turn_action_points(),
             config: config,
             current_robot_index: 0,
-            current_robot_done_action: false,
+            turn_order: vec![],
+            turn_index: 0,
             player_actions: HashMap::new(),
+            audit: None,
+            history: History::new(&[], &[], vec![]),
+            result: None,
+            past_map: (vec![], vec![]),
+        }
+    }
+
+    fn start(&mut self) {
+        self.start_audit();
+        self.history = History::new(
+            &self.robots,
+            &self.energy_stations,
+            self.rngs.word_positions(),
+        );
+        self.begin_round();
+    }
+
+    fn begin_round(&mut self) {
+        self.rules.round_start(self);
+        self.turn_order = TurnOrder::from_config(self.config.turn_order).robots_order(
+            self.round,
+            &self.robots,
+            self.config.players_count,
+            &mut self.rngs.turn_order,
+        );
+        self.turn_index = 0;
+        self.begin_turn();
+    }
+
+    fn begin_turn(&mut self) {
+        self.current_robot_index = match self.turn_order.get(self.turn_index) {
+            Some(robot_index) => *robot_index,
+            None => self.robots.len(),
+        };
+        self.current_robot_action_points = self.config.turn_action_points();
+    }
+
+    fn end_turn(&mut self) {
+        self.turn_index += 1;
+        self.begin_turn();
+    }
+
+    /// Lets the rules validate and apply an action of the current robot. Failed
+    /// actions are recorded, but don't use up action points.
+    fn do_action(&mut self, action: Action) {
+        let cost = self.config.action_cost(action.kind());
+        if self.current_robot_action_points < cost {
+            println!("[core] Robot tried to do action without enough action points");
+            return;
+        }
+
+        let rules = self.rules;
+        if let Err(reason) = rules.validate_action(self, action) {
+            println!("[core] {:?} failed: {}", action, reason);
+            self.add_player_action(action.failed(self.current_robot_index));
+            return;
+        }
+
+        let robots_count = self.robots.len();
+        let player_action = rules.apply_action(self, action);
+        if self.config.clones_wait_a_round == 0 {
+            self.turn_order.extend(robots_count..self.robots.len());
+        }
+        println!("[core] {:?}", player_action);
+        self.add_player_action(player_action);
+
+        self.check_invariants(self.current_robot_index);
+        self.current_robot_action_points -= cost;
+    }
+
+    fn add_player_action(&mut self, action: PlayerActions) {
+        self.player_actions
+            .entry(self.round)
+            .or_default()
+            .push(action);
+    }
+
+    /// Ends the round once every robot had its turn and starts the next one,
+    /// unless the game is over.
+    fn finish_round(&mut self) {
+        let rules = self.rules;
+        rules.round_end(self);
+        self.check_invariants(NO_ROBOT);
+        self.round += 1;
+        self.history.push_round(
+            &self.robots,
+            &self.energy_stations,
+            self.rngs.word_positions(),
+        );
+        self.result = rules.check_end(self);
+        // The finished round is still reported, but no new one starts
+        if self.result.is_none() {
+            self.begin_round();
+        }
+    }
+
+    /// Restores the map after `round` finished rounds and continues the game from there.
+    fn rewind(&mut self, round: u32) -> bool {
+        let Some((robots, energy_stations, rng_word_positions)) = self.history.map_at(round) else {
+            return false;
+        };
+
+        self.history.truncate(round);
+        self.robots = robots;
+        self.energy_stations = energy_stations;
+        self.rngs.set_word_positions(&rng_word_positions);
+        self.round = round;
+        self.result = None;
+        self.player_actions.retain(|r, _| *r < round);
+        let total_energy = self.total_energy();
+        if let Some(audit) = self.audit.as_mut() {
+            audit.truncate(round, total_energy);
+        }
+        self.begin_round();
+        true
+    }
+
+    fn total_energy(&self) -> u64 {
+        self.robots.iter().map(|r| r.energy as u64).sum::<u64>()
+            + self.energy_stations.iter().map(|e| e.energy as u64).sum::<u64>()
+    }
+
+    fn start_audit(&mut self) {
+        if self.config.audit != 0 {
+            self.audit = Some(Audit::new(self.total_energy()));
+        }
+    }
+
+    fn record_energy(&mut self, entry: LedgerEntry) {
+        if let Some(audit) = self.audit.as_mut() {
+            audit.record(self.round, entry);
+        }
+    }
+
+    fn check_invariants(&mut self, robot_id: usize) {
+        if let Some(audit) = self.audit.as_mut() {
+            audit.check(
+                self.round,
+                robot_id,
+                &self.board,
+                &self.robots,
+                &self.energy_stations,
+            );
+        }
+    }
+
+    /// Adds `amount` to `energy`, saturating instead of overflowing.
+    fn add_energy(&mut self, robot_id: usize, position: Position, energy: u32, amount: u64) -> u32 {
+        let sum = energy as u64 + amount;
+        if sum <= u32::MAX as u64 {
+            return sum as u32;
+        }
+
+        if let Some(audit) = self.audit.as_mut() {
+            audit.report(AuditViolation {
+                round: self.round,
+                robot_id,
+                kind: audit::AuditViolationKind::EnergyOverflow,
+                position,
+                expected: sum,
+                actual: u32::MAX as u64,
+            });
+        }
+        u32::MAX
+    }
+
+    fn recover_energy_stations(&mut self) {
+        for i in 0..self.energy_stations.len() {
+            let station = self.energy_stations[i];
+            let energy = self.add_energy(
+                NO_ROBOT,
+                station.position,
+                station.energy,
+                station.recovery_rate as u64,
+            );
+            self.energy_stations[i].energy = energy;
+            self.record_energy(LedgerEntry::Recovered((energy - station.energy) as u64));
         }
     }
 
     fn is_empty(&self, q: i32, r: i32) -> bool {
+        let Position { q, r } = self.board.normalize(Position { q, r });
         self.is_valid_position(q, r)
             && !self
                 .robots
@@ -190,11 +387,13 @@ impl GameState {
     }
 
     fn is_valid_position(&self, q: i32, r: i32) -> bool {
-        axial_distance(Position { q, r }, Position { q: 0, r: 0 }) < self.config.width
+        self.board.is_valid_position(Position { q, r })
     }
 
     fn calculate_loss(&self, q: i32, r: i32, new_q: i32, new_r: i32) -> u32 {
-        return axial_distance(Position { q, r }, Position { q: new_q, r: new_r }) as u32;
+        return self
+            .board
+            .distance(Position { q, r }, Position { q: new_q, r: new_r }) as u32;
     }
 
     fn get_robots_by_owner(&self, owner: u32) -> Vec<&Robot> {
@@ -207,11 +406,13 @@ impl GameState {
         while distance <= self.config.width {
             for dx in -distance..=distance {
                 for dy in -distance..=distance {
-                    let new_q = near_q + dx;
-                    let new_r = near_r + dy;
+                    let Position { q: new_q, r: new_r } = self.board.normalize(Position {
+                        q: near_q + dx,
+                        r: near_r + dy,
+                    });
 
                     if !self.is_empty(new_q, new_r)
-                        || axial_distance(
+                        || self.board.distance(
                             Position {
                                 q: near_q,
                                 r: near_r,
@@ -236,7 +437,8 @@ impl GameState {
         self.energy_stations
             .iter_mut()
             .filter(|e| {
-                axial_distance(e.position, Position { q, r }) <= self.config.energy_collect_distance
+                self.board.distance(e.position, Position { q, r })
+                    <= self.config.energy_collect_distance
             })
             .collect()
     }
@@ -251,18 +453,49 @@ impl GameState {
         return self.robots.len() - 1;
     }
 
+    /// Places the scenario's entities, at their normalized position so the
+    /// same cell can't be taken twice on a torus.
+    fn load_scenario(&mut self, scenario: Scenario) -> Result<(), String> {
+        for energy_station in scenario.energy_stations {
+            let Position { q, r } = energy_station.position;
+            if !self.is_empty(q, r) {
+                return Err(format!("energy station at ({}, {}) is not on a free cell", q, r));
+            }
+            self.energy_stations.push(EnergyStation {
+                position: self.board.normalize(energy_station.position),
+                ..energy_station
+            });
+        }
+
+        for robot in scenario.robots {
+            let Position { q, r } = robot.position;
+            if robot.owner >= self.config.players_count {
+                return Err(format!(
+                    "robot at ({}, {}) belongs to player {}, but there are only {} players",
+                    q, r, robot.owner, self.config.players_count
+                ));
+            }
+            if !self.is_empty(q, r) {
+                return Err(format!("robot at ({}, {}) is not on a free cell", q, r));
+            }
+            let Position { q, r } = self.board.normalize(robot.position);
+            self.add_robot(robot.owner, q, r, robot.energy);
+        }
+
+        Ok(())
+    }
+
     fn generate(&mut self) {
         let energy_stations_count = self.config.initial_robots_count
             * self.config.players_count
             * self.config.energy_stations_per_robot;
 
         for _ in 0..energy_stations_count {
-            let q = self.rng.gen_range(-self.config.width..self.config.width);
-            let r = self.rng.gen_range(-self.config.width..self.config.width);
+            let Position { q, r } = self.board.random_position(&mut self.rngs.map);
             if self.is_empty(q, r) {
                 self.energy_stations.push(EnergyStation {
                     position: Position { q, r },
-                    recovery_rate: self.rng.gen_range(1..10), // TODO calculate
+                    recovery_rate: self.rngs.map.gen_range(1..10), // TODO calculate
                     energy: 200,                              // TODO calculate
                 });
             }
@@ -271,8 +504,7 @@ impl GameState {
         for _ in 0..self.config.initial_robots_count {
             for owner in 0..self.config.players_count {
                 loop {
-                    let q = (&mut self.rng).gen_range(-self.config.width..self.config.width);
-                    let r = (&mut self.rng).gen_range(-self.config.width..self.config.width);
+                    let Position { q, r } = self.board.random_position(&mut self.rngs.map);
                     if self.is_empty(q, r) {
                         self.add_robot(owner, q, r, self.config.start_energy);
 
@@ -322,21 +554,55 @@ mod imports {
     }
 }
 
+/// Bots exporting another version can't be played against this core.
+#[no_mangle]
+pub fn abi_version() -> u32 {
+    ABI_VERSION
+}
+
 #[no_mangle]
 pub fn init_mod() {
     panic::set_hook(Box::new(console_error_panic_hook));
 }
 
+/// # Safety
+///
+/// `ptr` has to point to a `GameConfig`.
 #[no_mangle]
-pub fn init_game(ptr: *const GameConfig) {
-    let config = unsafe { &*ptr };
-    *CURRENT_GAME_STATE.write().unwrap() = Some(GameState::new((*config).clone()));
+pub unsafe fn init_game(ptr: *const GameConfig) {
+    let config = &*ptr;
+    *CURRENT_GAME_STATE.write().unwrap() = Some(GameState::new(*config));
     let guard = &mut *CURRENT_GAME_STATE.write().unwrap();
     let game_state = guard.as_mut().unwrap();
     game_state.generate();
+    game_state.start();
     // println!("{:#?}", game_state);
 }
 
+/// Same as `init_game`, but places robots and energy stations from a scenario
+/// instead of generating them. Returns false if the scenario is invalid.
+///
+/// # Safety
+///
+/// `ptr` has to point to a `GameConfig` and `scenario` to a nul terminated
+/// string.
+#[no_mangle]
+pub unsafe fn init_game_with_scenario(ptr: *const GameConfig, scenario: *const c_char) -> bool {
+    let config = &*ptr;
+    let scenario = CStr::from_ptr(scenario).to_string_lossy();
+
+    let mut game_state = GameState::new(*config);
+    let result = Scenario::parse(&scenario).and_then(|s| game_state.load_scenario(s));
+    if let Err(error) = result {
+        eprintln!("[core] invalid scenario: {}", error);
+        return false;
+    }
+    game_state.start();
+
+    *CURRENT_GAME_STATE.write().unwrap() = Some(game_state);
+    true
+}
+
 macro_rules! with_game_state {
     ($name: ident, $block: block) => {{
         let lol = &mut *CURRENT_GAME_STATE.write().unwrap();
@@ -358,18 +624,14 @@ pub fn do_round() {
     println!("[core] do_round");
 
     with_game_state_drop!(game_state, lock, {
-        if (game_state.round >= game_state.config.rounds_count) {
+        if game_state.result.is_some() {
             eprintln!("[core] do_round: game is over");
             return;
         }
 
-        if (game_state.current_robot_index >= game_state.robots.len()) {
+        if game_state.turn_index >= game_state.turn_order.len() {
             println!("[core] do_round finished");
-            game_state.current_robot_index = 0;
-            game_state.round += 1;
-            game_state.energy_stations.iter_mut().for_each(|e| {
-                e.energy += e.recovery_rate;
-            });
+            game_state.finish_round();
             unsafe {
                 imports::round_finished(
                     get_map_ffi(game_state),
@@ -394,276 +656,289 @@ pub fn do_round() {
     println!("[core] do_round done");
 }
 
-macro_rules! add_player_action {
-    ($game_state: ident, $action: expr) => {
-        (*$game_state
-            .player_actions
-            .entry($game_state.round)
-            .or_insert(Vec::new()))
-        .push($action);
-    };
-}
-
 #[no_mangle]
 pub fn done_step(is_timeout: bool, is_timeout_too_much: bool) {
     with_game_state!(game_state, {
-        if (is_timeout && !game_state.current_robot_done_action) {
-            add_player_action!(
-                game_state,
-                PlayerActions::Timeout(Timeout {
-                    robot_id: game_state.current_robot_index,
-                    is_timeout_too_much,
-                })
-            );
+        // Robots that acted before timing out aren't reported
+        let acted =
+            game_state.current_robot_action_points < game_state.config.turn_action_points();
+        if is_timeout && !acted {
+            game_state.add_player_action(PlayerActions::Timeout(Timeout {
+                robot_id: game_state.current_robot_index,
+                is_timeout_too_much,
+            }));
             println!("[core] done_step (timeout) {:?}", is_timeout_too_much);
         } else {
             println!("[core] done_step");
         }
-        game_state.current_robot_index += 1;
-        game_state.current_robot_done_action = false;
+        game_state.end_turn();
     });
 
     do_round();
 }
 
-macro_rules! game_action {
-    ($name: ident, $current_robot_name: ident, $block: block) => {
-        with_game_state!($name, {
-            if($name.current_robot_done_action) {
-                println!("[core] Robot tried to do action twice");
-                return;
-            }
-
-            let $current_robot_name = &$name.robots[$name.current_robot_index];
-
-            $block
-
-            $name.current_robot_done_action = true;
-
-            return;
-        });
-    }
+fn do_action(action: Action) {
+    with_game_state!(game_state, { game_state.do_action(action) });
 }
 
 #[no_mangle]
 pub fn clone_robot(new_bot_energy: u32) {
-    game_action!(game_state, current_robot, {
-        let loss = game_state.config.energy_loss_to_clone_robot + new_bot_energy;
-
-        if (new_bot_energy == 0) {
-            add_player_action!(
-                game_state,
-                PlayerActions::CloneRobotFailed(CloneRobotFailed {
-                    robot_id: game_state.current_robot_index,
-                })
-            );
-            return;
-        }
-
-        if (current_robot.energy < loss) {
-            println!("Robot tried to clone with too much energy");
-            add_player_action!(
-                game_state,
-                PlayerActions::CloneRobotFailed(CloneRobotFailed {
-                    robot_id: game_state.current_robot_index,
-                })
-            );
-            return;
-        }
+    do_action(Action::CloneRobot { new_bot_energy });
+}
 
-        if (game_state.get_robots_by_owner(current_robot.owner).len()
-            >= game_state.config.max_robots_count.try_into().unwrap())
-        {
-            println!("Robot tried to clone too many robots");
-            add_player_action!(
-                game_state,
-                PlayerActions::CloneRobotFailed(CloneRobotFailed {
-                    robot_id: game_state.current_robot_index,
-                })
-            );
-            return;
-        }
+#[no_mangle]
+pub fn collect_energy() {
+    do_action(Action::CollectEnergy);
+}
 
-        let free_cell =
-            game_state.find_free_cell(current_robot.position.q, current_robot.position.r);
+#[no_mangle]
+pub fn move_robot(q: i32, r: i32) -> () {
+    let position = with_game_state!(game_state, { game_state.board.normalize(Position { q, r }) });
+    do_action(Action::Move(position));
+}
 
-        if (free_cell.is_none()) {
-            println!("Robot tried to clone but there is no free cell");
-            add_player_action!(
-                game_state,
-                PlayerActions::CloneRobotFailed(CloneRobotFailed {
-                    robot_id: game_state.current_robot_index,
-                })
-            );
-            return;
-        }
+/// Gives `amount` energy to an adjacent robot of the same player. The sender
+/// additionally loses `energy_transfer_fee`.
+#[no_mangle]
+pub fn transfer_energy(target_robot: usize, amount: u32) {
+    do_action(Action::TransferEnergy {
+        target_robot,
+        amount,
+    });
+}
 
-        let free_cell = free_cell.unwrap();
+#[no_mangle]
+pub fn allocate(length: usize) -> *mut c_void {
+    let mut v = Vec::with_capacity(length);
+    let ptr = v.as_mut_ptr();
+    std::mem::forget(v);
+    ptr
+}
 
-        let new_robot_index = game_state.add_robot(
-            current_robot.owner,
-            free_cell.q,
-            free_cell.r,
-            new_bot_energy,
-        );
+#[no_mangle]
+pub fn deallocate(ptr: *mut c_void, length: usize) {
+    unsafe {
+        std::mem::drop(Vec::from_raw_parts(ptr, 0, length));
+    }
+}
 
-        let current_robot = &mut game_state.robots[game_state.current_robot_index];
-        current_robot.energy -= loss;
+#[no_mangle]
+pub fn get_map() -> *mut MapFFI {
+    let guard = &mut *CURRENT_GAME_STATE.write().unwrap();
+    let game_state = guard.as_ref().unwrap();
 
-        let new_robot = &game_state.robots[new_robot_index];
-        let current_robot = &game_state.robots[game_state.current_robot_index];
+    get_map_ffi(game_state)
+}
 
-        add_player_action!(
-            game_state,
-            PlayerActions::CloneRobot(CloneRobot {
-                robot_id: game_state.current_robot_index,
-                new_robot: new_robot.clone(),
-            })
-        );
+/// Sets up a game without generating a map, for copies of the core that only
+/// answer bot queries about maps passed to `load_snapshot`.
+///
+/// # Safety
+///
+/// `ptr` has to point to a `GameConfig`.
+#[no_mangle]
+pub unsafe fn init_queries(ptr: *const GameConfig) {
+    *CURRENT_GAME_STATE.write().unwrap() = Some(GameState::new(*ptr));
+}
 
-        println!("[core] clone_robot {:?} {:?}", current_robot, new_robot);
+/// Replaces the map with a snapshot and makes `robot_index` the current robot.
+/// Player workers keep their own copy of the core to answer bot queries, and
+/// apply the bot's actions to it as well so queries see them like on the server.
+///
+/// # Safety
+///
+/// `map` has to point to a `MapFFI` whose arrays hold the given number of
+/// robots and energy stations.
+#[no_mangle]
+pub unsafe fn load_snapshot(map: *const MapFFI, robot_index: usize, round: u32) {
+    let map = &*map;
+    with_game_state!(game_state, {
+        game_state.robots = match map.robots_len {
+            0 => vec![],
+            len => std::slice::from_raw_parts(map.robots, len).to_vec(),
+        };
+        game_state.energy_stations = match map.energy_stations_len {
+            0 => vec![],
+            len => std::slice::from_raw_parts(map.energy_stations, len).to_vec(),
+        };
+        game_state.current_robot_index = robot_index;
+        game_state.current_robot_action_points = game_state.config.turn_action_points();
+        game_state.round = round;
+        game_state.player_actions.clear();
     });
 }
 
+/// # Safety
+///
+/// `out` has to point to memory for a `GameConfig`.
 #[no_mangle]
-pub fn collect_energy() {
-    game_action!(game_state, current_robot, {
-        println!("[core] collect_energy {:?}", current_robot);
-
-        let energy_stations_around = game_state
-            .get_energy_stations_around(current_robot.position.q, current_robot.position.r);
-
-        if energy_stations_around.is_empty() {
-            add_player_action!(
-                game_state,
-                PlayerActions::CollectEnergyFailed(CollectEnergyFailed {
-                    robot_id: game_state.current_robot_index,
-                })
-            );
-            println!("Robot tried to collect energy but there is no energy station around");
-            return;
-        }
-
-        if !energy_stations_around.iter().any(|e| e.energy > 0) {
-            add_player_action!(
-                game_state,
-                PlayerActions::CollectEnergyFailed(CollectEnergyFailed {
-                    robot_id: game_state.current_robot_index,
-                })
-            );
-            println!("Robot tried to collect energy but there is no energy in energy stations");
-            return;
-        }
+pub unsafe fn query_config(out: *mut GameConfig) {
+    with_game_state!(game_state, {
+        out.write(game_state.config);
+    });
+}
 
-        let mut total_energy = 0;
-        for energy_station in energy_stations_around {
-            let energy = energy_station.energy;
-            energy_station.energy = 0;
-            total_energy += energy;
-        }
+/// Writes indices of the current player's robots into `out` and returns how
+/// many there are, which may be more than `capacity`.
+///
+/// # Safety
+///
+/// `out` has to point to memory for `capacity` indices, it's not read when
+/// `capacity` is 0.
+#[no_mangle]
+pub unsafe fn query_my_robots(out: *mut usize, capacity: usize) -> usize {
+    with_game_state!(game_state, {
+        let owner = match game_state.robots.get(game_state.current_robot_index) {
+            Some(robot) => robot.owner,
+            None => return 0,
+        };
+        let robots: Vec<usize> = game_state
+            .robots
+            .iter()
+            .enumerate()
+            .filter(|(_, robot)| robot.owner == owner)
+            .map(|(i, _)| i)
+            .collect();
+
+        write_indices(indices_out(out, capacity), &robots)
+    })
+}
 
-        let current_robot = &mut game_state.robots[game_state.current_robot_index];
-        current_robot.energy += total_energy;
+#[no_mangle]
+pub fn query_is_cell_free(q: i32, r: i32) -> bool {
+    with_game_state!(game_state, { game_state.is_empty(q, r) })
+}
 
-        add_player_action!(
-            game_state,
-            PlayerActions::CollectEnergy(CollectEnergy {
-                robot_id: game_state.current_robot_index,
-            })
-        );
-    });
+#[no_mangle]
+pub fn query_move_cost(from_q: i32, from_r: i32, to_q: i32, to_r: i32) -> u32 {
+    with_game_state!(game_state, {
+        game_state.calculate_loss(from_q, from_r, to_q, to_r)
+    })
 }
 
+/// Writes indices of energy stations a robot at (q, r) would collect from into
+/// `out` and returns how many there are, which may be more than `capacity`.
+///
+/// # Safety
+///
+/// Same as `query_my_robots`.
 #[no_mangle]
-pub fn move_robot(q: i32, r: i32) -> () {
-    game_action!(game_state, current_robot, {
-        let old_q = current_robot.position.q;
-        let old_r = current_robot.position.r;
-        let energy = current_robot.energy;
-
-        if old_q == q && old_r == r {
-            println!("Robot tried to move to the same cell");
-            add_player_action!(
-                game_state,
-                PlayerActions::PlayerActionMoveFailed(PlayerActionMoveFailed {
-                    robot_id: game_state.current_robot_index,
-                    new_position: Position { q, r },
-                })
-            );
-            return;
-        }
+pub unsafe fn query_stations_in_range(q: i32, r: i32, out: *mut usize, capacity: usize) -> usize {
+    with_game_state!(game_state, {
+        let stations: Vec<usize> = game_state
+            .energy_stations
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| {
+                game_state.board.distance(e.position, Position { q, r })
+                    <= game_state.config.energy_collect_distance
+            })
+            .map(|(i, _)| i)
+            .collect();
 
-        if !game_state.is_empty(q, r) {
-            add_player_action!(
-                game_state,
-                PlayerActions::PlayerActionMoveFailed(PlayerActionMoveFailed {
-                    robot_id: game_state.current_robot_index,
-                    new_position: Position { q, r },
-                })
-            );
-            println!("[core] move_robot cell is occupied {:?}", current_robot);
-            return;
-        }
+        write_indices(indices_out(out, capacity), &stations)
+    })
+}
 
-        let loss = game_state.calculate_loss(old_q, old_r, q, r);
+/// Buffer a query writes its indices to, empty when `capacity` is 0 since
+/// hosts may pass a null `out` then.
+///
+/// # Safety
+///
+/// Same as `query_my_robots`.
+unsafe fn indices_out<'a>(out: *mut usize, capacity: usize) -> &'a mut [usize] {
+    match capacity {
+        0 => &mut [],
+        _ => std::slice::from_raw_parts_mut(out, capacity),
+    }
+}
 
-        if loss > energy {
-            add_player_action!(
-                game_state,
-                PlayerActions::PlayerActionMoveFailed(PlayerActionMoveFailed {
-                    robot_id: game_state.current_robot_index,
-                    new_position: Position { q, r },
-                })
-            );
-            println!("[core] not enough energy {:?}", current_robot);
-            return;
-        }
+fn write_indices(out: &mut [usize], indices: &[usize]) -> usize {
+    let count = indices.len().min(out.len());
+    out[..count].copy_from_slice(&indices[..count]);
+    indices.len()
+}
 
-        let current_robot = &mut game_state.robots[game_state.current_robot_index];
-        current_robot.position.q = q;
-        current_robot.position.r = r;
-        current_robot.energy -= loss;
+/// Returns the map as it was after `round` finished rounds, round 0 being the
+/// initial map. The map is empty if the round wasn't played yet.
+#[no_mangle]
+pub fn get_map_at(round: u32) -> *mut MapFFI {
+    with_game_state!(game_state, {
+        game_state.past_map = game_state
+            .history
+            .map_at(round)
+            .map(|(robots, energy_stations, _)| (robots, energy_stations))
+            .unwrap_or_default();
+
+        Box::into_raw(Box::new(MapFFI {
+            robots_len: game_state.past_map.0.len(),
+            robots: game_state.past_map.0.as_ptr() as *mut Robot,
+            energy_stations_len: game_state.past_map.1.len(),
+            energy_stations: game_state.past_map.1.as_ptr() as *mut EnergyStation,
+        }))
+    })
+}
 
-        add_player_action!(
-            game_state,
-            PlayerActions::PlayerActionMove(PlayerActionMove {
-                robot_id: game_state.current_robot_index,
-                new_position: Position { q, r },
-                loss,
-            })
-        );
-    });
+/// Rewinds the game to the map after `round` finished rounds, dropping
+/// everything played after it. Returns false if the round wasn't played yet.
+#[no_mangle]
+pub fn rewind(round: u32) -> bool {
+    with_game_state!(game_state, { game_state.rewind(round) })
 }
 
 #[no_mangle]
-pub fn allocate(length: usize) -> *mut c_void {
-    let mut v = Vec::with_capacity(length);
-    let ptr = v.as_mut_ptr();
-    std::mem::forget(v);
-    ptr
+pub fn is_game_over() -> bool {
+    with_game_state!(game_state, { game_state.result.is_some() })
 }
 
+/// Returns why and after which round the game ended. Returns null while the
+/// game is still running.
 #[no_mangle]
-pub fn deallocate(ptr: *mut c_void, length: usize) {
-    unsafe {
-        std::mem::drop(Vec::from_raw_parts(ptr, 0, length));
-    }
+pub fn get_game_result() -> *mut GameResult {
+    with_game_state!(game_state, {
+        match game_state.result {
+            Some(result) => Box::into_raw(Box::new(result)),
+            None => std::ptr::null_mut(),
+        }
+    })
 }
 
+/// Returns the violations found in audit mode, empty if it's disabled.
 #[no_mangle]
-pub fn get_map() -> *mut MapFFI {
-    let guard = &mut *CURRENT_GAME_STATE.write().unwrap();
-    let game_state = guard.as_ref().unwrap();
+pub fn get_audit_violations() -> *mut AuditViolationsFFI {
+    with_game_state!(game_state, {
+        let violations = match game_state.audit.as_ref() {
+            Some(audit) => audit.violations(),
+            None => &[],
+        };
 
-    get_map_ffi(game_state)
+        Box::into_raw(Box::new(AuditViolationsFFI {
+            audit_violations_len: violations.len(),
+            audit_violations_values: violations.as_ptr(),
+        }))
+    })
+}
+
+/// Returns the energy sources and sinks of a round in audit mode.
+#[no_mangle]
+pub fn get_energy_ledger(round: u32) -> *mut EnergyLedger {
+    with_game_state!(game_state, {
+        let ledger = game_state
+            .audit
+            .as_ref()
+            .map(|audit| audit.ledger(round))
+            .unwrap_or_default();
+
+        Box::into_raw(Box::new(ledger))
+    })
 }
 
 fn get_map_ffi(game_state: &GameState) -> *mut MapFFI {
     let map_ffi = MapFFI {
         robots_len: game_state.robots.len(),
-        robots: game_state.robots.as_ptr(),
+        robots: game_state.robots.as_ptr() as *mut Robot,
         energy_stations_len: game_state.energy_stations.len(),
-        energy_stations: game_state.energy_stations.as_ptr(),
+        energy_stations: game_state.energy_stations.as_ptr() as *mut EnergyStation,
     };
 
     Box::into_raw(Box::new(map_ffi))
@@ -692,3 +967,327 @@ fn get_player_actions_ffi(game_state: &GameState, key: &u32) -> *mut PlayerActio
 
     Box::into_raw(Box::new(player_actions_ffi))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torus_game() -> GameState {
+        GameState::new(GameConfig {
+            board_shape: board::BOARD_SHAPE_TORUS,
+            width: 4,
+            height: 4,
+            players_count: 1,
+            ..GameConfig::default()
+        })
+    }
+
+    #[test]
+    fn scenario_positions_are_normalized() {
+        let mut game_state = torus_game();
+        let scenario = Scenario::parse("robot 0 2 0 10\nstation 0 2 100 1").unwrap();
+        game_state.load_scenario(scenario).unwrap();
+        assert_eq!(game_state.robots[0].position, Position { q: -2, r: 0 });
+        assert_eq!(game_state.energy_stations[0].position, Position { q: 2, r: -2 });
+    }
+
+    fn audited_game() -> GameState {
+        let mut game_state = GameState::new(GameConfig {
+            players_count: 2,
+            turn_order: turn_order::TURN_ORDER_RANDOM,
+            audit: 1,
+            ..GameConfig::default()
+        });
+        game_state.generate();
+        game_state.start();
+        game_state
+    }
+
+    /// Plays `rounds` rounds in which robots collect energy, move and clone
+    /// themselves in turn. Returns the map after each of them.
+    fn play_rounds(
+        game_state: &mut GameState,
+        rounds: u32,
+    ) -> Vec<(Vec<Robot>, Vec<EnergyStation>)> {
+        (0..rounds)
+            .map(|_| {
+                while game_state.turn_index < game_state.turn_order.len() {
+                    let Position { q, r } =
+                        game_state.robots[game_state.current_robot_index].position;
+                    game_state.do_action(match game_state.round % 3 {
+                        0 => Action::CollectEnergy,
+                        1 => Action::Move(Position { q: q + 1, r }),
+                        _ => Action::CloneRobot { new_bot_energy: 1 },
+                    });
+                    game_state.end_turn();
+                }
+                game_state.finish_round();
+                (
+                    game_state.robots.clone(),
+                    game_state.energy_stations.clone(),
+                )
+            })
+            .collect()
+    }
+
+    fn ledgers(game_state: &GameState, rounds: u32) -> Vec<EnergyLedger> {
+        let audit = game_state.audit.as_ref().unwrap();
+        (0..rounds).map(|round| audit.ledger(round)).collect()
+    }
+
+    #[test]
+    fn rewound_game_matches_a_fresh_one() {
+        let mut fresh = audited_game();
+        play_rounds(&mut fresh, 3);
+        let mut rewound = audited_game();
+        play_rounds(&mut rewound, 6);
+
+        assert!(rewound.rewind(3));
+        assert_eq!(rewound.round, 3);
+        assert_eq!(rewound.robots, fresh.robots);
+        assert_eq!(rewound.energy_stations, fresh.energy_stations);
+        assert_eq!(rewound.turn_order, fresh.turn_order);
+        assert_eq!(rewound.rngs.word_positions(), fresh.rngs.word_positions());
+        assert_eq!(ledgers(&rewound, 6), ledgers(&fresh, 6));
+        let mut rounds: Vec<u32> = rewound.player_actions.keys().copied().collect();
+        rounds.sort();
+        assert_eq!(rounds, vec![0, 1, 2]);
+
+        // Both continue the same way, down to the random turn order
+        assert_eq!(play_rounds(&mut rewound, 3), play_rounds(&mut fresh, 3));
+        assert_eq!(ledgers(&rewound, 6), ledgers(&fresh, 6));
+        assert!(rewound.audit.as_ref().unwrap().violations().is_empty());
+        assert_eq!(
+            rewound.rngs.turn_order.gen::<u64>(),
+            fresh.rngs.turn_order.gen::<u64>()
+        );
+    }
+
+    /// Plays a round in which the robots do `actions` in turn order.
+    fn play_round(game_state: &mut GameState, actions: &[Action]) {
+        for action in actions {
+            game_state.do_action(*action);
+            game_state.end_turn();
+        }
+        game_state.finish_round();
+    }
+
+    /// Owner and robot of every action recorded for `round`, and whether it failed.
+    fn recorded_actions(game_state: &GameState, round: u32) -> Vec<(u32, usize, bool)> {
+        game_state.player_actions[&round]
+            .iter()
+            .map(|action| {
+                let (robot_id, failed) = match *action {
+                    PlayerActions::PlayerActionMove(a) => (a.robot_id, false),
+                    PlayerActions::PlayerActionMoveFailed(a) => (a.robot_id, true),
+                    PlayerActions::CollectEnergy(a) => (a.robot_id, false),
+                    PlayerActions::CloneRobotFailed(a) => (a.robot_id, true),
+                    _ => panic!("Unexpected action {:?}", action),
+                };
+                (game_state.robots[robot_id].owner, robot_id, failed)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn audit_records_actions_per_round() {
+        let mut game_state = GameState::new(GameConfig {
+            audit: 1,
+            ..GameConfig::default()
+        });
+        let scenario = Scenario::parse("robot 0 0 0 100\nrobot 1 3 0 100\nstation 1 0 50 5");
+        game_state.load_scenario(scenario.unwrap()).unwrap();
+        game_state.start();
+
+        play_round(
+            &mut game_state,
+            &[Action::CollectEnergy, Action::Move(Position { q: 4, r: 0 })],
+        );
+        // The station's cell is taken and clones can't be empty
+        play_round(
+            &mut game_state,
+            &[
+                Action::Move(Position { q: 1, r: 0 }),
+                Action::CloneRobot { new_bot_energy: 0 },
+            ],
+        );
+        let round_0 = EnergyLedger {
+            recovered: 5,
+            collected: 50,
+            move_loss: 1,
+            ..EnergyLedger::default()
+        };
+        let round_1 = EnergyLedger {
+            recovered: 5,
+            ..EnergyLedger::default()
+        };
+        assert_eq!(ledgers(&game_state, 2), vec![round_0, round_1]);
+        assert_eq!(
+            recorded_actions(&game_state, 0),
+            vec![(0, 0, false), (1, 1, false)]
+        );
+        assert_eq!(
+            recorded_actions(&game_state, 1),
+            vec![(0, 0, true), (1, 1, true)]
+        );
+
+        play_round(
+            &mut game_state,
+            &[Action::CollectEnergy, Action::Move(Position { q: 5, r: 0 })],
+        );
+        let round_2 = EnergyLedger {
+            recovered: 5,
+            collected: 10,
+            move_loss: 1,
+            ..EnergyLedger::default()
+        };
+        assert_eq!(ledgers(&game_state, 3), vec![round_0, round_1, round_2]);
+        assert!(game_state.audit.as_ref().unwrap().violations().is_empty());
+
+        assert!(game_state.rewind(1));
+        assert_eq!(
+            ledgers(&game_state, 3),
+            vec![round_0, EnergyLedger::default(), EnergyLedger::default()]
+        );
+        assert!(!game_state.player_actions.contains_key(&1));
+    }
+
+    #[test]
+    fn cant_rewind_to_an_unplayed_round() {
+        let mut game_state = audited_game();
+        play_rounds(&mut game_state, 2);
+        assert!(!game_state.rewind(3));
+        assert_eq!(game_state.round, 2);
+    }
+
+    #[test]
+    fn get_map_at_returns_the_map_of_each_round() {
+        let mut game_state = audited_game();
+        let initial_map = (
+            game_state.robots.clone(),
+            game_state.energy_stations.clone(),
+        );
+        let mut maps = vec![initial_map];
+        maps.extend(play_rounds(&mut game_state, 4));
+        // The only test using the global game
+        *CURRENT_GAME_STATE.write().unwrap() = Some(game_state);
+
+        for (round, (robots, energy_stations)) in maps.iter().enumerate() {
+            let map = unsafe { Box::from_raw(get_map_at(round as u32)) };
+            let map_robots = unsafe { std::slice::from_raw_parts(map.robots, map.robots_len) };
+            let map_energy_stations =
+                unsafe { std::slice::from_raw_parts(map.energy_stations, map.energy_stations_len) };
+            assert_eq!(map_robots, &robots[..], "Robots after round {}", round);
+            assert_eq!(
+                map_energy_stations,
+                &energy_stations[..],
+                "Stations after round {}",
+                round
+            );
+        }
+
+        let map = unsafe { Box::from_raw(get_map_at(5)) };
+        assert_eq!((map.robots_len, map.energy_stations_len), (0, 0));
+    }
+
+    fn seeded_game() -> GameState {
+        GameState::new(GameConfig {
+            width: 4,
+            initial_robots_count: 2,
+            rng_seed: 42,
+            energy_stations_per_robot: 1,
+            ..GameConfig::default()
+        })
+    }
+
+    /// Owner and position of every robot, position and recovery rate of every station
+    type MapSummary = (Vec<(u32, i32, i32)>, Vec<(i32, i32, u32)>);
+
+    fn map_summary(game_state: &GameState) -> MapSummary {
+        (
+            game_state
+                .robots
+                .iter()
+                .map(|robot| (robot.owner, robot.position.q, robot.position.r))
+                .collect(),
+            game_state
+                .energy_stations
+                .iter()
+                .map(|station| (station.position.q, station.position.r, station.recovery_rate))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn generated_map_matches_the_single_rng_baseline() {
+        // Generated with the single game RNG used before the streams were split
+        let baseline = (
+            vec![(0, 2, 1), (1, 2, 0), (0, 0, -1), (1, 3, -2)],
+            vec![(1, -3, 9), (2, -1, 4), (1, -2, 2)],
+        );
+        let mut game_state = seeded_game();
+        game_state.generate();
+        assert_eq!(map_summary(&game_state), baseline);
+
+        let mut game_state = seeded_game();
+        game_state.rngs.turn_order.gen::<u64>();
+        game_state.rngs.events.gen::<u64>();
+        game_state.generate();
+        assert_eq!(map_summary(&game_state), baseline);
+    }
+
+    /// Lets the first robot clone itself, then plays the rest of the round
+    /// without actions. Returns the robots that got a turn.
+    fn robots_acting_after_a_clone(turn_order: u32, clones_wait_a_round: u32) -> Vec<usize> {
+        let mut game_state = GameState::new(GameConfig {
+            turn_order,
+            clones_wait_a_round,
+            ..GameConfig::default()
+        });
+        let scenario = Scenario::parse("robot 0 0 0 100\nrobot 1 4 0 100").unwrap();
+        game_state.load_scenario(scenario).unwrap();
+        game_state.start();
+
+        let mut acting = vec![];
+        while game_state.turn_index < game_state.turn_order.len() {
+            acting.push(game_state.current_robot_index);
+            if acting.len() == 1 {
+                game_state.do_action(Action::CloneRobot { new_bot_energy: 10 });
+                assert_eq!(game_state.robots.len(), 3);
+            }
+            game_state.end_turn();
+        }
+        acting
+    }
+
+    #[test]
+    fn clones_act_in_the_round_they_are_created() {
+        assert_eq!(
+            robots_acting_after_a_clone(turn_order::TURN_ORDER_SEQUENTIAL, 0),
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            robots_acting_after_a_clone(turn_order::TURN_ORDER_INTERLEAVED, 0),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn clones_can_wait_for_the_next_round() {
+        assert_eq!(
+            robots_acting_after_a_clone(turn_order::TURN_ORDER_SEQUENTIAL, 1),
+            vec![0, 1]
+        );
+        assert_eq!(
+            robots_acting_after_a_clone(turn_order::TURN_ORDER_REVERSE_ALTERNATING, 1),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn scenario_cant_take_a_cell_twice_on_a_torus() {
+        let mut game_state = torus_game();
+        let scenario = Scenario::parse("robot 0 -2 0 10\nrobot 0 2 0 10").unwrap();
+        assert!(game_state.load_scenario(scenario).is_err());
+    }
+}
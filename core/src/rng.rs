@@ -0,0 +1,71 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Random number streams derived from `GameConfig::rng_seed`.
+///
+/// Every subsystem draws from its own ChaCha stream, so adding a random
+/// mechanic never shifts the numbers another subsystem gets for the same
+/// seed. The map stream is the default stream of the seed, which keeps maps
+/// identical to the ones generated before the streams were split.
+#[derive(Debug)]
+pub struct GameRngs {
+    /// Map generation
+    pub map: ChaCha8Rng,
+    /// Turn order of robots within a round
+    pub turn_order: ChaCha8Rng,
+    /// Random events happening while the game runs
+    pub events: ChaCha8Rng,
+}
+
+// Never renumber the streams, that would change the games of existing seeds
+const MAP_STREAM: u64 = 0;
+const TURN_ORDER_STREAM: u64 = 1;
+const EVENTS_STREAM: u64 = 2;
+
+fn stream(seed: u32, stream: u64) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed as u64);
+    rng.set_stream(stream);
+    rng
+}
+
+impl GameRngs {
+    pub fn new(seed: u32) -> GameRngs {
+        GameRngs {
+            map: stream(seed, MAP_STREAM),
+            turn_order: stream(seed, TURN_ORDER_STREAM),
+            events: stream(seed, EVENTS_STREAM),
+        }
+    }
+
+    /// Positions of all streams, used to restore them when rewinding the game.
+    pub fn word_positions(&self) -> Vec<u128> {
+        vec![
+            self.map.get_word_pos(),
+            self.turn_order.get_word_pos(),
+            self.events.get_word_pos(),
+        ]
+    }
+
+    pub fn set_word_positions(&mut self, word_positions: &[u128]) {
+        self.map.set_word_pos(word_positions[0]);
+        self.turn_order.set_word_pos(word_positions[1]);
+        self.events.set_word_pos(word_positions[2]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn map_stream_is_the_default_stream_of_the_seed() {
+        let mut rngs = GameRngs::new(42);
+        let mut single = ChaCha8Rng::seed_from_u64(42);
+        rngs.turn_order.gen::<u64>();
+        rngs.events.gen::<u64>();
+        let map: Vec<u32> = (0..8).map(|_| rngs.map.gen()).collect();
+        let expected: Vec<u32> = (0..8).map(|_| single.gen()).collect();
+        assert_eq!(map, expected);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::audit::LedgerEntry;
+use crate::game_result::{self, GameResult};
+use crate::{
+    ActionKind, CloneRobot, CloneRobotFailed, CollectEnergy, CollectEnergyFailed, GameState,
+    PlayerActionMove, PlayerActionMoveFailed, PlayerActions, Position, TransferEnergy,
+    TransferEnergyFailed,
+};
+use std::fmt::Debug;
+
+pub use robotchallenge_types::GAME_MODE_CLASSIC;
+
+/// Action requested by the robot whose turn it is.
+#[derive(Debug, Copy, Clone)]
+pub enum Action {
+    /// Target position, already normalized for the board
+    Move(Position),
+    CollectEnergy,
+    CloneRobot { new_bot_energy: u32 },
+    TransferEnergy { target_robot: usize, amount: u32 },
+}
+
+impl Action {
+    pub fn kind(&self) -> ActionKind {
+        match self {
+            Action::Move(_) => ActionKind::Move,
+            Action::CollectEnergy => ActionKind::CollectEnergy,
+            Action::CloneRobot { .. } => ActionKind::CloneRobot,
+            Action::TransferEnergy { .. } => ActionKind::TransferEnergy,
+        }
+    }
+
+    /// Player action recorded when the rules reject this action.
+    pub fn failed(&self, robot_id: usize) -> PlayerActions {
+        match *self {
+            Action::Move(new_position) => {
+                PlayerActions::PlayerActionMoveFailed(PlayerActionMoveFailed {
+                    robot_id,
+                    new_position,
+                })
+            }
+            Action::CollectEnergy => {
+                PlayerActions::CollectEnergyFailed(CollectEnergyFailed { robot_id })
+            }
+            Action::CloneRobot { .. } => {
+                PlayerActions::CloneRobotFailed(CloneRobotFailed { robot_id })
+            }
+            Action::TransferEnergy { target_robot, .. } => {
+                PlayerActions::TransferEnergyFailed(TransferEnergyFailed {
+                    robot_id,
+                    target_robot_id: target_robot,
+                })
+            }
+        }
+    }
+}
+
+/// Rules of a game mode.
+///
+/// Every hook defaults to the classic rules, so a new mode only overrides
+/// what it changes. Register new modes in `rules_for_mode`.
+pub trait GameRules: Debug + Sync {
+    /// Checks whether the current robot may do `action`, returning the reason
+    /// if it may not.
+    fn validate_action(&self, game_state: &GameState, action: Action) -> Result<(), &'static str> {
+        let robot = &game_state.robots[game_state.current_robot_index];
+
+        match action {
+            Action::Move(Position { q, r }) => {
+                if robot.position.q == q && robot.position.r == r {
+                    return Err("robot tried to move to the same cell");
+                }
+                if !game_state.is_empty(q, r) {
+                    return Err("cell is occupied");
+                }
+                if game_state.calculate_loss(robot.position.q, robot.position.r, q, r)
+                    > robot.energy
+                {
+                    return Err("not enough energy");
+                }
+            }
+            Action::CollectEnergy => {
+                let stations_around: Vec<_> = game_state
+                    .energy_stations
+                    .iter()
+                    .filter(|e| {
+                        game_state.board.distance(e.position, robot.position)
+                            <= game_state.config.energy_collect_distance
+                    })
+                    .collect();
+                if stations_around.is_empty() {
+                    return Err("there is no energy station around");
+                }
+                if !stations_around.iter().any(|e| e.energy > 0) {
+                    return Err("there is no energy in energy stations");
+                }
+            }
+            Action::CloneRobot { new_bot_energy } => {
+                if new_bot_energy == 0 {
+                    return Err("clone can't have zero energy");
+                }
+                if (robot.energy as u64)
+                    < game_state.config.energy_loss_to_clone_robot as u64 + new_bot_energy as u64
+                {
+                    return Err("robot tried to clone with too much energy");
+                }
+                if game_state.get_robots_by_owner(robot.owner).len()
+                    >= game_state.config.max_robots_count as usize
+                {
+                    return Err("robot tried to clone too many robots");
+                }
+                if game_state
+                    .find_free_cell(robot.position.q, robot.position.r)
+                    .is_none()
+                {
+                    return Err("there is no free cell");
+                }
+            }
+            Action::TransferEnergy {
+                target_robot,
+                amount,
+            } => {
+                let Some(target) = game_state.robots.get(target_robot) else {
+                    return Err("target robot doesn't exist");
+                };
+                if amount == 0 {
+                    return Err("nothing to transfer");
+                }
+                if target_robot == game_state.current_robot_index {
+                    return Err("robot can't transfer energy to itself");
+                }
+                if target.owner != robot.owner {
+                    return Err("target robot belongs to another player");
+                }
+                if game_state.board.distance(robot.position, target.position) != 1 {
+                    return Err("target robot isn't adjacent");
+                }
+                if (robot.energy as u64)
+                    < amount as u64 + game_state.config.energy_transfer_fee as u64
+                {
+                    return Err("not enough energy");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies an action that passed `validate_action` and returns what
+    /// happened. Robots added here get a turn according to `clones_wait_a_round`.
+    fn apply_action(&self, game_state: &mut GameState, action: Action) -> PlayerActions {
+        let robot_id = game_state.current_robot_index;
+        let robot = game_state.robots[robot_id];
+
+        match action {
+            Action::Move(new_position) => {
+                let loss = game_state.calculate_loss(
+                    robot.position.q,
+                    robot.position.r,
+                    new_position.q,
+                    new_position.r,
+                );
+                let current_robot = &mut game_state.robots[robot_id];
+                current_robot.position = new_position;
+                current_robot.energy -= loss;
+                game_state.record_energy(LedgerEntry::MoveLoss(loss as u64));
+
+                PlayerActions::PlayerActionMove(PlayerActionMove {
+                    robot_id,
+                    new_position,
+                    loss,
+                })
+            }
+            Action::CollectEnergy => {
+                let mut total_energy: u64 = 0;
+                for energy_station in
+                    game_state.get_energy_stations_around(robot.position.q, robot.position.r)
+                {
+                    total_energy += energy_station.energy as u64;
+                    energy_station.energy = 0;
+                }
+
+                let energy =
+                    game_state.add_energy(robot_id, robot.position, robot.energy, total_energy);
+                game_state.robots[robot_id].energy = energy;
+                game_state.record_energy(LedgerEntry::Collected(total_energy));
+
+                PlayerActions::CollectEnergy(CollectEnergy { robot_id })
+            }
+            Action::CloneRobot { new_bot_energy } => {
+                let loss_to_clone = game_state.config.energy_loss_to_clone_robot;
+                let free_cell = game_state
+                    .find_free_cell(robot.position.q, robot.position.r)
+                    .unwrap();
+                let new_robot_index =
+                    game_state.add_robot(robot.owner, free_cell.q, free_cell.r, new_bot_energy);
+
+                game_state.robots[robot_id].energy -= loss_to_clone + new_bot_energy;
+                game_state.record_energy(LedgerEntry::Cloned(new_bot_energy as u64));
+                game_state.record_energy(LedgerEntry::CloneLoss(loss_to_clone as u64));
+
+                PlayerActions::CloneRobot(CloneRobot {
+                    robot_id,
+                    new_robot: game_state.robots[new_robot_index],
+                })
+            }
+            Action::TransferEnergy {
+                target_robot,
+                amount,
+            } => {
+                let fee = game_state.config.energy_transfer_fee;
+                game_state.robots[robot_id].energy -= amount + fee;
+                let target = game_state.robots[target_robot];
+                let energy =
+                    game_state.add_energy(target_robot, target.position, target.energy, amount as u64);
+                game_state.robots[target_robot].energy = energy;
+                game_state.record_energy(LedgerEntry::Transferred(amount as u64));
+                game_state.record_energy(LedgerEntry::TransferLoss(fee as u64));
+
+                PlayerActions::TransferEnergy(TransferEnergy {
+                    robot_id,
+                    target_robot_id: target_robot,
+                    amount,
+                    fee,
+                })
+            }
+        }
+    }
+
+    /// Called before the turn order of a round is decided.
+    fn round_start(&self, _game_state: &mut GameState) {}
+
+    /// Called after every robot acted in a round.
+    fn round_end(&self, game_state: &mut GameState) {
+        game_state.recover_energy_stations();
+    }
+
+    /// Score of every player, indexed by owner. The best score wins the game.
+    fn scores(&self, game_state: &GameState) -> Vec<u64> {
+        game_result::players_energy(&game_state.config, &game_state.robots)
+    }
+
+    /// Decides whether the game is over once a round has finished.
+    fn check_end(&self, game_state: &GameState) -> Option<GameResult> {
+        game_result::check_end(
+            &game_state.config,
+            game_state.round,
+            game_state.history.unchanged_rounds(),
+            &game_state.robots,
+            &game_state.energy_stations,
+            &self.scores(game_state),
+        )
+    }
+}
+
+/// Current rules of the game.
+#[derive(Debug)]
+pub struct ClassicRules;
+
+impl GameRules for ClassicRules {}
+
+pub fn rules_for_mode(game_mode: u32) -> &'static dyn GameRules {
+    match game_mode {
+        GAME_MODE_CLASSIC => &ClassicRules,
+        // Unknown modes fall back to the classic rules
+        _ => &ClassicRules,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+    use crate::GameConfig;
+
+    /// Robot 0 acts first. Robot 1 is its neighbour, robot 2 a neighbour of
+    /// another player and robot 3 belongs to the same player, but is too far.
+    fn transfer_game() -> GameState {
+        let mut game_state = GameState::new(GameConfig {
+            energy_transfer_fee: 2,
+            ..GameConfig::default()
+        });
+        let scenario =
+            Scenario::parse("robot 0 0 0 100\nrobot 0 1 0 10\nrobot 1 0 1 10\nrobot 0 3 0 10");
+        game_state.load_scenario(scenario.unwrap()).unwrap();
+        game_state.start();
+        game_state
+    }
+
+    fn validate_transfer(target_robot: usize, amount: u32) -> Result<(), &'static str> {
+        ClassicRules.validate_action(
+            &transfer_game(),
+            Action::TransferEnergy {
+                target_robot,
+                amount,
+            },
+        )
+    }
+
+    #[test]
+    fn transfer_needs_an_adjacent_robot_of_the_same_player() {
+        assert_eq!(validate_transfer(3, 10), Err("target robot isn't adjacent"));
+        assert_eq!(
+            validate_transfer(2, 10),
+            Err("target robot belongs to another player")
+        );
+        assert_eq!(
+            validate_transfer(0, 10),
+            Err("robot can't transfer energy to itself")
+        );
+        assert_eq!(validate_transfer(4, 10), Err("target robot doesn't exist"));
+    }
+
+    #[test]
+    fn transfer_needs_a_positive_affordable_amount() {
+        assert_eq!(validate_transfer(1, 0), Err("nothing to transfer"));
+        // Amount and fee together don't leave enough energy
+        assert_eq!(validate_transfer(1, 99), Err("not enough energy"));
+        // Wraps around to a small cost in u32 arithmetic
+        assert_eq!(validate_transfer(1, u32::MAX - 1), Err("not enough energy"));
+        assert_eq!(validate_transfer(1, 98), Ok(()));
+    }
+
+    #[test]
+    fn transfer_moves_energy_and_loses_the_fee() {
+        let mut game_state = transfer_game();
+        let action = Action::TransferEnergy {
+            target_robot: 1,
+            amount: 30,
+        };
+        assert_eq!(ClassicRules.validate_action(&game_state, action), Ok(()));
+
+        let player_action = ClassicRules.apply_action(&mut game_state, action);
+        assert!(matches!(
+            player_action,
+            PlayerActions::TransferEnergy(TransferEnergy {
+                robot_id: 0,
+                target_robot_id: 1,
+                amount: 30,
+                fee: 2,
+            })
+        ));
+        assert_eq!(game_state.robots[0].energy, 68);
+        assert_eq!(game_state.robots[1].energy, 40);
+    }
+}
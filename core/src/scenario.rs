@@ -0,0 +1,104 @@
+use crate::{EnergyStation, Position, Robot};
+
+/// Handcrafted map used instead of the random generation.
+///
+/// Scenarios are written one entity per line, `#` starts a comment:
+///
+/// ```text
+/// # Collect all energy in 20 rounds
+/// robot 0 0 0 100      # owner q r energy
+/// station 2 -1 200 5   # q r energy recovery_rate
+/// ```
+#[derive(Debug)]
+pub struct Scenario {
+    pub robots: Vec<Robot>,
+    pub energy_stations: Vec<EnergyStation>,
+}
+
+fn parse_values<T: std::str::FromStr>(
+    line_no: usize,
+    kind: &str,
+    values: &[&str],
+    names: &[&str],
+) -> Result<Vec<T>, String> {
+    if values.len() != names.len() {
+        return Err(format!(
+            "line {}: `{}` expects {} values ({}), got {}",
+            line_no,
+            kind,
+            names.len(),
+            names.join(" "),
+            values.len()
+        ));
+    }
+
+    values
+        .iter()
+        .zip(names)
+        .map(|(value, name)| {
+            value
+                .parse::<T>()
+                .map_err(|_| format!("line {}: invalid {} `{}`", line_no, name, value))
+        })
+        .collect()
+}
+
+impl Scenario {
+    pub fn parse(text: &str) -> Result<Scenario, String> {
+        let mut scenario = Scenario {
+            robots: vec![],
+            energy_stations: vec![],
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.split('#').next().unwrap_or_default();
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let Some((kind, values)) = tokens.split_first() else {
+                continue;
+            };
+
+            match *kind {
+                "robot" => {
+                    let values =
+                        parse_values::<i64>(line_no, kind, values, &["owner", "q", "r", "energy"])?;
+                    scenario.robots.push(Robot {
+                        position: Position {
+                            q: to_coordinate(line_no, values[1])?,
+                            r: to_coordinate(line_no, values[2])?,
+                        },
+                        energy: to_amount(line_no, values[3])?,
+                        owner: to_amount(line_no, values[0])?,
+                    });
+                }
+                "station" => {
+                    let values = parse_values::<i64>(
+                        line_no,
+                        kind,
+                        values,
+                        &["q", "r", "energy", "recovery_rate"],
+                    )?;
+                    scenario.energy_stations.push(EnergyStation {
+                        position: Position {
+                            q: to_coordinate(line_no, values[0])?,
+                            r: to_coordinate(line_no, values[1])?,
+                        },
+                        energy: to_amount(line_no, values[2])?,
+                        recovery_rate: to_amount(line_no, values[3])?,
+                    });
+                }
+                _ => return Err(format!("line {}: unknown entity `{}`", line_no, kind)),
+            }
+        }
+
+        Ok(scenario)
+    }
+}
+
+fn to_coordinate(line_no: usize, value: i64) -> Result<i32, String> {
+    i32::try_from(value).map_err(|_| format!("line {}: coordinate {} is out of range", line_no, value))
+}
+
+fn to_amount(line_no: usize, value: i64) -> Result<u32, String> {
+    u32::try_from(value).map_err(|_| format!("line {}: value {} is out of range", line_no, value))
+}
@@ -0,0 +1,132 @@
+use crate::Robot;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+
+pub use robotchallenge_types::{
+    TURN_ORDER_INTERLEAVED, TURN_ORDER_RANDOM, TURN_ORDER_REVERSE_ALTERNATING,
+    TURN_ORDER_SEQUENTIAL,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TurnOrder {
+    /// Robots act in the order they were created
+    Sequential,
+    /// One robot of each player at a time, players in ascending order
+    Interleaved,
+    /// Shuffled every round using the turn order RNG stream
+    Random,
+    /// Like `Interleaved`, but players go in descending order every other round
+    ReverseAlternating,
+}
+
+impl TurnOrder {
+    pub fn from_config(turn_order: u32) -> TurnOrder {
+        match turn_order {
+            TURN_ORDER_SEQUENTIAL => TurnOrder::Sequential,
+            TURN_ORDER_INTERLEAVED => TurnOrder::Interleaved,
+            TURN_ORDER_RANDOM => TurnOrder::Random,
+            TURN_ORDER_REVERSE_ALTERNATING => TurnOrder::ReverseAlternating,
+            _ => TurnOrder::Sequential,
+        }
+    }
+
+    /// Returns indices of the robots in the order they act during `round`.
+    pub fn robots_order(
+        self,
+        round: u32,
+        robots: &[Robot],
+        players_count: u32,
+        rng: &mut ChaCha8Rng,
+    ) -> Vec<usize> {
+        match self {
+            TurnOrder::Sequential => (0..robots.len()).collect(),
+            TurnOrder::Interleaved => interleave(robots, players_count, false),
+            TurnOrder::Random => {
+                let mut order: Vec<usize> = (0..robots.len()).collect();
+                order.shuffle(rng);
+                order
+            }
+            TurnOrder::ReverseAlternating => interleave(robots, players_count, round % 2 == 1),
+        }
+    }
+}
+
+fn interleave(robots: &[Robot], players_count: u32, reverse: bool) -> Vec<usize> {
+    let mut by_owner: Vec<Vec<usize>> = vec![vec![]; players_count as usize];
+    for (i, robot) in robots.iter().enumerate() {
+        if let Some(owned) = by_owner.get_mut(robot.owner as usize) {
+            owned.push(i);
+        }
+    }
+    if reverse {
+        by_owner.reverse();
+    }
+
+    let longest = by_owner.iter().map(Vec::len).max().unwrap_or(0);
+    (0..longest)
+        .flat_map(|i| by_owner.iter().filter_map(move |owned| owned.get(i).copied()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+    use rand::SeedableRng;
+
+    fn robots(owners: &[u32]) -> Vec<Robot> {
+        owners
+            .iter()
+            .map(|&owner| Robot {
+                position: Position { q: 0, r: 0 },
+                energy: 10,
+                owner,
+            })
+            .collect()
+    }
+
+    fn order(turn_order: TurnOrder, round: u32) -> Vec<usize> {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        turn_order.robots_order(round, &robots(&[0, 0, 1, 1, 1]), 2, &mut rng)
+    }
+
+    #[test]
+    fn sequential_follows_creation_order() {
+        assert_eq!(order(TurnOrder::Sequential, 0), vec![0, 1, 2, 3, 4]);
+        assert_eq!(order(TurnOrder::Sequential, 1), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn interleaved_alternates_players() {
+        assert_eq!(order(TurnOrder::Interleaved, 0), vec![0, 2, 1, 3, 4]);
+        assert_eq!(order(TurnOrder::Interleaved, 1), vec![0, 2, 1, 3, 4]);
+    }
+
+    #[test]
+    fn reverse_alternating_reverses_players_on_odd_rounds() {
+        assert_eq!(order(TurnOrder::ReverseAlternating, 0), vec![0, 2, 1, 3, 4]);
+        assert_eq!(order(TurnOrder::ReverseAlternating, 1), vec![2, 0, 3, 1, 4]);
+        assert_eq!(order(TurnOrder::ReverseAlternating, 2), vec![0, 2, 1, 3, 4]);
+    }
+
+    #[test]
+    fn random_is_a_seeded_permutation() {
+        let robots = robots(&[0, 0, 1, 1, 1]);
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let first = TurnOrder::Random.robots_order(0, &robots, 2, &mut rng);
+        let second = TurnOrder::Random.robots_order(1, &robots, 2, &mut rng);
+
+        assert_eq!(first, order(TurnOrder::Random, 0));
+        assert_ne!(first, second);
+        for order in [first, second] {
+            let mut sorted = order.clone();
+            sorted.sort();
+            assert_eq!(sorted, vec![0, 1, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn unknown_config_falls_back_to_sequential() {
+        assert_eq!(TurnOrder::from_config(100), TurnOrder::Sequential);
+    }
+}
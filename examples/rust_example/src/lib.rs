@@ -1,20 +1,27 @@
-use robotchallenge::{move_robot, GameConfig, LibInfo, Map};
+use robotchallenge::{export_bot, move_robot, Bot, GameConfig, LibInfo, Map};
 
-#[no_mangle]
-pub fn get_library_info() -> LibInfo {
-    return LibInfo {
-        name: "Rust example".to_string(),
-        version: "0.1.0".to_string(),
-    };
+struct Example {
+    owner: u32,
 }
 
-#[no_mangle]
-pub fn do_step(map: &Map, robot_to_move_index: usize, round_no: u32) {
-    let robot = map.robots[robot_to_move_index];
-    move_robot(robot.position.q + 1, robot.position.r);
-}
+impl Bot for Example {
+    fn info() -> LibInfo {
+        LibInfo {
+            name: "Rust example".to_string(),
+            version: "0.1.0".to_string(),
+        }
+    }
+
+    fn init(game_config: &GameConfig, owner: u32) -> Self {
+        println!("Init game: {:?}, owner: {:?}", game_config, owner);
+        Example { owner }
+    }
 
-#[no_mangle]
-pub fn init(game_config: GameConfig, owner: u32) {
-    println!("Init game: {:?}, owner: {:?}", game_config, owner);
+    fn step(&mut self, map: &Map, robot_to_move_index: usize, _round_no: u32) {
+        let robot = map.robots[robot_to_move_index];
+        debug_assert_eq!(robot.owner, self.owner);
+        move_robot(robot.position.q + 1, robot.position.r);
+    }
 }
+
+export_bot!(Example);
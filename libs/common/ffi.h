@@ -1,3 +1,9 @@
+#include <stddef.h>
+
+// Version of the structs and functions shared with the core, must match
+// `ABI_VERSION` in core/src/lib.rs. Bots export it as `abi_version`.
+#define ROBOTCHALLENGE_ABI_VERSION 1
+
 struct GameConfig {
     int width;
     unsigned int rounds_count;
@@ -9,6 +15,22 @@ struct GameConfig {
     unsigned int energy_loss_to_clone_robot;
     unsigned int max_robots_count;
     int energy_collect_distance;
+    unsigned int board_shape;
+    int height;
+    unsigned int action_points;
+    unsigned int move_action_points;
+    unsigned int collect_energy_action_points;
+    unsigned int clone_robot_action_points;
+    unsigned int audit;
+    unsigned int turn_order;
+    unsigned int clones_wait_a_round;
+    unsigned int end_when_last_player_standing;
+    unsigned int target_energy;
+    unsigned int end_when_stations_exhausted;
+    unsigned int stale_rounds_limit;
+    unsigned int energy_transfer_fee;
+    unsigned int transfer_energy_action_points;
+    unsigned int game_mode;
 };
 
 struct Position {
@@ -32,14 +54,14 @@ struct EnergyStation
 
 struct MapFFI
 {
-    int robots_len;
+    size_t robots_len;
     struct Robot * robots;
-    int energy_stations_len;
+    size_t energy_stations_len;
     struct EnergyStation * energy_stations;
 };
 
 struct LibInfo {
     char* name;
-    char* author;
+    char* language;
     char* version;
 };
@@ -56,4 +56,6 @@ public class Interop
     public static extern void MoveRobot(int q, int r);
     [MethodImpl(MethodImplOptions.InternalCall)]
     public static extern void CollectEnergy();
+    [MethodImpl(MethodImplOptions.InternalCall)]
+    public static extern void TransferEnergy(int targetRobot, uint amount);
 }
@@ -52,6 +52,22 @@ public struct GameConfig {
     public readonly uint EnergyLossToCloneRobot;
     public readonly uint MaxRobotsCount;
     public readonly int EnergyCollectDistance;
+    public readonly uint BoardShape;
+    public readonly int Height;
+    public readonly uint ActionPoints;
+    public readonly uint MoveActionPoints;
+    public readonly uint CollectEnergyActionPoints;
+    public readonly uint CloneRobotActionPoints;
+    public readonly uint Audit;
+    public readonly uint TurnOrder;
+    public readonly uint ClonesWaitARound;
+    public readonly uint EndWhenLastPlayerStanding;
+    public readonly uint TargetEnergy;
+    public readonly uint EndWhenStationsExhausted;
+    public readonly uint StaleRoundsLimit;
+    public readonly uint EnergyTransferFee;
+    public readonly uint TransferEnergyActionPoints;
+    public readonly uint GameMode;
 }
 
 public struct Map
@@ -2,6 +2,11 @@
 #include <mono-wasi/driver.h>
 #include <mono/metadata/assembly.h>
 #include <assert.h>
+#include <stddef.h>
+
+// Version of the structs and functions shared with the core, must match
+// `ABI_VERSION` in core/src/lib.rs. Bots export it as `abi_version`.
+#define ROBOTCHALLENGE_ABI_VERSION 1
 
 struct GameConfig {
     int width;
@@ -14,6 +19,22 @@ struct GameConfig {
     unsigned int energy_loss_to_clone_robot;
     unsigned int max_robots_count;
     int energy_collect_distance;
+    unsigned int board_shape;
+    int height;
+    unsigned int action_points;
+    unsigned int move_action_points;
+    unsigned int collect_energy_action_points;
+    unsigned int clone_robot_action_points;
+    unsigned int audit;
+    unsigned int turn_order;
+    unsigned int clones_wait_a_round;
+    unsigned int end_when_last_player_standing;
+    unsigned int target_energy;
+    unsigned int end_when_stations_exhausted;
+    unsigned int stale_rounds_limit;
+    unsigned int energy_transfer_fee;
+    unsigned int transfer_energy_action_points;
+    unsigned int game_mode;
 };
 
 struct Position {
@@ -37,9 +58,9 @@ struct EnergyStation
 
 struct MapFFI
 {
-    int robots_len;
+    size_t robots_len;
     struct Robot * robots;
-    int energy_stations_len;
+    size_t energy_stations_len;
     struct EnergyStation * energy_stations;
 };
 
@@ -74,6 +95,11 @@ MonoObject* interop_instance = 0;
 
 int is_runtime_loaded = 0;
 
+__attribute__((export_name("abi_version")))
+unsigned int abi_version() {
+    return ROBOTCHALLENGE_ABI_VERSION;
+}
+
 __attribute__((export_name("init_game")))
 void init_game(struct GameConfig config, unsigned int owner) {
     load_runtime();
@@ -138,6 +164,9 @@ void collect_energy();
 __attribute__((__import_module__("robotchallenge"), import_name("move_robot"))) extern
 void move_robot(int q, int r);
 
+__attribute__((__import_module__("robotchallenge"), import_name("transfer_energy"))) extern
+void transfer_energy(int target_robot, unsigned int amount);
+
 void load_runtime() {
     if(!is_runtime_loaded) {
         is_runtime_loaded = 1;
@@ -147,6 +176,7 @@ void load_runtime() {
         mono_add_internal_call("csharp_robotchallenge.Interop::CollectEnergy", collect_energy);
         mono_add_internal_call("csharp_robotchallenge.Interop::MoveRobot", move_robot);
         mono_add_internal_call("csharp_robotchallenge.Interop::CloneRobot", clone_robot);
+        mono_add_internal_call("csharp_robotchallenge.Interop::TransferEnergy", transfer_energy);
 
         mono_wasm_assembly_load(dotnet_wasi_getentrypointassemblyname());
         entrypoint_name = "csharp_robotchallenge.dll";
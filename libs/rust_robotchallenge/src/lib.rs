@@ -1,29 +1,13 @@
-use std::ffi::CString;
-use std::os::raw::{c_char, c_void};
+use std::mem::MaybeUninit;
+use std::os::raw::c_void;
 use std::panic;
 
-#[derive(Debug, Clone, Copy)]
-#[repr(C)]
-pub struct Position {
-    pub q: i32,
-    pub r: i32,
-}
-
-#[derive(Debug, Clone, Copy)]
-#[repr(C)]
-pub struct Robot {
-    pub position: Position,
-    pub energy: u32,
-    pub owner: u32,
-}
-
-#[derive(Debug)]
-#[repr(C)]
-pub struct EnergyStation {
-    pub position: Position,
-    pub recovery_rate: u32,
-    pub energy: u32,
-}
+use robotchallenge_types::MapFFI;
+pub use robotchallenge_types::{
+    EnergyStation, GameConfig, Position, Robot, ABI_VERSION, BOARD_SHAPE_HEXAGON,
+    BOARD_SHAPE_RECTANGLE, BOARD_SHAPE_TORUS, GAME_MODE_CLASSIC, TURN_ORDER_INTERLEAVED,
+    TURN_ORDER_RANDOM, TURN_ORDER_REVERSE_ALTERNATING, TURN_ORDER_SEQUENTIAL,
+};
 
 #[derive(Debug)]
 pub struct Map {
@@ -31,57 +15,151 @@ pub struct Map {
     pub energy_stations: Vec<EnergyStation>,
 }
 
-#[repr(C)]
-struct MapFFI {
-    robots_len: usize,
-    robots: *mut Robot,
-    energy_stations_len: usize,
-    energy_stations: *mut EnergyStation,
+pub struct LibInfo {
+    pub name: String,
+    pub version: String,
 }
 
-#[repr(C)]
-#[derive(Debug, Clone)]
-pub struct GameConfig {
-    pub width: i32,
-    pub rounds_count: u32,
-    pub players_count: u32,
-    pub initial_robots_count: u32,
-    pub start_energy: u32,
-    pub rng_seed: u32,
-    pub energy_stations_per_robot: u32,
-    pub energy_loss_to_clone_robot: u32,
-    pub max_robots_count: u32,
-    pub energy_collect_distance: i32,
-}
+/// A bot, created at the start of every game. Its state lives in `self`
+/// between steps.
+///
+/// ```ignore
+/// use robotchallenge::{export_bot, move_robot, Bot, GameConfig, LibInfo, Map};
+///
+/// struct MyBot {
+///     steps: u32,
+/// }
+///
+/// impl Bot for MyBot {
+///     fn info() -> LibInfo {
+///         LibInfo {
+///             name: "My bot".to_string(),
+///             version: "0.1.0".to_string(),
+///         }
+///     }
+///
+///     fn init(_config: &GameConfig, _owner: u32) -> Self {
+///         MyBot { steps: 0 }
+///     }
+///
+///     fn step(&mut self, map: &Map, robot: usize, _round: u32) {
+///         self.steps += 1;
+///         let position = map.robots[robot].position;
+///         move_robot(position.q + 1, position.r);
+///     }
+/// }
+///
+/// export_bot!(MyBot);
+/// ```
+pub trait Bot {
+    /// Name and version of the bot, read when it's uploaded.
+    fn info() -> LibInfo;
 
-#[repr(C)]
-struct InternalLibInfo {
-    name: *mut c_char,
-    language: *mut c_char,
-    version: *mut c_char,
+    /// Called once when a game starts, `owner` is the player you play as.
+    fn init(config: &GameConfig, owner: u32) -> Self;
+
+    /// Called on the turn of each of your robots, `robot` is its index in
+    /// `Map::robots`.
+    fn step(&mut self, map: &Map, robot: usize, round: u32);
 }
 
-pub struct LibInfo {
-    pub name: String,
-    pub version: String,
+/// Exports a type implementing `Bot` as the bot of the module. Call it once,
+/// at the root of your crate.
+#[macro_export]
+macro_rules! export_bot {
+    ($bot:ty) => {
+        const _: () = {
+            ::std::thread_local! {
+                static BOT: ::std::cell::RefCell<::std::option::Option<$bot>> =
+                    ::std::cell::RefCell::new(::std::option::Option::None);
+            }
+
+            #[no_mangle]
+            extern "C" fn init_game(game_config: $crate::GameConfig, owner: u32) {
+                $crate::__export::init_game::<$bot>(&BOT, game_config, owner);
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn do_step_ffi(
+                map_ffi: *mut $crate::__export::MapFFI,
+                robot_to_move_index: usize,
+                round_no: u32,
+            ) {
+                $crate::__export::do_step::<$bot>(&BOT, map_ffi, robot_to_move_index, round_no);
+            }
+
+            #[no_mangle]
+            extern "C" fn get_lib_info() -> *const $crate::__export::InternalLibInfo {
+                $crate::__export::get_lib_info::<$bot>()
+            }
+        };
+    };
 }
 
-mod lib {
-    use crate::{GameConfig, LibInfo, Map};
+/// Used by `export_bot!`, not part of the public API.
+#[doc(hidden)]
+pub mod __export {
+    use crate::{console_error_panic_hook, ffi_to_map, Bot, GameConfig};
+    use std::cell::RefCell;
+    use std::ffi::CString;
+    use std::panic;
+    use std::thread::LocalKey;
 
-    extern "C" {
-        pub fn get_library_info() -> LibInfo;
-        pub fn do_step(map: &Map, robot_to_move_index: usize, round_no: u32);
-        pub fn init(game_config: GameConfig, owner: u32);
+    pub use robotchallenge_types::{InternalLibInfo, MapFFI};
+
+    pub type BotSlot<B> = LocalKey<RefCell<Option<B>>>;
+
+    pub fn init_game<B: Bot>(slot: &'static BotSlot<B>, game_config: GameConfig, owner: u32) {
+        panic::set_hook(Box::new(console_error_panic_hook));
+        let bot = B::init(&game_config, owner);
+        slot.with(|slot| *slot.borrow_mut() = Some(bot));
+    }
+
+    /// # Safety
+    ///
+    /// `map_ffi` has to point to a map allocated with `allocate`, which the bot
+    /// takes ownership of.
+    pub unsafe fn do_step<B: Bot>(
+        slot: &'static BotSlot<B>,
+        map_ffi: *mut MapFFI,
+        robot_to_move_index: usize,
+        round_no: u32,
+    ) {
+        let map = ffi_to_map(&mut *map_ffi);
+        slot.with(|slot| {
+            slot.borrow_mut()
+                .as_mut()
+                .expect("`do_step_ffi` called before `init_game`")
+                .step(&map, robot_to_move_index, round_no)
+        });
+    }
+
+    pub fn get_lib_info<B: Bot>() -> *const InternalLibInfo {
+        let info = B::info();
+
+        Box::into_raw(Box::new(InternalLibInfo {
+            name: CString::new(info.name).unwrap().into_raw(),
+            language: CString::new("rust").unwrap().into_raw(),
+            version: CString::new(info.version).unwrap().into_raw(),
+        }))
     }
 }
 
 mod imports {
+    use crate::GameConfig;
+
     #[link(wasm_import_module = "robotchallenge")]
     extern "C" {
         pub fn clone_robot(new_bot_energy: u32);
         pub fn collect_energy();
         pub fn move_robot(x: i32, y: i32);
+        pub fn transfer_energy(target_robot: usize, amount: u32);
+
+        pub fn get_config(out: *mut GameConfig);
+        pub fn get_my_robots(out: *mut usize, capacity: usize) -> usize;
+        pub fn is_cell_free(q: i32, r: i32) -> bool;
+        pub fn move_cost(from_q: i32, from_r: i32, to_q: i32, to_r: i32) -> u32;
+        pub fn stations_in_range(q: i32, r: i32, out: *mut usize, capacity: usize) -> usize;
     }
 }
 
@@ -121,37 +199,64 @@ pub fn move_robot(q: i32, r: i32) {
     }
 }
 
-fn console_error_panic_hook(info: &panic::PanicInfo) {
-    eprintln!("{}", info);
+/// Gives `amount` energy to an adjacent robot of yours. Your robot also loses
+/// `GameConfig::energy_transfer_fee`.
+pub fn transfer_energy(target_robot: usize, amount: u32) {
+    unsafe {
+        imports::transfer_energy(target_robot, amount);
+    }
 }
 
-#[no_mangle]
-extern "C" fn init_game(game_config: GameConfig, owner: u32) {
-    panic::set_hook(Box::new(console_error_panic_hook));
+/// Returns the config of the current game.
+pub fn get_config() -> GameConfig {
+    let mut config = MaybeUninit::<GameConfig>::uninit();
     unsafe {
-        lib::init(game_config, owner);
+        imports::get_config(config.as_mut_ptr());
+        config.assume_init()
     }
 }
 
-#[no_mangle]
-extern "C" fn do_step_ffi(map_ffi: *mut MapFFI, robot_to_move_index: usize, round_no: u32) {
-    unsafe {
-        let map = ffi_to_map(&mut *map_ffi);
-        lib::do_step(&map, robot_to_move_index, round_no);
+fn read_indices(query: impl Fn(*mut usize, usize) -> usize) -> Vec<usize> {
+    let mut indices = vec![0; 16];
+    loop {
+        let count = query(indices.as_mut_ptr(), indices.len());
+        if count <= indices.len() {
+            indices.truncate(count);
+            return indices;
+        }
+        indices.resize(count, 0);
     }
 }
 
-#[no_mangle]
-extern "C" fn get_lib_info() -> *const InternalLibInfo {
-    unsafe {
-        let info = lib::get_library_info();
+/// Returns indices (in `Map::robots`) of all robots owned by you.
+pub fn get_my_robots() -> Vec<usize> {
+    read_indices(|out, capacity| unsafe { imports::get_my_robots(out, capacity) })
+}
 
-        return Box::into_raw(Box::new(InternalLibInfo {
-            name: CString::new(info.name).unwrap().into_raw(),
-            language: CString::new("rust").unwrap().into_raw(),
-            version: CString::new(info.version).unwrap().into_raw(),
-        }));
-    }
+/// Returns whether a robot can move to the cell: it is on the board and
+/// there is neither a robot nor an energy station on it.
+pub fn is_cell_free(q: i32, r: i32) -> bool {
+    unsafe { imports::is_cell_free(q, r) }
+}
+
+/// Returns how much energy a robot would lose moving from `from` to `to`.
+pub fn move_cost(from: Position, to: Position) -> u32 {
+    unsafe { imports::move_cost(from.q, from.r, to.q, to.r) }
+}
+
+/// Returns indices (in `Map::energy_stations`) of the stations a robot
+/// standing at (q, r) would collect energy from.
+pub fn stations_in_range(q: i32, r: i32) -> Vec<usize> {
+    read_indices(|out, capacity| unsafe { imports::stations_in_range(q, r, out, capacity) })
+}
+
+fn console_error_panic_hook(info: &panic::PanicInfo) {
+    eprintln!("{}", info);
+}
+
+#[no_mangle]
+extern "C" fn abi_version() -> u32 {
+    ABI_VERSION
 }
 
 #[no_mangle]
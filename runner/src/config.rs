@@ -0,0 +1,120 @@
+use crate::sandbox::Limits;
+use robotchallenge_types::BOARD_SHAPE_TORUS;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub use robotchallenge_types::GameConfig;
+
+/// Game config plus the limits the host enforces on the bots.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchConfig {
+    #[serde(flatten)]
+    pub game: GameConfig,
+    /// Time a bot has for a single step, in milliseconds
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Timeouts a bot may have in a round before it's skipped until the next one
+    #[serde(default = "default_max_timeouts_count")]
+    pub max_timeouts_count: u32,
+    /// Wasm operators a bot may run per call, see `fuel`. Running out counts
+    /// as a timeout. When set, `timeout` should only be a backstop, so that
+    /// results don't depend on the machine.
+    #[serde(default)]
+    pub fuel: Option<u64>,
+    /// Resources a bot may use
+    #[serde(default)]
+    pub limits: Limits,
+    /// Places robots and energy stations from this scenario instead of
+    /// generating them, see core/src/scenario.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<String>,
+    /// Fields the config doesn't know, kept so `validate` can report typos
+    /// instead of them silently falling back to defaults
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, serde_json::Value>,
+}
+
+fn default_timeout() -> u64 {
+    1000
+}
+
+fn default_max_timeouts_count() -> u32 {
+    5
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig {
+            game: GameConfig::default(),
+            timeout: default_timeout(),
+            max_timeouts_count: default_max_timeouts_count(),
+            fuel: None,
+            limits: Limits::default(),
+            scenario: None,
+            unknown: BTreeMap::new(),
+        }
+    }
+}
+
+impl MatchConfig {
+    /// Unknown fields and values out of the bounds the core and the runner
+    /// handle, checked before a config posted by an admin is stored. Lists
+    /// every problem found.
+    pub fn validate(&self) -> Vec<String> {
+        let game = &self.game;
+        let mut problems: Vec<String> = self
+            .unknown
+            .keys()
+            .map(|key| format!("unknown field `{}`", key))
+            .collect();
+        let mut check = |ok: bool, problem: &str| {
+            if !ok {
+                problems.push(problem.to_string());
+            }
+        };
+
+        check((1..=256).contains(&game.width), "`width` should be between 1 and 256");
+        check((0..=256).contains(&game.height), "`height` should be between 0 and 256");
+        check(
+            game.players_count >= 1,
+            "`playersCount` should be at least 1, matches set it to their number of bots",
+        );
+        check(
+            (1..=10_000).contains(&game.rounds_count),
+            "`roundsCount` should be between 1 and 10000",
+        );
+        check(
+            (1..=1000).contains(&game.max_robots_count),
+            "`maxRobotsCount` should be between 1 and 1000",
+        );
+        check(
+            (1..=game.max_robots_count).contains(&game.initial_robots_count),
+            "`initialRobotsCount` should be between 1 and `maxRobotsCount`",
+        );
+        check(
+            game.energy_collect_distance >= 0,
+            "`energyCollectDistance` can't be negative",
+        );
+        check(game.board_shape <= 2, "`boardShape` should be 0, 1 or 2");
+        let rows = if game.height > 0 { game.height } else { game.width };
+        check(
+            game.board_shape != BOARD_SHAPE_TORUS || rows % 2 == 0,
+            "`height` should be even on a torus, it defaults to `width` when 0",
+        );
+        check(game.turn_order <= 3, "`turnOrder` should be between 0 and 3");
+        check(game.game_mode == 0, "`gameMode` should be 0");
+        check(
+            (1..=60_000).contains(&self.timeout),
+            "`timeout` should be between 1 and 60000 milliseconds",
+        );
+        check(self.max_timeouts_count >= 1, "`maxTimeoutsCount` should be at least 1");
+        check(self.fuel != Some(0), "`fuel` should be at least 1");
+        check(
+            self.limits.memory_pages >= 1 && self.limits.instances >= 1,
+            "`limits` should allow at least a page of memory and an instance",
+        );
+
+        problems
+    }
+}
@@ -0,0 +1,388 @@
+use crate::config::GameConfig;
+use crate::ffi::{GameResultFFI, Map, PlayerActionsFFI, ABI_VERSION};
+use crate::replay::ReplayRound;
+use std::io::Read;
+use std::mem::size_of;
+use wasmer::{
+    imports, Function, FunctionEnv, FunctionEnvMut, Instance, Memory, Module, Store, Value,
+    WasmPtr,
+};
+use wasmer_wasi::{Pipe, WasiState};
+
+/// Robot the core asked to move, see the `do_step` import.
+#[derive(Debug, Copy, Clone)]
+pub struct PendingStep {
+    pub owner: u32,
+    pub robot_index: u32,
+    pub round: u32,
+}
+
+#[derive(Default)]
+struct CoreEnv {
+    memory: Option<Memory>,
+    pending_step: Option<PendingStep>,
+    finished_rounds: Vec<ReplayRound>,
+}
+
+// The core holds its state lock while calling the imports, so they only
+// record what happened and the runner reacts once the export returned.
+fn do_step(
+    mut env: FunctionEnvMut<CoreEnv>,
+    owner: u32,
+    robot_index: u32,
+    _map: u32,
+    round: u32,
+) -> u32 {
+    env.data_mut().pending_step = Some(PendingStep {
+        owner,
+        robot_index,
+        round,
+    });
+    0
+}
+
+fn round_finished(mut env: FunctionEnvMut<CoreEnv>, map: u32, player_actions: u32) {
+    let (data, store) = env.data_and_store_mut();
+    let view = data.memory.as_ref().unwrap().view(&store);
+    let round = Map::read(map, &view).and_then(|map| {
+        Ok(ReplayRound {
+            player_actions: PlayerActionsFFI::read(player_actions, &view)?,
+            map,
+        })
+    });
+
+    match round {
+        Ok(round) => data.finished_rounds.push(round),
+        Err(e) => eprintln!("[runner] can't read finished round: {}", e),
+    }
+}
+
+/// The game core running in its own wasm instance.
+pub struct Core {
+    store: Store,
+    instance: Instance,
+    env: FunctionEnv<CoreEnv>,
+    memory: Memory,
+    stderr: Pipe,
+}
+
+impl Core {
+    pub fn new(wasm: &[u8]) -> Result<Core, String> {
+        let mut store = Store::default();
+        let module = Module::new(&store, wasm).map_err(|e| e.to_string())?;
+
+        // The core logs every call, only its errors are worth keeping
+        let stderr = Pipe::new();
+        let wasi_env = WasiState::new("robotchallenge")
+            .stdout(Box::new(Pipe::new()))
+            .stderr(Box::new(stderr.clone()))
+            .finalize(&mut store)
+            .map_err(|e| e.to_string())?;
+
+        let env = FunctionEnv::new(&mut store, CoreEnv::default());
+        let mut import_object = imports! {
+            "robotchallenge" => {
+                "do_step" => Function::new_typed_with_env(&mut store, &env, do_step),
+                "round_finished" => Function::new_typed_with_env(&mut store, &env, round_finished),
+            }
+        };
+        let wasi_imports = wasi_env
+            .import_object(&mut store, &module)
+            .map_err(|e| e.to_string())?;
+        import_object.extend(wasi_imports.into_iter());
+
+        let instance =
+            Instance::new(&mut store, &module, &import_object).map_err(|e| e.to_string())?;
+        let memory = instance
+            .exports
+            .get_memory("memory")
+            .map_err(|e| e.to_string())?
+            .clone();
+        wasi_env.data_mut(&mut store).set_memory(memory.clone());
+        env.as_mut(&mut store).memory = Some(memory.clone());
+
+        let mut core = Core {
+            store,
+            instance,
+            env,
+            memory,
+            stderr,
+        };
+
+        let abi_version = core.call_u32("abi_version", &[])?;
+        if abi_version != ABI_VERSION {
+            return Err(format!(
+                "core was built for ABI version {}, but the runner supports version {}",
+                abi_version, ABI_VERSION
+            ));
+        }
+        core.call("init_mod", &[])?;
+
+        Ok(core)
+    }
+
+    fn call(&mut self, name: &str, params: &[Value]) -> Result<Box<[Value]>, String> {
+        let function = self
+            .instance
+            .exports
+            .get_function(name)
+            .map_err(|e| format!("core export `{}`: {}", name, e))?
+            .clone();
+
+        function
+            .call(&mut self.store, params)
+            .map_err(|e| format!("core trapped in `{}`: {}{}", name, e, self.take_stderr()))
+    }
+
+    fn call_u32(&mut self, name: &str, params: &[Value]) -> Result<u32, String> {
+        self.call(name, params)?
+            .first()
+            .and_then(Value::i32)
+            .map(|value| value as u32)
+            .ok_or_else(|| format!("core export `{}` didn't return a value", name))
+    }
+
+    /// Whatever the core wrote to stderr since the last call, prefixed with
+    /// a newline unless empty.
+    pub fn take_stderr(&mut self) -> String {
+        let mut stderr = String::new();
+        let _ = self.stderr.read_to_string(&mut stderr);
+        if stderr.is_empty() {
+            stderr
+        } else {
+            format!("\n{}", stderr.trim_end())
+        }
+    }
+
+    /// Starts a game, with robots and energy stations from `scenario` if
+    /// there's one.
+    pub fn init_game(&mut self, config: &GameConfig, scenario: Option<&str>) -> Result<(), String> {
+        let size = size_of::<GameConfig>() as u32;
+        let ptr = self.call_u32("allocate", &[Value::I32(size as i32)])?;
+        WasmPtr::<GameConfig>::new(ptr)
+            .write(&self.memory.view(&self.store), *config)
+            .map_err(|e| e.to_string())?;
+
+        match scenario {
+            Some(scenario) => {
+                let mut bytes = scenario.as_bytes().to_vec();
+                bytes.push(0);
+                let scenario_size = bytes.len() as u32;
+                let scenario_ptr =
+                    self.call_u32("allocate", &[Value::I32(scenario_size as i32)])?;
+                self.memory
+                    .view(&self.store)
+                    .write(scenario_ptr as u64, &bytes)
+                    .map_err(|e| e.to_string())?;
+                let loaded = self.call_u32(
+                    "init_game_with_scenario",
+                    &[Value::I32(ptr as i32), Value::I32(scenario_ptr as i32)],
+                )?;
+                self.deallocate(scenario_ptr, scenario_size)?;
+                if loaded == 0 {
+                    return Err(format!("invalid scenario{}", self.take_stderr()));
+                }
+            }
+            None => {
+                self.call("init_game", &[Value::I32(ptr as i32)])?;
+            }
+        }
+        self.deallocate(ptr, size)
+    }
+
+    fn deallocate(&mut self, ptr: u32, size: u32) -> Result<(), String> {
+        self.call(
+            "deallocate",
+            &[Value::I32(ptr as i32), Value::I32(size as i32)],
+        )?;
+        Ok(())
+    }
+
+    pub fn do_round(&mut self) -> Result<(), String> {
+        self.call("do_round", &[])?;
+        Ok(())
+    }
+
+    pub fn done_step(&mut self, is_timeout: bool, is_timeout_too_much: bool) -> Result<(), String> {
+        self.call(
+            "done_step",
+            &[
+                Value::I32(is_timeout as i32),
+                Value::I32(is_timeout_too_much as i32),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn take_pending_step(&mut self) -> Option<PendingStep> {
+        self.env.as_mut(&mut self.store).pending_step.take()
+    }
+
+    pub fn take_finished_rounds(&mut self) -> Vec<ReplayRound> {
+        std::mem::take(&mut self.env.as_mut(&mut self.store).finished_rounds)
+    }
+
+    pub fn get_map(&mut self) -> Result<Map, String> {
+        let ptr = self.call_u32("get_map", &[])?;
+        Map::read(ptr, &self.memory.view(&self.store))
+    }
+
+    pub fn is_game_over(&mut self) -> Result<bool, String> {
+        Ok(self.call_u32("is_game_over", &[])? != 0)
+    }
+
+    pub fn get_game_result(&mut self) -> Result<GameResultFFI, String> {
+        let ptr = self.call_u32("get_game_result", &[])?;
+        if ptr == 0 {
+            return Err("game is still running".to_string());
+        }
+        WasmPtr::<GameResultFFI>::new(ptr)
+            .read(&self.memory.view(&self.store))
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn move_robot(&mut self, q: i32, r: i32) -> Result<(), String> {
+        self.call("move_robot", &[Value::I32(q), Value::I32(r)])?;
+        Ok(())
+    }
+
+    pub fn collect_energy(&mut self) -> Result<(), String> {
+        self.call("collect_energy", &[])?;
+        Ok(())
+    }
+
+    pub fn clone_robot(&mut self, new_bot_energy: u32) -> Result<(), String> {
+        self.call("clone_robot", &[Value::I32(new_bot_energy as i32)])?;
+        Ok(())
+    }
+
+    pub fn transfer_energy(&mut self, target_robot: u32, amount: u32) -> Result<(), String> {
+        self.call(
+            "transfer_energy",
+            &[Value::I32(target_robot as i32), Value::I32(amount as i32)],
+        )?;
+        Ok(())
+    }
+
+    pub fn query_config(&mut self) -> Result<GameConfig, String> {
+        let size = size_of::<GameConfig>() as u32;
+        let ptr = self.call_u32("allocate", &[Value::I32(size as i32)])?;
+        self.call("query_config", &[Value::I32(ptr as i32)])?;
+        let config = WasmPtr::<GameConfig>::new(ptr)
+            .read(&self.memory.view(&self.store))
+            .map_err(|e| e.to_string());
+        self.deallocate(ptr, size)?;
+        config
+    }
+
+    /// Runs a query writing up to `capacity` indices and returning their total count.
+    /// `capacity` comes from the bot, so the query is counted first and the
+    /// core only allocates room for the indices it actually has.
+    fn query_indices(
+        &mut self,
+        name: &str,
+        capacity: u32,
+        params: impl Fn(Value, u32) -> Vec<Value>,
+    ) -> Result<(Vec<u32>, u32), String> {
+        let count = self.call_u32(name, &params(Value::I32(0), 0))?;
+        let capacity = capacity.min(count);
+        if capacity == 0 {
+            return Ok((vec![], count));
+        }
+
+        let size = indices_size(capacity)?;
+        let ptr = self.call_u32("allocate", &[Value::I32(size as i32)])?;
+        let count = self.call_u32(name, &params(Value::I32(ptr as i32), capacity))?;
+        let indices = WasmPtr::<u32>::new(ptr)
+            .slice(&self.memory.view(&self.store), count.min(capacity))
+            .and_then(|s| s.read_to_vec())
+            .map_err(|e| e.to_string());
+        self.deallocate(ptr, size)?;
+        Ok((indices?, count))
+    }
+
+    pub fn query_my_robots(&mut self, capacity: u32) -> Result<(Vec<u32>, u32), String> {
+        self.query_indices("query_my_robots", capacity, |ptr, capacity| {
+            vec![ptr, Value::I32(capacity as i32)]
+        })
+    }
+
+    pub fn query_stations_in_range(
+        &mut self,
+        q: i32,
+        r: i32,
+        capacity: u32,
+    ) -> Result<(Vec<u32>, u32), String> {
+        self.query_indices("query_stations_in_range", capacity, |ptr, capacity| {
+            vec![Value::I32(q), Value::I32(r), ptr, Value::I32(capacity as i32)]
+        })
+    }
+
+    pub fn query_is_cell_free(&mut self, q: i32, r: i32) -> Result<bool, String> {
+        Ok(self.call_u32("query_is_cell_free", &[Value::I32(q), Value::I32(r)])? != 0)
+    }
+
+    pub fn query_move_cost(
+        &mut self,
+        from_q: i32,
+        from_r: i32,
+        to_q: i32,
+        to_r: i32,
+    ) -> Result<u32, String> {
+        self.call_u32(
+            "query_move_cost",
+            &[
+                Value::I32(from_q),
+                Value::I32(from_r),
+                Value::I32(to_q),
+                Value::I32(to_r),
+            ],
+        )
+    }
+}
+
+/// Bytes the core allocates for `capacity` indices.
+fn indices_size(capacity: u32) -> Result<u32, String> {
+    capacity
+        .checked_mul(size_of::<u32>() as u32)
+        .filter(|size| *size <= i32::MAX as u32)
+        .ok_or_else(|| format!("can't allocate room for {} indices", capacity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn indices_size_rejects_overflowing_capacities() {
+        assert_eq!(indices_size(3), Ok(12));
+        assert!(indices_size(u32::MAX).is_err());
+        assert!(indices_size(u32::MAX / 4 + 1).is_err());
+    }
+
+    /// Needs a core built for wasm32-wasi at `CORE_WASM_PATH`.
+    #[test]
+    fn queries_only_allocate_what_the_core_has() {
+        let path = env::var("CORE_WASM_PATH").unwrap_or_else(|_| "core.wasm".to_string());
+        let wasm = fs::read(path).expect("CORE_WASM_PATH should point at a built core");
+        let mut core = Core::new(&wasm).unwrap();
+        let config = GameConfig {
+            players_count: 1,
+            ..GameConfig::default()
+        };
+        let scenario = "robot 0 0 0 10\nrobot 0 0 1 10\nstation 1 -1 10 1\n";
+        core.init_game(&config, Some(scenario)).unwrap();
+
+        assert_eq!(core.query_my_robots(u32::MAX), Ok((vec![0, 1], 2)));
+        assert_eq!(core.query_my_robots(1), Ok((vec![0], 2)));
+        assert_eq!(
+            core.query_stations_in_range(0, 0, u32::MAX),
+            Ok((vec![0], 1))
+        );
+        assert_eq!(
+            core.query_stations_in_range(8, 8, u32::MAX),
+            Ok((vec![], 0))
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Copies of the `repr(C)` structs exchanged with the core and the bots,
+//! laid out as they are in wasm32 memory.
+
+use serde::{Deserialize, Serialize};
+use wasmer::{MemoryView, WasmPtr};
+use wasmer_derive::ValueType;
+
+pub use robotchallenge_types::host::{InternalLibInfo, MapFFI};
+pub use robotchallenge_types::{EnergyStation, Position, Robot, ABI_VERSION};
+
+/// Matches `NO_PLAYER` in the core.
+pub const NO_PLAYER: u32 = u32::MAX;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueType)]
+#[repr(C)]
+pub struct PlayerActionsFFI {
+    pub player_actions_len: u32,
+    pub player_actions_values: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueType)]
+#[repr(C)]
+pub struct GameResultFFI {
+    pub rounds_played: u32,
+    pub reason: u32,
+    pub winner: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Map {
+    pub robots: Vec<Robot>,
+    pub energy_stations: Vec<EnergyStation>,
+}
+
+impl Map {
+    /// Reads the `MapFFI` at `ptr` and the robots and stations it points to.
+    pub fn read(ptr: u32, view: &MemoryView) -> Result<Map, String> {
+        let map = WasmPtr::<MapFFI>::new(ptr)
+            .read(view)
+            .map_err(|e| e.to_string())?;
+
+        Ok(Map {
+            robots: map
+                .robots
+                .slice(view, map.robots_len)
+                .and_then(|s| s.read_to_vec())
+                .map_err(|e| e.to_string())?,
+            energy_stations: map
+                .energy_stations
+                .slice(view, map.energy_stations_len)
+                .and_then(|s| s.read_to_vec())
+                .map_err(|e| e.to_string())?,
+        })
+    }
+}
+
+/// Same shape as `GamePlayerActions` in the frontend, so replays can be
+/// shown there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PlayerAction {
+    #[serde(rename_all = "camelCase")]
+    Move {
+        robot_id: u32,
+        new_position: Position,
+        loss: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    MoveFailed {
+        robot_id: u32,
+        new_position: Position,
+    },
+    #[serde(rename_all = "camelCase")]
+    CloneRobot { robot_id: u32, new_robot: Robot },
+    #[serde(rename_all = "camelCase")]
+    CloneRobotFailed { robot_id: u32 },
+    #[serde(rename_all = "camelCase")]
+    CollectEnergy { robot_id: u32 },
+    #[serde(rename_all = "camelCase")]
+    CollectEnergyFailed { robot_id: u32 },
+    #[serde(rename_all = "camelCase")]
+    Timeout {
+        robot_id: u32,
+        is_timeout_too_much: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    TransferEnergy {
+        robot_id: u32,
+        target_robot_id: u32,
+        amount: u32,
+        fee: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    TransferEnergyFailed { robot_id: u32, target_robot_id: u32 },
+}
+
+/// Size of a `PlayerActions` value: a `u32` tag followed by the largest
+/// variant, `CloneRobot`.
+const PLAYER_ACTION_SIZE: usize = 24;
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_i32(bytes: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+impl PlayerAction {
+    fn decode(bytes: &[u8]) -> Option<PlayerAction> {
+        let robot_id = read_u32(bytes, 4);
+        let position = |offset| Position {
+            q: read_i32(bytes, offset),
+            r: read_i32(bytes, offset + 4),
+        };
+
+        Some(match read_u32(bytes, 0) {
+            0 => PlayerAction::Move {
+                robot_id,
+                new_position: position(8),
+                loss: read_u32(bytes, 16),
+            },
+            1 => PlayerAction::MoveFailed {
+                robot_id,
+                new_position: position(8),
+            },
+            2 => PlayerAction::CloneRobot {
+                robot_id,
+                new_robot: Robot {
+                    position: position(8),
+                    energy: read_u32(bytes, 16),
+                    owner: read_u32(bytes, 20),
+                },
+            },
+            3 => PlayerAction::CloneRobotFailed { robot_id },
+            4 => PlayerAction::CollectEnergy { robot_id },
+            5 => PlayerAction::CollectEnergyFailed { robot_id },
+            6 => PlayerAction::Timeout {
+                robot_id,
+                is_timeout_too_much: bytes[8] != 0,
+            },
+            7 => PlayerAction::TransferEnergy {
+                robot_id,
+                target_robot_id: read_u32(bytes, 8),
+                amount: read_u32(bytes, 12),
+                fee: read_u32(bytes, 16),
+            },
+            8 => PlayerAction::TransferEnergyFailed {
+                robot_id,
+                target_robot_id: read_u32(bytes, 8),
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl PlayerActionsFFI {
+    pub fn read(ptr: u32, view: &MemoryView) -> Result<Vec<PlayerAction>, String> {
+        let actions = WasmPtr::<PlayerActionsFFI>::new(ptr)
+            .read(view)
+            .map_err(|e| e.to_string())?;
+
+        let mut bytes = vec![0; actions.player_actions_len as usize * PLAYER_ACTION_SIZE];
+        if !bytes.is_empty() {
+            view.read(actions.player_actions_values as u64, &mut bytes)
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(bytes
+            .chunks_exact(PLAYER_ACTION_SIZE)
+            .filter_map(PlayerAction::decode)
+            .collect())
+    }
+}
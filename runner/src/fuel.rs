@@ -0,0 +1,32 @@
+//! Deterministic step budgets. Every wasm operator a bot runs costs a point of
+//! fuel, so whether a step fits its budget doesn't depend on the machine.
+
+use std::sync::Arc;
+use wasmer::wasmparser::Operator;
+use wasmer::{Instance, ModuleMiddleware, Store};
+use wasmer_middlewares::metering::{get_remaining_points, set_remaining_points, MeteringPoints};
+use wasmer_middlewares::Metering;
+
+fn cost(_operator: &Operator) -> u64 {
+    1
+}
+
+/// Compiler middleware making instances start with `fuel` points and trap
+/// once they run out.
+pub fn metering(fuel: u64) -> Arc<dyn ModuleMiddleware> {
+    Arc::new(Metering::new(fuel, cost))
+}
+
+/// Gives an instance of a metered store a fresh budget.
+pub fn refuel(store: &mut Store, instance: &Instance, fuel: u64) {
+    set_remaining_points(store, instance, fuel);
+}
+
+/// Whether the last call of an instance of a metered store trapped because it
+/// ran out of fuel.
+pub fn is_exhausted(store: &mut Store, instance: &Instance) -> bool {
+    matches!(
+        get_remaining_points(store, instance),
+        MeteringPoints::Exhausted
+    )
+}
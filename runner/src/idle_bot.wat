@@ -0,0 +1,52 @@
+;; Bot that never moves, built in so no toolchain is needed to test other bots
+;; against it. Memory is never freed, it only plays short games.
+(module
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 1024))
+
+  ;; InternalLibInfo at 16, pointing at the strings below
+  (data (i32.const 16) "\20\00\00\00\25\00\00\00\29\00\00\00")
+  (data (i32.const 32) "idle\00wat\001\00")
+
+  (func (export "abi_version") (result i32)
+    i32.const 1)
+
+  (func (export "get_lib_info") (result i32)
+    i32.const 16)
+
+  (func (export "init_game") (param i32 i32))
+
+  (func (export "do_step_ffi") (param i32 i32 i32))
+
+  (func (export "allocate") (param $size i32) (result i32)
+    (local $ptr i32)
+    global.get $heap
+    local.set $ptr
+    ;; Keeps allocations 8 byte aligned
+    global.get $heap
+    local.get $size
+    i32.const 7
+    i32.add
+    i32.const -8
+    i32.and
+    i32.add
+    global.set $heap
+    (block $done
+      (loop $grow
+        global.get $heap
+        memory.size
+        i32.const 16
+        i32.shl
+        i32.le_u
+        br_if $done
+        i32.const 1
+        memory.grow
+        i32.const -1
+        i32.eq
+        if
+          unreachable
+        end
+        br $grow))
+    local.get $ptr)
+
+  (func (export "deallocate") (param i32 i32)))
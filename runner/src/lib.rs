@@ -0,0 +1,343 @@
+//! Runs games between wasm bots natively, the same way the browser does,
+//! without a frontend.
+
+pub mod config;
+pub mod core;
+pub mod ffi;
+pub mod fuel;
+pub mod player;
+pub mod replay;
+pub mod sandbox;
+
+use crate::config::MatchConfig;
+use crate::core::{Core, PendingStep};
+use crate::ffi::NO_PLAYER;
+use crate::player::{Answer, BotAction, LibInfo, Player, PlayerEvent, Query};
+use crate::replay::Replay;
+use crate::sandbox::Sandbox;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A bot that does nothing, for games that only test another one.
+pub const IDLE_BOT: &str = include_str!("idle_bot.wat");
+
+/// Names of the core's `GameEndReason` values, as used by the frontend.
+const GAME_END_REASONS: [&str; 5] = [
+    "roundsLimit",
+    "lastPlayerStanding",
+    "targetEnergy",
+    "stationsExhausted",
+    "noStateChange",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchResult {
+    pub rounds_played: u32,
+    pub reason: String,
+    /// `None` on a tie
+    pub winner: Option<u32>,
+    /// Energy of each player's robots at the end, the classic score
+    pub energy: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerSummary {
+    pub lib_info: LibInfo,
+    /// Steps the bot finished in time
+    pub steps: u32,
+    pub timeouts: u32,
+    /// Everything the bot printed, plus the reason of each timeout
+    pub log: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchOutcome {
+    pub result: MatchResult,
+    pub players: Vec<PlayerSummary>,
+    pub replay: Replay,
+}
+
+/// How a bot's step or initialization ended.
+enum StepEnd {
+    Done,
+    Timeout,
+}
+
+struct Seat {
+    wasm: Arc<Vec<u8>>,
+    owner: u32,
+    player: Option<Player>,
+    /// Timeouts in the current round
+    timeouts: u32,
+    summary: PlayerSummary,
+}
+
+impl Seat {
+    fn log(&mut self, message: &str) {
+        self.summary.log.push_str(message);
+        if !message.is_empty() && !message.ends_with('\n') {
+            self.summary.log.push('\n');
+        }
+    }
+}
+
+/// Plays a match between `players`, one wasm module per player, with the core
+/// built from core/.
+///
+/// A bot that times out, runs out of fuel or traps is replaced by a fresh
+/// instance, exactly as the browser does. Errors are only returned when the
+/// core itself fails or a bot can't be loaded at all.
+pub fn run_match(
+    core_wasm: &[u8],
+    players: &[Vec<u8>],
+    config: &MatchConfig,
+) -> Result<MatchOutcome, String> {
+    let mut config = config.clone();
+    config.game.players_count = players.len() as u32;
+    let timeout = Duration::from_millis(config.timeout);
+
+    let mut core = Core::new(core_wasm)?;
+    core.init_game(&config.game, config.scenario.as_deref())?;
+
+    let mut seats = Vec::with_capacity(players.len());
+    for (owner, wasm) in players.iter().enumerate() {
+        let wasm = Arc::new(wasm.clone());
+        let player = Player::spawn(wasm.clone(), timeout, sandbox(&config, owner as u32))
+            .map_err(|e| format!("player {}: {}", owner, e))?;
+        let mut seat = Seat {
+            wasm,
+            owner: owner as u32,
+            summary: PlayerSummary {
+                lib_info: player.lib_info.clone(),
+                steps: 0,
+                timeouts: 0,
+                log: String::new(),
+            },
+            player: Some(player),
+            timeouts: 0,
+        };
+        init_player(&mut core, &mut seat, &config, timeout)?;
+        seats.push(seat);
+    }
+
+    let mut replay = Replay {
+        config: config.game,
+        initial_map: core.get_map()?,
+        rounds: Vec::new(),
+    };
+
+    loop {
+        if let Some(step) = core.take_pending_step() {
+            let seat = &mut seats[step.owner as usize];
+            let (is_timeout, is_timeout_too_much) = play_step(&mut core, seat, step, &config)?;
+            core.done_step(is_timeout, is_timeout_too_much)?;
+        } else if core.is_game_over()? {
+            break;
+        } else {
+            core.do_round()?;
+        }
+
+        let finished_rounds = core.take_finished_rounds();
+        if !finished_rounds.is_empty() {
+            for seat in &mut seats {
+                if seat.player.is_none() {
+                    restart_player(&mut core, seat, &config, timeout)?;
+                }
+                seat.timeouts = 0;
+            }
+        }
+        replay.rounds.extend(finished_rounds);
+    }
+
+    let result = core.get_game_result()?;
+    let final_map = replay
+        .rounds
+        .last()
+        .map_or(&replay.initial_map, |round| &round.map);
+    let mut energy = vec![0; players.len()];
+    for robot in &final_map.robots {
+        if let Some(total) = energy.get_mut(robot.owner as usize) {
+            *total += robot.energy as u64;
+        }
+    }
+
+    Ok(MatchOutcome {
+        result: MatchResult {
+            rounds_played: result.rounds_played,
+            reason: GAME_END_REASONS
+                .get(result.reason as usize)
+                .unwrap_or(&"unknown")
+                .to_string(),
+            winner: (result.winner != NO_PLAYER).then_some(result.winner),
+            energy,
+        },
+        players: seats.into_iter().map(|seat| seat.summary).collect(),
+        replay,
+    })
+}
+
+/// Every seat gets its own random source, the same in every match with the
+/// same seed.
+fn sandbox(config: &MatchConfig, owner: u32) -> Sandbox {
+    Sandbox {
+        limits: config.limits,
+        fuel: config.fuel,
+        seed: (config.game.rng_seed as u64) << 32 | owner as u64,
+    }
+}
+
+/// Runs a robot's step. Returns the `done_step` flags for the core.
+fn play_step(
+    core: &mut Core,
+    seat: &mut Seat,
+    step: PendingStep,
+    config: &MatchConfig,
+) -> Result<(bool, bool), String> {
+    // Too many timeouts in this round, skipped until the next one
+    if seat.timeouts >= config.max_timeouts_count {
+        return Ok((true, true));
+    }
+    let Some(player) = &seat.player else {
+        return Ok((true, true));
+    };
+
+    let map = core.get_map()?;
+    player.do_step(map, step.robot_index, step.round);
+
+    match drive(core, seat, Duration::from_millis(config.timeout), true)? {
+        StepEnd::Done => {
+            seat.summary.steps += 1;
+            Ok((false, false))
+        }
+        StepEnd::Timeout => {
+            seat.timeouts += 1;
+            seat.summary.timeouts += 1;
+            if seat.timeouts < config.max_timeouts_count {
+                restart_player(core, seat, config, Duration::from_millis(config.timeout))?;
+            } else {
+                seat.player = None;
+            }
+            Ok((true, false))
+        }
+    }
+}
+
+/// Answers the bot's queries and forwards its actions to the core until it
+/// finished or ran out of time. Actions are ignored unless `allow_actions`.
+fn drive(
+    core: &mut Core,
+    seat: &mut Seat,
+    timeout: Duration,
+    allow_actions: bool,
+) -> Result<StepEnd, String> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let Some(player) = &seat.player else {
+            return Ok(StepEnd::Timeout);
+        };
+        let event = player
+            .events
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()));
+
+        match event {
+            Ok(PlayerEvent::Action(action)) => {
+                if allow_actions {
+                    apply_action(core, action)?;
+                }
+            }
+            Ok(PlayerEvent::Query(query, reply)) => {
+                let _ = reply.send(answer_query(core, query)?);
+            }
+            Ok(PlayerEvent::Done { result, log }) => {
+                seat.log(&log);
+                return match result {
+                    Ok(()) => Ok(StepEnd::Done),
+                    Err(e) => {
+                        seat.log(&e);
+                        Ok(StepEnd::Timeout)
+                    }
+                };
+            }
+            Err(_) => {
+                seat.log("Robot killed because of timeout");
+                // Drops the channels, the bot's thread ends once it returns
+                seat.player = None;
+                return Ok(StepEnd::Timeout);
+            }
+        }
+    }
+}
+
+fn apply_action(core: &mut Core, action: BotAction) -> Result<(), String> {
+    match action {
+        BotAction::Move { q, r } => core.move_robot(q, r),
+        BotAction::CollectEnergy => core.collect_energy(),
+        BotAction::CloneRobot { new_bot_energy } => core.clone_robot(new_bot_energy),
+        BotAction::TransferEnergy {
+            target_robot,
+            amount,
+        } => core.transfer_energy(target_robot, amount),
+    }
+}
+
+fn answer_query(core: &mut Core, query: Query) -> Result<Answer, String> {
+    Ok(match query {
+        Query::Config => Answer::Config(core.query_config()?),
+        Query::MyRobots { capacity } => {
+            let (indices, count) = core.query_my_robots(capacity)?;
+            Answer::Indices(indices, count)
+        }
+        Query::IsCellFree { q, r } => Answer::Value(core.query_is_cell_free(q, r)? as u32),
+        Query::MoveCost {
+            from_q,
+            from_r,
+            to_q,
+            to_r,
+        } => Answer::Value(core.query_move_cost(from_q, from_r, to_q, to_r)?),
+        Query::StationsInRange { q, r, capacity } => {
+            let (indices, count) = core.query_stations_in_range(q, r, capacity)?;
+            Answer::Indices(indices, count)
+        }
+    })
+}
+
+fn init_player(
+    core: &mut Core,
+    seat: &mut Seat,
+    config: &MatchConfig,
+    timeout: Duration,
+) -> Result<(), String> {
+    if let Some(player) = &seat.player {
+        player.init_game(config.game, seat.owner);
+    }
+    if let StepEnd::Timeout = drive(core, seat, timeout, false)? {
+        seat.player = None;
+    }
+    Ok(())
+}
+
+/// Replaces the bot with a fresh instance. A bot that can't even be restarted
+/// stays without an instance and times out on every step.
+fn restart_player(
+    core: &mut Core,
+    seat: &mut Seat,
+    config: &MatchConfig,
+    timeout: Duration,
+) -> Result<(), String> {
+    match Player::spawn(seat.wasm.clone(), timeout, sandbox(&config, seat.owner)) {
+        Ok(player) => {
+            seat.player = Some(player);
+            init_player(core, seat, config, timeout)
+        }
+        Err(e) => {
+            seat.log(&e);
+            seat.player = None;
+            Ok(())
+        }
+    }
+}
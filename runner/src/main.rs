@@ -0,0 +1,116 @@
+use runner::config::MatchConfig;
+use runner::run_match;
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str = "Usage: runner --core <core.wasm> [--config <config.json>] \
+[--replay <replay.json>] <player.wasm>...";
+
+struct Args {
+    core: String,
+    config: Option<String>,
+    replay: Option<String>,
+    players: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut core = None;
+    let mut config = None;
+    let mut replay = None;
+    let mut players = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--core" => core = Some(value()?),
+            "--config" => config = Some(value()?),
+            "--replay" => replay = Some(value()?),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ => players.push(arg),
+        }
+    }
+
+    let core = core.ok_or("missing --core")?;
+    if players.is_empty() {
+        return Err("at least one player is needed".to_string());
+    }
+
+    Ok(Args {
+        core,
+        config,
+        replay,
+        players,
+    })
+}
+
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("can't read {}: {}", path, e))
+}
+
+fn run() -> Result<(), String> {
+    let args = parse_args()?;
+
+    let core = read(&args.core)?;
+    let players = args
+        .players
+        .iter()
+        .map(|path| read(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let config = match &args.config {
+        Some(path) => serde_json::from_slice::<MatchConfig>(&read(path)?)
+            .map_err(|e| format!("invalid config {}: {}", path, e))?,
+        None => MatchConfig::default(),
+    };
+
+    let outcome = run_match(&core, &players, &config)?;
+
+    for (owner, player) in outcome.players.iter().enumerate() {
+        eprintln!(
+            "Player {}: {} ({} {}), {} timeouts",
+            owner,
+            player.lib_info.name,
+            player.lib_info.language,
+            player.lib_info.version,
+            player.timeouts
+        );
+    }
+    match outcome.result.winner {
+        Some(winner) => eprintln!(
+            "Player {} won after {} rounds ({})",
+            winner, outcome.result.rounds_played, outcome.result.reason
+        ),
+        None => eprintln!(
+            "Tie after {} rounds ({})",
+            outcome.result.rounds_played, outcome.result.reason
+        ),
+    }
+
+    if let Some(path) = &args.replay {
+        let replay = serde_json::to_vec(&outcome.replay).map_err(|e| e.to_string())?;
+        fs::write(path, replay).map_err(|e| format!("can't write {}: {}", path, e))?;
+        let summary = serde_json::json!({
+            "result": outcome.result,
+            "players": outcome.players,
+        });
+        println!("{}", summary);
+    } else {
+        println!(
+            "{}",
+            serde_json::to_string(&outcome).map_err(|e| e.to_string())?
+        );
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use crate::config::GameConfig;
+use crate::ffi::{EnergyStation, InternalLibInfo, Map, MapFFI, Robot, ABI_VERSION};
+use crate::fuel;
+use crate::sandbox::{self, InstancePermit, Sandbox, Wasi};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::mem::size_of;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use wasmer::{
+    imports, Function, FunctionEnv, FunctionEnvMut, Instance, Memory, Module, Store, Value,
+    WasmPtr,
+};
+use wasmer_wasi::Pipe;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LibInfo {
+    pub name: String,
+    pub language: String,
+    pub version: String,
+}
+
+/// Action imports called by a bot, forwarded to the core.
+#[derive(Debug, Copy, Clone)]
+pub enum BotAction {
+    Move { q: i32, r: i32 },
+    CollectEnergy,
+    CloneRobot { new_bot_energy: u32 },
+    TransferEnergy { target_robot: u32, amount: u32 },
+}
+
+/// Query imports called by a bot, answered by the core.
+#[derive(Debug, Copy, Clone)]
+pub enum Query {
+    Config,
+    MyRobots { capacity: u32 },
+    IsCellFree { q: i32, r: i32 },
+    MoveCost { from_q: i32, from_r: i32, to_q: i32, to_r: i32 },
+    StationsInRange { q: i32, r: i32, capacity: u32 },
+}
+
+#[derive(Debug, Clone)]
+pub enum Answer {
+    Config(GameConfig),
+    Value(u32),
+    /// Up to `capacity` indices and their total count
+    Indices(Vec<u32>, u32),
+}
+
+pub enum PlayerEvent {
+    Action(BotAction),
+    Query(Query, Sender<Answer>),
+    /// The bot returned from `init_game` or `do_step_ffi`, or trapped
+    Done { result: Result<(), String>, log: String },
+}
+
+enum Command {
+    Init { config: GameConfig, owner: u32 },
+    Step { map: Map, robot_index: u32, round: u32 },
+}
+
+/// A bot running on its own thread, so a step can be abandoned once it takes
+/// too long.
+///
+/// Wasm can't be interrupted from outside, so without fuel an abandoned bot
+/// stuck in a loop keeps its thread busy until the process exits. It stops
+/// counting as a live instance once the player is dropped though, so it
+/// can't lock out the bots of later matches.
+pub struct Player {
+    commands: Sender<Command>,
+    pub events: Receiver<PlayerEvent>,
+    pub lib_info: LibInfo,
+    _permit: InstancePermit,
+}
+
+impl Player {
+    /// Instantiates the bot in `sandbox` and reads its library info, giving up
+    /// after `timeout`.
+    pub fn spawn(
+        wasm: Arc<Vec<u8>>,
+        timeout: Duration,
+        sandbox: Sandbox,
+    ) -> Result<Player, String> {
+        let permit = InstancePermit::acquire(&sandbox.limits)?;
+        let (commands_tx, commands_rx) = channel::<Command>();
+        let (events_tx, events_rx) = channel();
+        let (ready_tx, ready_rx) = channel();
+
+        thread::spawn(move || {
+            let mut bot = match Bot::new(&wasm, events_tx.clone(), sandbox).and_then(|mut bot| {
+                let lib_info = bot.lib_info()?;
+                Ok((bot, lib_info))
+            }) {
+                Ok((bot, lib_info)) => {
+                    let _ = ready_tx.send(Ok(lib_info));
+                    bot
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            // Ends once the runner drops the player
+            for command in commands_rx {
+                let result = match command {
+                    Command::Init { config, owner } => bot.init_game(&config, owner),
+                    Command::Step {
+                        map,
+                        robot_index,
+                        round,
+                    } => bot.do_step(&map, robot_index, round),
+                };
+                let done = PlayerEvent::Done {
+                    result,
+                    log: bot.take_log(),
+                };
+                if events_tx.send(done).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let lib_info = ready_rx
+            .recv_timeout(timeout)
+            .map_err(|_| "bot didn't load in time".to_string())??;
+
+        Ok(Player {
+            commands: commands_tx,
+            events: events_rx,
+            lib_info,
+            _permit: permit,
+        })
+    }
+
+    pub fn init_game(&self, config: GameConfig, owner: u32) {
+        let _ = self.commands.send(Command::Init { config, owner });
+    }
+
+    pub fn do_step(&self, map: Map, robot_index: u32, round: u32) {
+        let _ = self.commands.send(Command::Step {
+            map,
+            robot_index,
+            round,
+        });
+    }
+}
+
+struct PlayerEnv {
+    memory: Option<Memory>,
+    events: Sender<PlayerEvent>,
+}
+
+impl PlayerEnv {
+    fn act(&self, action: BotAction) {
+        let _ = self.events.send(PlayerEvent::Action(action));
+    }
+
+    /// Blocks until the runner answers. Gets nothing once the bot was abandoned.
+    fn ask(&self, query: Query) -> Option<Answer> {
+        let (answer_tx, answer_rx) = channel();
+        self.events
+            .send(PlayerEvent::Query(query, answer_tx))
+            .ok()?;
+        answer_rx.recv().ok()
+    }
+}
+
+fn move_robot(env: FunctionEnvMut<PlayerEnv>, q: i32, r: i32) {
+    env.data().act(BotAction::Move { q, r });
+}
+
+fn collect_energy(env: FunctionEnvMut<PlayerEnv>) {
+    env.data().act(BotAction::CollectEnergy);
+}
+
+fn clone_robot(env: FunctionEnvMut<PlayerEnv>, new_bot_energy: u32) {
+    env.data().act(BotAction::CloneRobot { new_bot_energy });
+}
+
+fn transfer_energy(env: FunctionEnvMut<PlayerEnv>, target_robot: u32, amount: u32) {
+    env.data().act(BotAction::TransferEnergy {
+        target_robot,
+        amount,
+    });
+}
+
+fn get_config(mut env: FunctionEnvMut<PlayerEnv>, out: u32) {
+    let Some(Answer::Config(config)) = env.data().ask(Query::Config) else {
+        return;
+    };
+    let (data, store) = env.data_and_store_mut();
+    let view = data.memory.as_ref().unwrap().view(&store);
+    let _ = WasmPtr::<GameConfig>::new(out).write(&view, config);
+}
+
+fn write_indices(env: &mut FunctionEnvMut<PlayerEnv>, out: u32, answer: Option<Answer>) -> u32 {
+    let Some(Answer::Indices(indices, count)) = answer else {
+        return 0;
+    };
+    let (data, store) = env.data_and_store_mut();
+    let view = data.memory.as_ref().unwrap().view(&store);
+    let _ = WasmPtr::<u32>::new(out)
+        .slice(&view, indices.len() as u32)
+        .and_then(|slice| slice.write_slice(&indices));
+    count
+}
+
+fn get_my_robots(mut env: FunctionEnvMut<PlayerEnv>, out: u32, capacity: u32) -> u32 {
+    let answer = env.data().ask(Query::MyRobots { capacity });
+    write_indices(&mut env, out, answer)
+}
+
+fn is_cell_free(env: FunctionEnvMut<PlayerEnv>, q: i32, r: i32) -> u32 {
+    match env.data().ask(Query::IsCellFree { q, r }) {
+        Some(Answer::Value(is_free)) => is_free,
+        _ => 0,
+    }
+}
+
+fn move_cost(
+    env: FunctionEnvMut<PlayerEnv>,
+    from_q: i32,
+    from_r: i32,
+    to_q: i32,
+    to_r: i32,
+) -> u32 {
+    let query = Query::MoveCost {
+        from_q,
+        from_r,
+        to_q,
+        to_r,
+    };
+    match env.data().ask(query) {
+        Some(Answer::Value(cost)) => cost,
+        _ => u32::MAX,
+    }
+}
+
+fn stations_in_range(
+    mut env: FunctionEnvMut<PlayerEnv>,
+    q: i32,
+    r: i32,
+    out: u32,
+    capacity: u32,
+) -> u32 {
+    let answer = env.data().ask(Query::StationsInRange { q, r, capacity });
+    write_indices(&mut env, out, answer)
+}
+
+/// Wasm instance of a bot, only used on the player's thread.
+struct Bot {
+    store: Store,
+    instance: Instance,
+    memory: Memory,
+    stdout: Pipe,
+    stderr: Pipe,
+    fuel: Option<u64>,
+}
+
+impl Bot {
+    fn new(wasm: &[u8], events: Sender<PlayerEvent>, sandbox: Sandbox) -> Result<Bot, String> {
+        let mut store = sandbox::store(&sandbox);
+        let module = Module::new(&store, wasm).map_err(|e| e.to_string())?;
+
+        let stdout = Pipe::new();
+        let stderr = Pipe::new();
+        let wasi = Wasi::new(&mut store, &sandbox, stdout.clone(), stderr.clone())?;
+
+        let env = FunctionEnv::new(
+            &mut store,
+            PlayerEnv {
+                memory: None,
+                events,
+            },
+        );
+        let mut import_object = imports! {
+            "robotchallenge" => {
+                "move_robot" => Function::new_typed_with_env(&mut store, &env, move_robot),
+                "collect_energy" => Function::new_typed_with_env(&mut store, &env, collect_energy),
+                "clone_robot" => Function::new_typed_with_env(&mut store, &env, clone_robot),
+                "transfer_energy" => Function::new_typed_with_env(&mut store, &env, transfer_energy),
+                "get_config" => Function::new_typed_with_env(&mut store, &env, get_config),
+                "get_my_robots" => Function::new_typed_with_env(&mut store, &env, get_my_robots),
+                "is_cell_free" => Function::new_typed_with_env(&mut store, &env, is_cell_free),
+                "move_cost" => Function::new_typed_with_env(&mut store, &env, move_cost),
+                "stations_in_range" => Function::new_typed_with_env(&mut store, &env, stations_in_range),
+            }
+        };
+        let wasi_imports = wasi.imports(&mut store, &module)?;
+        import_object.extend(wasi_imports.into_iter());
+
+        let instance =
+            Instance::new(&mut store, &module, &import_object).map_err(|e| e.to_string())?;
+        let memory = instance
+            .exports
+            .get_memory("memory")
+            .map_err(|e| e.to_string())?
+            .clone();
+        wasi.set_memory(&mut store, &memory);
+        env.as_mut(&mut store).memory = Some(memory.clone());
+
+        let mut bot = Bot {
+            store,
+            instance,
+            memory,
+            stdout,
+            stderr,
+            fuel: sandbox.fuel,
+        };
+
+        if bot.instance.exports.get_function("abi_version").is_err() {
+            return Err(format!(
+                "bot doesn't export `abi_version`, rebuild it with a library supporting ABI version {}",
+                ABI_VERSION
+            ));
+        }
+        let abi_version = bot.call_u32("abi_version", &[])?;
+        if abi_version != ABI_VERSION {
+            return Err(format!(
+                "bot was built for ABI version {}, but the runner supports version {}",
+                abi_version, ABI_VERSION
+            ));
+        }
+
+        Ok(bot)
+    }
+
+    fn call(&mut self, name: &str, params: &[Value]) -> Result<Box<[Value]>, String> {
+        let function = self
+            .instance
+            .exports
+            .get_function(name)
+            .map_err(|e| format!("bot export `{}`: {}", name, e))?
+            .clone();
+
+        if let Some(fuel) = self.fuel {
+            fuel::refuel(&mut self.store, &self.instance, fuel);
+        }
+        function.call(&mut self.store, params).map_err(|e| {
+            if self.fuel.is_some() && fuel::is_exhausted(&mut self.store, &self.instance) {
+                format!("bot ran out of fuel in `{}`", name)
+            } else {
+                format!("bot trapped in `{}`: {}", name, e)
+            }
+        })
+    }
+
+    fn call_u32(&mut self, name: &str, params: &[Value]) -> Result<u32, String> {
+        self.call(name, params)?
+            .first()
+            .and_then(Value::i32)
+            .map(|value| value as u32)
+            .ok_or_else(|| format!("bot export `{}` didn't return a value", name))
+    }
+
+    fn allocate(&mut self, size: u32) -> Result<u32, String> {
+        self.call_u32("allocate", &[Value::I32(size as i32)])
+    }
+
+    fn deallocate(&mut self, ptr: u32, size: u32) -> Result<(), String> {
+        self.call(
+            "deallocate",
+            &[Value::I32(ptr as i32), Value::I32(size as i32)],
+        )?;
+        Ok(())
+    }
+
+    fn take_log(&mut self) -> String {
+        let mut log = String::new();
+        let _ = self.stdout.read_to_string(&mut log);
+        let _ = self.stderr.read_to_string(&mut log);
+        log
+    }
+
+    fn lib_info(&mut self) -> Result<LibInfo, String> {
+        let ptr = self.call_u32("get_lib_info", &[])?;
+        let view = self.memory.view(&self.store);
+        let lib_info = WasmPtr::<InternalLibInfo>::new(ptr)
+            .read(&view)
+            .map_err(|e| e.to_string())?;
+        let read = |ptr: WasmPtr<u8>| {
+            ptr.read_utf8_string_with_nul(&view)
+                .map_err(|e| e.to_string())
+        };
+
+        Ok(LibInfo {
+            name: read(lib_info.name)?,
+            language: read(lib_info.language)?,
+            version: read(lib_info.version)?,
+        })
+    }
+
+    fn init_game(&mut self, config: &GameConfig, owner: u32) -> Result<(), String> {
+        let size = size_of::<GameConfig>() as u32;
+        let ptr = self.allocate(size)?;
+        WasmPtr::<GameConfig>::new(ptr)
+            .write(&self.memory.view(&self.store), *config)
+            .map_err(|e| e.to_string())?;
+        self.call("init_game", &[Value::I32(ptr as i32), Value::I32(owner as i32)])?;
+        self.deallocate(ptr, size)
+    }
+
+    fn do_step(&mut self, map: &Map, robot_index: u32, round: u32) -> Result<(), String> {
+        // The robot and station arrays are owned by the bot library after the call
+        let robots_len = map.robots.len() as u32;
+        let robots = self.allocate(robots_len * size_of::<Robot>() as u32)?;
+        let energy_stations_len = map.energy_stations.len() as u32;
+        let energy_stations =
+            self.allocate(energy_stations_len * size_of::<EnergyStation>() as u32)?;
+        let map_size = size_of::<MapFFI>() as u32;
+        let map_ptr = self.allocate(map_size)?;
+
+        let view = self.memory.view(&self.store);
+        WasmPtr::<Robot>::new(robots)
+            .slice(&view, robots_len)
+            .and_then(|slice| slice.write_slice(&map.robots))
+            .map_err(|e| e.to_string())?;
+        WasmPtr::<EnergyStation>::new(energy_stations)
+            .slice(&view, energy_stations_len)
+            .and_then(|slice| slice.write_slice(&map.energy_stations))
+            .map_err(|e| e.to_string())?;
+        WasmPtr::<MapFFI>::new(map_ptr)
+            .write(
+                &view,
+                MapFFI {
+                    robots_len,
+                    robots: WasmPtr::new(robots),
+                    energy_stations_len,
+                    energy_stations: WasmPtr::new(energy_stations),
+                },
+            )
+            .map_err(|e| e.to_string())?;
+
+        self.call(
+            "do_step_ffi",
+            &[
+                Value::I32(map_ptr as i32),
+                Value::I32(robot_index as i32),
+                Value::I32(round as i32),
+            ],
+        )?;
+        self.deallocate(map_ptr, map_size)
+    }
+}
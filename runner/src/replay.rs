@@ -0,0 +1,20 @@
+use crate::config::GameConfig;
+use crate::ffi::{Map, PlayerAction};
+use serde::{Deserialize, Serialize};
+
+/// Actions of a finished round and the map they left behind.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayRound {
+    pub player_actions: Vec<PlayerAction>,
+    pub map: Map,
+}
+
+/// Everything needed to show a match again without running the bots.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Replay {
+    pub config: GameConfig,
+    pub initial_map: Map,
+    pub rounds: Vec<ReplayRound>,
+}
@@ -0,0 +1,316 @@
+//! Limits for running untrusted bots: how much memory, table space, stack and
+//! how many live instances they may use, and a WASI environment that shows
+//! them nothing of the host.
+
+use crate::fuel;
+use serde::{Deserialize, Serialize};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wasmer::vm::{
+    MemoryError, MemoryStyle, TableStyle, VMMemory, VMMemoryDefinition, VMTable,
+    VMTableDefinition,
+};
+use wasmer::{
+    BaseTunables, CompilerConfig, Cranelift, EngineBuilder, Function, FunctionEnv,
+    FunctionEnvMut, Imports, Memory, MemoryType, Module, Pages, Store, TableType, Target,
+    Tunables, WasmPtr,
+};
+use wasmer_wasi::{Pipe, WasiFunctionEnv, WasiState};
+
+const ERRNO_SUCCESS: u32 = 0;
+const ERRNO_FAULT: u32 = 21;
+
+/// Nanoseconds the virtual clock advances every time it's read.
+const CLOCK_TICK: u64 = 1_000;
+
+/// Bytes `random_get` generates at a time, a multiple of 8.
+const RANDOM_CHUNK: usize = 256;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Limits {
+    /// Linear memory of a bot, in 64 KiB pages
+    pub memory_pages: u32,
+    /// Elements of each table of a bot
+    pub table_elements: u32,
+    /// Stack the bots run on, in bytes. Bounds how deep they can recurse.
+    pub stack_size: usize,
+    /// Bot instances alive at once in the whole process, not counting the ones
+    /// of abandoned players
+    pub instances: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            memory_pages: 1024,
+            table_elements: 10_000,
+            stack_size: 1024 * 1024,
+            instances: 64,
+        }
+    }
+}
+
+/// Everything a bot instance is set up with.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Sandbox {
+    pub limits: Limits,
+    /// Operators per call into the bot, unlimited when `None`
+    pub fuel: Option<u64>,
+    /// Seed of the bytes `random_get` returns
+    pub seed: u64,
+}
+
+static LIVE_INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts as a live instance until dropped.
+pub struct InstancePermit(());
+
+impl InstancePermit {
+    pub fn acquire(limits: &Limits) -> Result<InstancePermit, String> {
+        let acquired = LIVE_INSTANCES.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |live| {
+            (live < limits.instances).then_some(live + 1)
+        });
+        match acquired {
+            Ok(_) => Ok(InstancePermit(())),
+            Err(_) => Err("too many bots are running, try again later".to_string()),
+        }
+    }
+}
+
+impl Drop for InstancePermit {
+    fn drop(&mut self) {
+        LIVE_INSTANCES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Caps the memories and tables instances create, on top of the defaults.
+struct LimitingTunables {
+    limits: Limits,
+    base: BaseTunables,
+}
+
+impl LimitingTunables {
+    fn memory(&self, ty: &MemoryType) -> Result<MemoryType, MemoryError> {
+        let limit = Pages(self.limits.memory_pages);
+        if ty.minimum > limit {
+            return Err(MemoryError::Generic(format!(
+                "memory needs {} pages, bots may use {}",
+                ty.minimum.0, limit.0
+            )));
+        }
+        Ok(MemoryType {
+            maximum: Some(ty.maximum.map_or(limit, |maximum| maximum.min(limit))),
+            ..*ty
+        })
+    }
+
+    fn table(&self, ty: &TableType) -> Result<TableType, String> {
+        let limit = self.limits.table_elements;
+        if ty.minimum > limit {
+            return Err(format!(
+                "table needs {} elements, bots may use {}",
+                ty.minimum, limit
+            ));
+        }
+        Ok(TableType {
+            maximum: Some(ty.maximum.map_or(limit, |maximum| maximum.min(limit))),
+            ..*ty
+        })
+    }
+}
+
+impl Tunables for LimitingTunables {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        let memory = self.memory(memory).unwrap_or(*memory);
+        self.base.memory_style(&memory)
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<VMMemory, MemoryError> {
+        self.base.create_host_memory(&self.memory(ty)?, style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<VMMemory, MemoryError> {
+        self.base
+            .create_vm_memory(&self.memory(ty)?, style, vm_definition_location)
+    }
+
+    fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<VMTable, String> {
+        self.base.create_host_table(&self.table(ty)?, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<VMTable, String> {
+        self.base
+            .create_vm_table(&self.table(ty)?, style, vm_definition_location)
+    }
+}
+
+/// Store enforcing the sandbox's limits and fuel on the modules it compiles.
+pub fn store(sandbox: &Sandbox) -> Store {
+    // Wasm runs on a stack of its own, sized when an instance is called
+    wasmer_vm::set_stack_size(sandbox.limits.stack_size);
+
+    let mut compiler = Cranelift::default();
+    if let Some(fuel) = sandbox.fuel {
+        compiler.push_middleware(fuel::metering(fuel));
+    }
+    let tunables = LimitingTunables {
+        limits: sandbox.limits,
+        base: BaseTunables::for_target(&Target::default()),
+    };
+    Store::new_with_tunables(EngineBuilder::new(compiler), tunables)
+}
+
+struct VirtualEnv {
+    memory: Option<Memory>,
+    /// Nanoseconds since the instance started, as far as it can tell
+    now: u64,
+    rng: u64,
+}
+
+impl VirtualEnv {
+    /// splitmix64
+    fn next_random(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+fn clock_res_get(mut env: FunctionEnvMut<VirtualEnv>, _clock_id: u32, out: u32) -> u32 {
+    let (data, store) = env.data_and_store_mut();
+    let Some(memory) = &data.memory else {
+        return ERRNO_FAULT;
+    };
+    match WasmPtr::<u64>::new(out).write(&memory.view(&store), CLOCK_TICK) {
+        Ok(()) => ERRNO_SUCCESS,
+        Err(_) => ERRNO_FAULT,
+    }
+}
+
+fn clock_time_get(
+    mut env: FunctionEnvMut<VirtualEnv>,
+    _clock_id: u32,
+    _precision: u64,
+    out: u32,
+) -> u32 {
+    let (data, store) = env.data_and_store_mut();
+    data.now += CLOCK_TICK;
+    let Some(memory) = &data.memory else {
+        return ERRNO_FAULT;
+    };
+    match WasmPtr::<u64>::new(out).write(&memory.view(&store), data.now) {
+        Ok(()) => ERRNO_SUCCESS,
+        Err(_) => ERRNO_FAULT,
+    }
+}
+
+fn random_get(mut env: FunctionEnvMut<VirtualEnv>, out: u32, len: u32) -> u32 {
+    let (data, store) = env.data_and_store_mut();
+    let Some(memory) = data.memory.clone() else {
+        return ERRNO_FAULT;
+    };
+    let view = memory.view(&store);
+    // `len` comes from the bot, nothing is generated unless it all fits
+    let (out, len) = (out as u64, len as u64);
+    if out + len > view.data_size() {
+        return ERRNO_FAULT;
+    }
+
+    let mut chunk = [0u8; RANDOM_CHUNK];
+    let mut written = 0;
+    while written < len {
+        let size = (len - written).min(RANDOM_CHUNK as u64) as usize;
+        for bytes in chunk[..size].chunks_mut(8) {
+            bytes.copy_from_slice(&data.next_random().to_le_bytes()[..bytes.len()]);
+        }
+        if view.write(out + written, &chunk[..size]).is_err() {
+            return ERRNO_FAULT;
+        }
+        written += size as u64;
+    }
+    ERRNO_SUCCESS
+}
+
+/// WASI without preopened directories, arguments or environment variables,
+/// whose clocks and random source only depend on the sandbox's seed.
+pub struct Wasi {
+    wasi_env: WasiFunctionEnv,
+    env: FunctionEnv<VirtualEnv>,
+}
+
+impl Wasi {
+    pub fn new(
+        store: &mut Store,
+        sandbox: &Sandbox,
+        stdout: Pipe,
+        stderr: Pipe,
+    ) -> Result<Wasi, String> {
+        let wasi_env = WasiState::new("robotchallenge")
+            .stdout(Box::new(stdout))
+            .stderr(Box::new(stderr))
+            .finalize(store)
+            .map_err(|e| e.to_string())?;
+        let env = FunctionEnv::new(
+            store,
+            VirtualEnv {
+                memory: None,
+                now: 0,
+                rng: sandbox.seed,
+            },
+        );
+        Ok(Wasi { wasi_env, env })
+    }
+
+    /// WASI imports for `module`, with the clocks and random source replaced.
+    pub fn imports(&self, store: &mut Store, module: &Module) -> Result<Imports, String> {
+        let mut imports = self
+            .wasi_env
+            .import_object(store, module)
+            .map_err(|e| e.to_string())?;
+        for namespace in ["wasi_unstable", "wasi_snapshot_preview1"] {
+            imports.define(
+                namespace,
+                "clock_res_get",
+                Function::new_typed_with_env(store, &self.env, clock_res_get),
+            );
+            imports.define(
+                namespace,
+                "clock_time_get",
+                Function::new_typed_with_env(store, &self.env, clock_time_get),
+            );
+            imports.define(
+                namespace,
+                "random_get",
+                Function::new_typed_with_env(store, &self.env, random_get),
+            );
+        }
+        Ok(imports)
+    }
+
+    /// Has to be called once the module was instantiated.
+    pub fn set_memory(&self, store: &mut Store, memory: &Memory) {
+        self.wasi_env.data_mut(store).set_memory(memory.clone());
+        self.env.as_mut(store).memory = Some(memory.clone());
+    }
+}
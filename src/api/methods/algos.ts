@@ -2,15 +2,22 @@ import type { RequestType } from './types';
 import {
   PostFileRequest, GetRequest, GetFileRequest, PostRequest,
 } from './types';
-import type { ApiAlgo, ApiAlgoVersion } from '../types';
-import type { GameLibraryInfo } from '../../types/gameTypes';
+import type {
+  ApiAlgo, ApiAlgoVersion, ApiAlgoVersionId, ApiAlgoVersionStatus, ApiMatch, ApiSmokeTest,
+} from '../types';
 
 const BASE = 'algos';
 
 export class PostAlgo extends PostFileRequest implements RequestType {
   type = BASE;
 
-  resultType?: { algoId: number, algoVersionId: number };
+  resultType?: {
+    algoId: number,
+    algoVersionId: number,
+    status: ApiAlgoVersionStatus,
+    smokeTest?: ApiSmokeTest,
+    sha256: string,
+  };
 }
 
 export class GetAlgos extends GetRequest implements RequestType {
@@ -42,11 +49,13 @@ export class GetAlgoFile extends GetFileRequest implements RequestType {
 export class Run extends PostRequest implements RequestType {
   type = `${BASE}/run`;
 
-  constructor(public payload: { algoVersions: number[] }) {
+  constructor(public payload: {
+    categoryId: number, algoVersions: ApiAlgoVersionId[], seed?: number,
+  }) {
     super();
   }
 
-  resultType?: GameLibraryInfo[];
+  resultType?: ApiMatch;
 }
 
 type AlgosRequests = PostAlgo | GetAlgos | GetAlgoFile | GetAlgoVersions | Run;
@@ -1,6 +1,6 @@
 import type { RequestType } from './types';
 import { GetRequest, PostRequest } from './types';
-import type { ApiCategory } from '../types';
+import type { ApiAlgoVersionId, ApiCategory, ApiSubmission } from '../types';
 
 const BASE = 'categories';
 export class GetCategories extends GetRequest implements RequestType {
@@ -19,5 +19,27 @@ export class PostCategory extends PostRequest implements RequestType {
   resultType?: ApiCategory;
 }
 
-type CategoriesRequests = GetCategories | PostCategory;
+export class GetSubmissions extends GetRequest implements RequestType {
+  type = BASE;
+
+  constructor(public categoryId: number) {
+    super();
+    this.path = [categoryId, 'submissions'];
+  }
+
+  resultType?: ApiSubmission[];
+}
+
+export class Submit extends PostRequest implements RequestType {
+  type = BASE;
+
+  constructor(categoryId: number, public payload: { algoVersionId: ApiAlgoVersionId }) {
+    super();
+    this.type = `${BASE}/${categoryId}/submissions`;
+  }
+
+  resultType?: ApiSubmission;
+}
+
+type CategoriesRequests = GetCategories | PostCategory | GetSubmissions | Submit;
 export default CategoriesRequests;
@@ -3,6 +3,10 @@ import type UsersRequests from './users';
 import type AlgosRequests from './algos';
 import type CategoriesRequests from './categories';
 import type UserGroupRequests from './userGroups';
+import type MatchesRequests from './matches';
+import type TournamentsRequests from './tournaments';
+import type LadderRequests from './ladder';
 
-type ApiRequests = AuthRequests | UsersRequests | AlgosRequests | CategoriesRequests | UserGroupRequests;
+type ApiRequests = AuthRequests | UsersRequests | AlgosRequests | CategoriesRequests
+| UserGroupRequests | MatchesRequests | TournamentsRequests | LadderRequests;
 export default ApiRequests;
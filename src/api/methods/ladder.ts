@@ -0,0 +1,43 @@
+import type { RequestType } from './types';
+import { GetRequest, PostRequest } from './types';
+import type {
+  ApiAlgoVersionId, ApiLadderEntry, ApiRating, ApiRatingHistory,
+} from '../types';
+
+const BASE = 'ladder';
+
+export class GetLadder extends GetRequest implements RequestType {
+  type = BASE;
+
+  constructor(public categoryId: number) {
+    super();
+    this.path = [categoryId];
+  }
+
+  resultType?: ApiLadderEntry[];
+}
+
+export class JoinLadder extends PostRequest implements RequestType {
+  type = BASE;
+
+  constructor(categoryId: number, public payload: { algoVersionId: ApiAlgoVersionId }) {
+    super();
+    this.type = `${BASE}/${categoryId}`;
+  }
+
+  resultType?: ApiRating;
+}
+
+export class GetRatingHistory extends GetRequest implements RequestType {
+  type = BASE;
+
+  constructor(public categoryId: number, public algoVersionId: ApiAlgoVersionId) {
+    super();
+    this.path = [categoryId, algoVersionId, 'history'];
+  }
+
+  resultType?: ApiRatingHistory[];
+}
+
+type LadderRequests = GetLadder | JoinLadder | GetRatingHistory;
+export default LadderRequests;
@@ -0,0 +1,35 @@
+import type { RequestType } from './types';
+import { GetRequest } from './types';
+import type { ApiMatch, ApiMatchId } from '../types';
+import type { GameConfig, GamePlayerActions, GameMap } from '../../types/gameTypes';
+
+const BASE = 'matches';
+
+export class GetMatch extends GetRequest implements RequestType {
+  type = BASE;
+
+  constructor(public id: ApiMatchId) {
+    super();
+    this.path = [id];
+  }
+
+  resultType?: ApiMatch;
+}
+
+export class GetMatchReplay extends GetRequest implements RequestType {
+  type = BASE;
+
+  constructor(public id: ApiMatchId) {
+    super();
+    this.path = [id, 'replay'];
+  }
+
+  resultType?: {
+    config: GameConfig,
+    initialMap: GameMap,
+    rounds: { playerActions: GamePlayerActions[], map: GameMap }[],
+  };
+}
+
+type MatchesRequests = GetMatch | GetMatchReplay;
+export default MatchesRequests;
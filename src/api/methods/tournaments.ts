@@ -0,0 +1,53 @@
+import type { RequestType } from './types';
+import { GetRequest, PostRequest } from './types';
+import type {
+  ApiAlgoVersionId,
+  ApiNewTournament,
+  ApiTournament,
+  ApiTournamentDetails,
+  ApiTournamentEntry,
+  ApiTournamentId,
+} from '../types';
+
+const BASE = 'tournaments';
+
+export class GetTournaments extends GetRequest implements RequestType {
+  type = BASE;
+
+  resultType?: ApiTournament[];
+}
+
+export class GetTournament extends GetRequest implements RequestType {
+  type = BASE;
+
+  constructor(public id: ApiTournamentId) {
+    super();
+    this.path = [id];
+  }
+
+  resultType?: ApiTournamentDetails;
+}
+
+export class PostTournament extends PostRequest implements RequestType {
+  type = BASE;
+
+  constructor(public newTournament: ApiNewTournament) {
+    super();
+  }
+
+  resultType?: ApiTournament;
+}
+
+export class EnterTournament extends PostRequest implements RequestType {
+  type = BASE;
+
+  constructor(id: ApiTournamentId, public payload: { algoVersionId: ApiAlgoVersionId }) {
+    super();
+    this.type = `${BASE}/${id}/entries`;
+  }
+
+  resultType?: ApiTournamentEntry;
+}
+
+type TournamentsRequests = GetTournaments | GetTournament | PostTournament | EnterTournament;
+export default TournamentsRequests;
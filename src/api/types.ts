@@ -1,4 +1,4 @@
-import type { GameConfig } from '../types/gameTypes';
+import type { GameConfig, GameEndReason, GameLibraryInfo } from '../types/gameTypes';
 import type { LottieIcon } from '../helpers/lottieIcons';
 
 export type ApiUser = {
@@ -42,12 +42,160 @@ export type ApiAlgo = {
   language: string,
 };
 
+export type ApiAlgoVersionStatus = 'unverified' | 'verified' | 'broken';
+
+export type ApiSmokeTest = {
+  played: boolean;
+  passed: boolean;
+  steps: number;
+  timeouts: number;
+  log: string;
+};
+
 export type ApiAlgoVersion = {
   id: ApiAlgoVersionId;
   algoId: ApiAlgoId;
   version: string;
+  status: ApiAlgoVersionStatus;
+  // Missing until the server played the test game
+  smokeTest?: ApiSmokeTest;
+  sha256: string;
 };
 
 export type ApiAlgoVersionWithFile = ApiAlgoVersion & {
   file?: Blob;
 };
+
+export type ApiMatchId = number;
+
+export type ApiMatchStatus = 'queued' | 'running' | 'finished' | 'failed';
+
+export type ApiMatchPlayer = {
+  libInfo: GameLibraryInfo;
+  steps: number;
+  timeouts: number;
+  log: string;
+};
+
+export type ApiMatchResult = {
+  result: {
+    roundsPlayed: number;
+    reason: GameEndReason;
+    // Undefined on a draw
+    winner?: number;
+    energy: number[];
+  };
+  players: ApiMatchPlayer[];
+};
+
+export type ApiMatch = {
+  id: ApiMatchId;
+  categoryId: number;
+  gameConfig: GameConfig;
+  seed: number;
+  algoVersionIds: ApiAlgoVersionId[];
+  status: ApiMatchStatus;
+  result?: ApiMatchResult;
+  error?: string;
+  createdAt: string;
+  updatedAt: string;
+};
+
+export type ApiTournamentId = number;
+
+export type ApiTournamentFormat = 'roundRobin' | 'swiss' | 'singleElimination';
+
+export type ApiTournamentStatus = 'scheduled' | 'running' | 'finished';
+
+export type ApiNewTournament = {
+  categoryId: number;
+  name: string;
+  format: ApiTournamentFormat;
+  // Only used by Swiss tournaments
+  roundsCount?: number;
+  startsAt: string;
+};
+
+export type ApiTournament = ApiNewTournament & {
+  id: ApiTournamentId;
+  status: ApiTournamentStatus;
+  currentRound: number;
+  createdAt: string;
+  updatedAt: string;
+};
+
+export type ApiTournamentEntry = {
+  id: number;
+  tournamentId: ApiTournamentId;
+  userId: string;
+  algoVersionId: ApiAlgoVersionId;
+  createdAt: string;
+};
+
+export type ApiPairingOutcome =
+  | { type: 'bye', entryId: number }
+  | { type: 'win', winnerId: number, loserId: number }
+  | { type: 'draw', entryIds: [number, number] };
+
+export type ApiTournamentPairing = {
+  id: number;
+  tournamentId: ApiTournamentId;
+  round: number;
+  // A single entry is a bye
+  entryIds: number[];
+  matchId?: ApiMatchId;
+  matchStatus?: ApiMatchStatus;
+  // Undefined while the match is being played
+  outcome?: ApiPairingOutcome;
+};
+
+export type ApiStanding = {
+  entryId: number;
+  userId: string;
+  algoVersionId: ApiAlgoVersionId;
+  points: number;
+  wins: number;
+  draws: number;
+  losses: number;
+  byes: number;
+};
+
+export type ApiTournamentDetails = ApiTournament & {
+  entries: ApiTournamentEntry[];
+  pairings: ApiTournamentPairing[];
+  standings: ApiStanding[];
+};
+
+export type ApiSubmission = {
+  id: number;
+  categoryId: number;
+  userId: string;
+  algoVersionId: ApiAlgoVersionId;
+  late: boolean;
+  createdAt: string;
+};
+
+export type ApiRating = {
+  id: number;
+  categoryId: number;
+  algoVersionId: ApiAlgoVersionId;
+  rating: number;
+  matchesPlayed: number;
+  createdAt: string;
+  updatedAt: string;
+};
+
+export type ApiLadderEntry = ApiRating & {
+  algoId: ApiAlgoId;
+  userId: string;
+  name: string;
+  version: string;
+};
+
+export type ApiRatingHistory = {
+  id: number;
+  ratingId: number;
+  matchId: ApiMatchId;
+  rating: number;
+  createdAt: string;
+};
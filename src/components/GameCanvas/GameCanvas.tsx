@@ -71,6 +71,15 @@ export default function GameCanvas({
           energy: acc.robots[action.robotId].energy - (gameConfig.energyLossToCloneRobot + action.newRobot.energy),
         };
         acc.robots = [...acc.robots, action.newRobot];
+      } else if (action.type === 'transferEnergy') {
+        acc.robots[action.robotId] = {
+          ...acc.robots[action.robotId],
+          energy: acc.robots[action.robotId].energy - (action.amount + action.fee),
+        };
+        acc.robots[action.targetRobotId] = {
+          ...acc.robots[action.targetRobotId],
+          energy: acc.robots[action.targetRobotId].energy + action.amount,
+        };
       }
       return acc;
     }, deepClone(startingMap));
@@ -260,6 +269,33 @@ export default function GameCanvas({
         break;
       }
 
+      case 'transferEnergy': {
+        const position = mapCurrent.robots[currentPlayerAction.robotId].position;
+
+        moveCamera(position, 'easeOut');
+
+        timeout(() => {
+          setCollectingEnergyFrom([position]);
+          setCollectingEnergyTo(mapCurrent.robots[currentPlayerAction.targetRobotId].position);
+          setIsUpdated(true);
+          timeout(onAnimationEnd, 1000).then(reset);
+        }, 1000);
+        break;
+      }
+
+      case 'transferEnergyFailed': {
+        const position = mapCurrent.robots[currentPlayerAction.robotId].position;
+
+        moveCamera(position, 'easeOut').then(() => {
+          timeout(() => {
+            setIsUpdated(true);
+            onAnimationEnd();
+            reset();
+          }, 1000);
+        });
+        break;
+      }
+
       case 'cloneRobotFailed': {
         const position = mapCurrent.robots[currentPlayerAction.robotId].position;
 
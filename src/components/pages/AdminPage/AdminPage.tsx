@@ -30,7 +30,7 @@ export default function AdminPage() {
   const [gameConfig, setGameConfig] = useState<GameConfig>({
     width: 16,
     roundsCount: 50,
-    playersCount: 0, // algos.length,
+    playersCount: 2, // algos.length,
     initialRobotsCount: 10,
     startEnergy: 50,
     rngSeed: 123,
@@ -18,6 +18,7 @@ import useAppDispatch from '../../../hooks/useAppDispatch';
 import {
   selectGameConfig,
   selectGameMapStates,
+  selectGameResult,
   selectPlayers,
 } from '../../../store/selectors/gamesSelectors';
 import useContextMenu from '../../../hooks/useContextMenu';
@@ -31,16 +32,17 @@ export default function GamePage() {
   const gameConfig = useAppSelector(selectGameConfig(gameId));
   const mapStates = useAppSelector(selectGameMapStates(gameId));
   const players = useAppSelector(selectPlayers(gameId));
+  const gameResult = useAppSelector(selectGameResult(gameId));
 
   if (!gameConfig || !mapStates) throw new Error('Game not found');
 
   const [roundNumber, setRoundNumber] = useState(0);
 
   useEffect(() => {
-    if (mapStates.length <= gameConfig.roundsCount) {
+    if (!gameResult && mapStates.length <= gameConfig.roundsCount) {
       dispatch(doRound({ gameId }));
     }
-  }, [dispatch, gameConfig.roundsCount, gameId, mapStates]);
+  }, [dispatch, gameConfig.roundsCount, gameId, gameResult, mapStates]);
 
   const hasPlayerActions = Boolean(mapStates[roundNumber]?.playerActions?.length);
   const mapState = useMemo(() => {
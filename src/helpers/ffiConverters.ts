@@ -1,6 +1,6 @@
 import type {
   EnergyStationStructType,
-  GameConfigStructType, LibraryInfoStructType,
+  GameConfigStructType, GameResultStructType, LibraryInfoStructType,
   MapStructType,
   PlayerActionsType,
   PlayerActionTypeEnum,
@@ -14,9 +14,12 @@ import {
   CollectEnergyStruct,
   PlayerActionMoveFailedStruct,
   PlayerActionMoveStruct, TimeoutStruct,
+  TransferEnergyFailedStruct,
+  TransferEnergyStruct,
 } from './ffiStructs';
 import type {
-  GameConfig, GameEnergyStation, GameLibraryInfo, GameMap, GamePlayerActions, GamePosition, GameRobot,
+  GameConfig, GameEndReason, GameEnergyStation, GameLibraryInfo, GameMap, GamePlayerActions, GamePosition,
+  GameResult, GameRobot,
 } from '../types/gameTypes';
 import ffiReadArray from './ffiReadArray';
 import ffiReadEnumArray from './ffiReadEnumArray';
@@ -33,6 +36,37 @@ export function gameConfigToStruct(gameConfig: GameConfig): GameConfigStructType
     energy_loss_to_clone_robot: gameConfig.energyLossToCloneRobot,
     max_robots_count: gameConfig.maxRobotsCount,
     energy_collect_distance: gameConfig.energyCollectDistance,
+    board_shape: gameConfig.boardShape ?? 0,
+    height: gameConfig.height ?? 0,
+    action_points: gameConfig.actionPoints ?? 0,
+    move_action_points: gameConfig.moveActionPoints ?? 0,
+    collect_energy_action_points: gameConfig.collectEnergyActionPoints ?? 0,
+    clone_robot_action_points: gameConfig.cloneRobotActionPoints ?? 0,
+    audit: gameConfig.audit ? 1 : 0,
+    turn_order: gameConfig.turnOrder ?? 0,
+    clones_wait_a_round: gameConfig.clonesWaitARound ? 1 : 0,
+    end_when_last_player_standing: gameConfig.endWhenLastPlayerStanding ? 1 : 0,
+    target_energy: gameConfig.targetEnergy ?? 0,
+    end_when_stations_exhausted: gameConfig.endWhenStationsExhausted ? 1 : 0,
+    stale_rounds_limit: gameConfig.staleRoundsLimit ?? 0,
+    energy_transfer_fee: gameConfig.energyTransferFee ?? 0,
+    transfer_energy_action_points: gameConfig.transferEnergyActionPoints ?? 0,
+    game_mode: gameConfig.gameMode ?? 0,
+  };
+}
+
+const GAME_END_REASONS: GameEndReason[] = [
+  'roundsLimit', 'lastPlayerStanding', 'targetEnergy', 'stationsExhausted', 'noStateChange',
+];
+
+// Matches `NO_PLAYER` in the core
+const NO_PLAYER = 0xFFFFFFFF;
+
+export function gameResultStructToObject(gameResult: GameResultStructType): GameResult {
+  return {
+    roundsPlayed: gameResult.rounds_played,
+    reason: GAME_END_REASONS[gameResult.reason],
+    winner: gameResult.winner === NO_PLAYER ? undefined : gameResult.winner,
   };
 }
 
@@ -117,6 +151,20 @@ function playerActionStructToObject(playerAction: PlayerActionTypeEnum): GamePla
         robotId: playerAction.robot_id,
         isTimeoutTooMuch: playerAction.is_timeout_too_much,
       };
+    case 7:
+      return {
+        type: 'transferEnergy',
+        robotId: playerAction.robot_id,
+        targetRobotId: playerAction.target_robot_id,
+        amount: playerAction.amount,
+        fee: playerAction.fee,
+      };
+    case 8:
+      return {
+        type: 'transferEnergyFailed',
+        robotId: playerAction.robot_id,
+        targetRobotId: playerAction.target_robot_id,
+      };
     default:
       return undefined;
   }
@@ -131,5 +179,7 @@ export function playerActionsStructToObject(playerActions: PlayerActionsType): a
     CollectEnergyStruct,
     CollectEnergyFailedStruct,
     TimeoutStruct,
+    TransferEnergyStruct,
+    TransferEnergyFailedStruct,
   ]).map(playerActionStructToObject);
 }
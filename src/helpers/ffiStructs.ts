@@ -129,6 +129,34 @@ export const TimeoutStruct = new Struct<Timeout>({
   is_timeout_too_much: 'bool',
 });
 
+export type TransferEnergy = {
+  id: 7,
+  robot_id: number,
+  target_robot_id: number,
+  amount: number,
+  fee: number,
+};
+
+export const TransferEnergyStruct = new Struct<TransferEnergy>({
+  id: 'u32',
+  robot_id: 'usize',
+  target_robot_id: 'usize',
+  amount: 'u32',
+  fee: 'u32',
+});
+
+export type TransferEnergyFailed = {
+  id: 8,
+  robot_id: number,
+  target_robot_id: number,
+};
+
+export const TransferEnergyFailedStruct = new Struct<TransferEnergyFailed>({
+  id: 'u32',
+  robot_id: 'usize',
+  target_robot_id: 'usize',
+});
+
 export type PlayerActionTypeEnum =
     | PlayerActionMove
     | PlayerActionMoveFailed
@@ -136,7 +164,9 @@ export type PlayerActionTypeEnum =
     | CloneRobotFailed
     | CollectEnergy
     | CollectEnergyFailed
-    | Timeout;
+    | Timeout
+    | TransferEnergy
+    | TransferEnergyFailed;
 
 export type PlayerActionsType = {
   player_actions_len: number,
@@ -171,6 +201,22 @@ export type GameConfigStructType = {
   energy_loss_to_clone_robot: number,
   max_robots_count: number,
   energy_collect_distance: number,
+  board_shape: number,
+  height: number,
+  action_points: number,
+  move_action_points: number,
+  collect_energy_action_points: number,
+  clone_robot_action_points: number,
+  audit: number,
+  turn_order: number,
+  clones_wait_a_round: number,
+  end_when_last_player_standing: number,
+  target_energy: number,
+  end_when_stations_exhausted: number,
+  stale_rounds_limit: number,
+  energy_transfer_fee: number,
+  transfer_energy_action_points: number,
+  game_mode: number,
 };
 
 export const GameConfigStruct = new Struct<GameConfigStructType>({
@@ -184,4 +230,32 @@ export const GameConfigStruct = new Struct<GameConfigStructType>({
   energy_loss_to_clone_robot: 'u32',
   max_robots_count: 'u32',
   energy_collect_distance: 'u32',
+  board_shape: 'u32',
+  height: 'i32',
+  action_points: 'u32',
+  move_action_points: 'u32',
+  collect_energy_action_points: 'u32',
+  clone_robot_action_points: 'u32',
+  audit: 'u32',
+  turn_order: 'u32',
+  clones_wait_a_round: 'u32',
+  end_when_last_player_standing: 'u32',
+  target_energy: 'u32',
+  end_when_stations_exhausted: 'u32',
+  stale_rounds_limit: 'u32',
+  energy_transfer_fee: 'u32',
+  transfer_energy_action_points: 'u32',
+  game_mode: 'u32',
+});
+
+export type GameResultStructType = {
+  rounds_played: number,
+  reason: number,
+  winner: number,
+};
+
+export const GameResultStruct = new Struct<GameResultStructType>({
+  rounds_played: 'u32',
+  reason: 'u32',
+  winner: 'u32',
 });
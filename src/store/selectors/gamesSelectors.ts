@@ -8,6 +8,7 @@ export const selectGame = (gameId: string) => createSelector(selectGames, (games
 export const selectGameConfig = (gameId: string) => createSelector(selectGame(gameId), (game) => game.gameConfig);
 export const selectGameCategoryId = (gameId: string) => createSelector(selectGame(gameId), (game) => game.categoryId);
 export const selectGameMapStates = (gameId: string) => createSelector(selectGame(gameId), (game) => game.mapStates);
+export const selectGameResult = (gameId: string) => createSelector(selectGame(gameId), (game) => game.result);
 export const selectPlayers = (gameId: string) => (state: RootState) => {
   return Object.values(selectGame(gameId)(state).players).map((playerId) => state.users.users[playerId]);
 };
@@ -61,12 +61,12 @@ AppThunkApi
 
 export const runGame = createAsyncThunk<
 ResultType<Run>,
-ApiAlgoVersionId[],
+{ categoryId: number, algoVersions: ApiAlgoVersionId[], seed?: number },
 AppThunkApi
 >(
   'algos/runGame',
-  async (algoVersions, { dispatch }) => {
-    return api(dispatch, new Run({ algoVersions }));
+  async (payload, { dispatch }) => {
+    return api(dispatch, new Run(payload));
   },
 );
 
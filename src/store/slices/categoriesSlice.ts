@@ -25,7 +25,7 @@ const initialState: CategoriesState = {
       gameConfig: {
         width: 16,
         roundsCount: 50,
-        playersCount: 0, // algos.length,
+        playersCount: 2, // algos.length,
         initialRobotsCount: 10,
         startEnergy: 50,
         rngSeed: 123,
@@ -49,7 +49,7 @@ const initialState: CategoriesState = {
       gameConfig: {
         width: 16,
         roundsCount: 50,
-        playersCount: 0, // algos.length,
+        playersCount: 2, // algos.length,
         initialRobotsCount: 10,
         startEnergy: 50,
         rngSeed: 123,
@@ -73,7 +73,7 @@ const initialState: CategoriesState = {
       gameConfig: {
         width: 16,
         roundsCount: 50,
-        playersCount: 0, // algos.length,
+        playersCount: 2, // algos.length,
         initialRobotsCount: 10,
         startEnergy: 50,
         rngSeed: 123,
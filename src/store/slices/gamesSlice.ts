@@ -2,7 +2,9 @@ import { createAsyncThunk, createSlice } from '@reduxjs/toolkit';
 import * as Comlink from 'comlink';
 import type { AppThunkApi } from '../index';
 import type { CoreWorkerType } from '../../workers/core.worker';
-import type { GameConfig, GameMap, GamePlayerActions } from '../../types/gameTypes';
+import type {
+  GameConfig, GameMap, GamePlayerActions, GameResult,
+} from '../../types/gameTypes';
 import type { UUID } from '../../helpers/createUUID';
 import createUUID from '../../helpers/createUUID';
 import type { ApiAlgoVersionWithFile } from '../../api/types';
@@ -30,6 +32,8 @@ export type GameState = {
   gameConfig: GameConfig;
   logs: Record<PlayerId, Log>;
   mapStates: MapState[];
+  // Set once the core ends the game
+  result?: GameResult;
   players: Record<PlayerId, string>;
   categoryId: CategoryId;
 };
@@ -141,6 +145,7 @@ AppThunkApi
 type MR = {
   map: GameMap;
   playerActions: GamePlayerActions[];
+  result?: GameResult;
 };
 
 export const doRound = createAsyncThunk<MR, { gameId: GameId; }, AppThunkApi>(
@@ -160,7 +165,12 @@ export const doRound = createAsyncThunk<MR, { gameId: GameId; }, AppThunkApi>(
 
     await game.core.doRound();
 
-    return promise;
+    const mr = await promise;
+
+    return {
+      ...mr,
+      result: await game.core.getGameResult(),
+    };
   },
 );
 
@@ -193,7 +203,7 @@ export const gamesSlice = createSlice({
         const game = state.games[action.meta.arg.gameId];
         if (!game) throw Error('Game not found');
 
-        const { map, playerActions } = action.payload;
+        const { map, playerActions, result } = action.payload;
 
         const other = game.mapStates.slice(0, -1);
         const last = game.mapStates[game.mapStates.length - 1];
@@ -205,6 +215,7 @@ export const gamesSlice = createSlice({
           map,
           playerActions: [],
         }];
+        state.games[action.meta.arg.gameId].result = result;
       })
       .addCase(doRound.rejected, () => {
         // TODO display error
@@ -33,6 +33,40 @@ export type GameConfig = {
   timeout: number,
   maxTimeoutsCount: number,
   energyCollectDistance: number,
+  boardShape?: number,
+  height?: number,
+  scenario?: string,
+  actionPoints?: number,
+  moveActionPoints?: number,
+  collectEnergyActionPoints?: number,
+  cloneRobotActionPoints?: number,
+  audit?: boolean,
+  turnOrder?: number,
+  clonesWaitARound?: boolean,
+  endWhenLastPlayerStanding?: boolean,
+  targetEnergy?: number,
+  endWhenStationsExhausted?: boolean,
+  staleRoundsLimit?: number,
+  energyTransferFee?: number,
+  transferEnergyActionPoints?: number,
+  gameMode?: number,
+  fuel?: number,
+  limits?: {
+    memoryPages: number,
+    tableElements: number,
+    stackSize: number,
+    instances: number,
+  },
+};
+
+export type GameEndReason =
+    'roundsLimit' | 'lastPlayerStanding' | 'targetEnergy' | 'stationsExhausted' | 'noStateChange';
+
+export type GameResult = {
+  roundsPlayed: number,
+  reason: GameEndReason,
+  // Undefined on a draw
+  winner?: number,
 };
 
 export type GameLibraryInfo = {
@@ -81,6 +115,20 @@ export type Timeout = {
   isTimeoutTooMuch: boolean,
 };
 
+export type GameTransferEnergy = {
+  type: 'transferEnergy',
+  robotId: number,
+  targetRobotId: number,
+  amount: number,
+  fee: number,
+};
+
+export type GameTransferEnergyFailed = {
+  type: 'transferEnergyFailed',
+  robotId: number,
+  targetRobotId: number,
+};
+
 export type GamePlayerActions =
     GamePlayerActionMove | GamePlayerActionMoveFailed | GameCloneRobot | GameCloneRobotFailed |
-    GameCollectEnergy | GameCollectEnergyFailed | Timeout;
+    GameCollectEnergy | GameCollectEnergyFailed | Timeout | GameTransferEnergy | GameTransferEnergyFailed;
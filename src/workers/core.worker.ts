@@ -5,26 +5,34 @@ import { init, WASI } from '@wasmer/wasi';
 import core from '../wasm/core.wasm';
 import type { GameConfig, GameMap, GamePlayerActions } from '../types/gameTypes';
 import type {
-  GameConfigStructType,
+  GameConfigStructType, GameResultStructType,
   MapStructType, PlayerActionsType,
 } from '../helpers/ffiStructs';
 import {
-  GameConfigStruct, MapStruct, PlayerActionsStruct,
+  GameConfigStruct, GameResultStruct, MapStruct, PlayerActionsStruct,
 } from '../helpers/ffiStructs';
 import type { PlayerWorkerType } from './player.worker';
-import { gameConfigToStruct, mapStructToObject, playerActionsStructToObject } from '../helpers/ffiConverters';
+import {
+  gameConfigToStruct, gameResultStructToObject, mapStructToObject, playerActionsStructToObject,
+} from '../helpers/ffiConverters';
 import delay from '../helpers/delay';
 
 type Exports = {
   init_mod: () => void,
   init_game: (gameConfig: GameConfigStructType) => void,
+  init_game_with_scenario: (gameConfig: GameConfigStructType, scenario: string) => boolean,
   do_round: VoidFunction,
   get_map: () => MapStructType,
+  get_map_at: (round: number) => MapStructType,
+  rewind: (round: number) => boolean,
   done_step: (is_timeout: boolean, is_timeout_too_much: boolean) => void,
   move_robot: (x: number, y: number) => void,
   clone_robot: (energy: number) => void,
   collect_energy: () => void;
+  transfer_energy: (targetRobot: number, amount: number) => void,
   get_player_actions: (round: number) => PlayerActionsType,
+  is_game_over: () => boolean,
+  get_game_result: () => GameResultStructType,
 };
 
 type RoundFinishedCallback = (map: GameMap, playerActions: GamePlayerActions[]) => void;
@@ -51,6 +59,7 @@ const initPlayerWorker = async (algo: Blob, i: number) => {
     Comlink.proxy(wrapper.move_robot.bind(wrapper)),
     Comlink.proxy(wrapper.collect_energy.bind(wrapper)),
     Comlink.proxy(wrapper.clone_robot.bind(wrapper)),
+    Comlink.proxy(wrapper.transfer_energy.bind(wrapper)),
     Comlink.proxy((log: string, errorLog: string) => onLogUpdated(i, log, errorLog)),
   );
 
@@ -60,7 +69,7 @@ const initPlayerWorker = async (algo: Blob, i: number) => {
     comlink,
     worker,
     algo,
-    timeouts: (playerWorkers[i]?.timeouts || -1) + 1,
+    timeouts: 0,
   };
 };
 
@@ -90,8 +99,11 @@ const doStep = async (owner: number, robotToMoveIndex: number, map: MapStructTyp
     playerWorkers[owner].worker.terminate();
     const isTimeoutTooMuch = playerWorkers[owner]?.timeouts >= currentGameConfig.maxTimeoutsCount;
     if (!isTimeoutTooMuch) {
-      playerWorkers[owner].timeouts++;
-      playerWorkers[owner] = await initPlayerWorker(playerWorkers[owner].algo, owner);
+      const timeouts = playerWorkers[owner].timeouts + 1;
+      playerWorkers[owner] = {
+        ...(await initPlayerWorker(playerWorkers[owner].algo, owner)),
+        timeouts,
+      };
     } else {
       // TODO we're too fast, lock is not unlocked at this point
       await delay(1);
@@ -116,6 +128,28 @@ const CoreWorker = {
 
     return mapStructToObject(map);
   },
+  getMapAt: async (round: number) => {
+    const map = wrapper.get_map_at(round);
+
+    return mapStructToObject(map);
+  },
+  // Continues the game from an earlier round, optionally with other algos
+  rewind: async (round: number, algos?: (File | Blob)[]) => {
+    if (algos) {
+      // Swapped at once, so timeouts are never counted against a half built list
+      const workers = await Promise.all(algos.map(initPlayerWorker));
+      playerWorkers.forEach(({ worker }) => worker.terminate());
+      playerWorkers = workers;
+    }
+    // Timeouts are counted per round, the rewound round starts without any
+    for (let i = 0; i < playerWorkers.length; i++) {
+      playerWorkers[i].timeouts = 0;
+    }
+
+    if (!wrapper.rewind(round)) {
+      throw Error(`Round ${round} wasn't played yet`);
+    }
+  },
   doRound: () => {
     wrapper.do_round();
     // eslint-disable-next-line no-console
@@ -131,7 +165,13 @@ const CoreWorker = {
     playerWorkers = await Promise.all(algos.map(initPlayerWorker));
 
     try {
-      wrapper.init_game(gameConfigToStruct(gameConfig));
+      if (gameConfig.scenario) {
+        if (!wrapper.init_game_with_scenario(gameConfigToStruct(gameConfig), gameConfig.scenario)) {
+          throw Error('Invalid scenario');
+        }
+      } else {
+        wrapper.init_game(gameConfigToStruct(gameConfig));
+      }
     } catch (e) {
       // eslint-disable-next-line no-console
       console.error('[wcore] log', wasi.getStdoutString());
@@ -141,6 +181,11 @@ const CoreWorker = {
       console.warn(e);
     }
   },
+  getGameResult: async () => {
+    if (!wrapper.is_game_over()) return undefined;
+
+    return gameResultStructToObject(wrapper.get_game_result());
+  },
   get_player_actions: (round: number) => {
     return playerActionsStructToObject(wrapper.get_player_actions(round));
   },
@@ -171,13 +216,19 @@ const CoreWorker = {
     wrapper = new Wrapper<Exports>({
       init_mod: [null],
       init_game: [null, [GameConfigStruct]],
+      init_game_with_scenario: ['bool', [GameConfigStruct, 'string']],
       do_round: [null],
       get_map: [MapStruct],
+      get_map_at: [MapStruct, ['u32']],
+      rewind: ['bool', ['u32']],
       done_step: [null, ['bool']],
       move_robot: [null, ['i32', 'i32']],
       clone_robot: [null, ['u32']],
       collect_energy: [null, []],
+      transfer_energy: [null, ['usize', 'u32']],
       get_player_actions: [PlayerActionsStruct, ['u32']],
+      is_game_over: ['bool'],
+      get_game_result: [GameResultStruct],
     });
 
     instance = await wasi.instantiate(module, wrapper.imports((wrap) => ({
@@ -4,6 +4,7 @@ import {
   Wrapper, Pointer,
 } from 'wasm-ffi';
 import { init, WASI } from '@wasmer/wasi';
+import core from '../wasm/core.wasm';
 import type {
   GameConfigStructType, LibraryInfoStructType,
   MapStructType,
@@ -22,10 +23,39 @@ type Exports = {
   get_lib_info: () => AbstractStructType<LibraryInfoStructType>
 };
 
+// Copy of the core used to answer bot queries synchronously
+type CoreExports = {
+  abi_version: () => number,
+  init_queries: (gameConfig: GameConfigStructType) => void,
+  load_snapshot: (map: AbstractStructType<MapStructType>, robotIndex: number, roundNo: number) => void,
+  query_config: (out: number) => void,
+  query_my_robots: (out: number, capacity: number) => number,
+  query_is_cell_free: (q: number, r: number) => boolean,
+  query_move_cost: (fromQ: number, fromR: number, toQ: number, toR: number) => number,
+  query_stations_in_range: (q: number, r: number, out: number, capacity: number) => number,
+  clone_robot: (energy: number) => void,
+  collect_energy: () => void,
+  move_robot: (q: number, r: number) => void,
+  transfer_energy: (targetRobot: number, amount: number) => void,
+};
+
+type RawExports = {
+  memory: WebAssembly.Memory,
+  abi_version?: () => number,
+  allocate: (length: number) => number,
+  deallocate: (ptr: number, length: number) => void,
+};
+
+const USIZE_WIDTH = 4;
+
 let wasi: WASI;
 let instance: WebAssembly.Instance;
 let wrapper: IWrapper<Exports>;
-let actionDoneResolver: VoidFunction | undefined;
+let coreInstance: WebAssembly.Instance;
+let coreWrapper: IWrapper<CoreExports>;
+// Actions of the current step. Each callback has its own port, so they are
+// chained to reach the core in the order the bot made them.
+let stepActions: Promise<unknown> = Promise.resolve();
 let onLogUpdated: (log: string, errorLog: string) => void;
 
 function addLogs() {
@@ -34,47 +64,115 @@ function addLogs() {
   onLogUpdated(stdout + (stdout ? '\n' : ''), stderr + (stderr ? '\n' : ''));
 }
 
-function withPromiseResolver<T extends Array<any>, U>(afterFn: (...args: T) => U) {
-  return (...args: T): U => {
-    actionDoneResolver?.();
-    actionDoneResolver = undefined;
-    return afterFn(...args);
+function mapToStruct(map: GameMap) {
+  const robots = map.robots.map((robot) => new RobotStruct({
+    position: new PositionStruct({
+      q: robot.position.q,
+      r: robot.position.r,
+    }),
+    energy: robot.energy,
+    owner: robot.owner,
+  }));
+
+  const energyStations = map.energyStations.map((energyStation) => new EnergyStationStruct({
+    position: new PositionStruct({
+      q: energyStation.position.q,
+      r: energyStation.position.r,
+    }),
+    recovery_rate: energyStation.recoveryRate,
+    energy: energyStation.energy,
+  }));
+
+  return new MapStruct({
+    robots_len: robots.length,
+    robots: new Pointer([RobotStruct, robots.length], robots),
+    energy_stations_len: energyStations.length,
+    energy_stations: new Pointer([EnergyStationStruct, energyStations.length], energyStations),
+  });
+}
+
+// Runs a core query writing `length` bytes and copies them into the bot's memory at `ptr`
+function copyFromCore<T>(ptr: number, length: number, query: (corePtr: number) => T): T {
+  const coreExports = coreInstance.exports as RawExports;
+  const corePtr = coreExports.allocate(length);
+  const result = query(corePtr);
+  new Uint8Array((instance.exports as RawExports).memory.buffer, ptr, length)
+    .set(new Uint8Array(coreExports.memory.buffer, corePtr, length));
+  coreExports.deallocate(corePtr, length);
+  return result;
+}
+
+// Runs an index query and copies up to `capacity` indices into the bot's memory at `ptr`.
+// `capacity` comes from the bot, so the query is counted first and only the
+// indices the core has are allocated and copied.
+function copyIndicesFromCore(
+  ptr: number,
+  capacity: number,
+  query: (corePtr: number, capacity: number) => number,
+): number {
+  const count = query(0, 0);
+  const length = Math.min(count, capacity);
+  if (length <= 0) return count;
+  return copyFromCore(ptr, length * USIZE_WIDTH, (corePtr) => query(corePtr, length));
+}
+
+async function initCore() {
+  const coreWasi = new WASI({
+    env: {},
+    args: [],
+  });
+
+  const module = await WebAssembly.compileStreaming(fetch(core));
+
+  coreWrapper = new Wrapper<CoreExports>({
+    abi_version: ['u32'],
+    init_queries: [null, [GameConfigStruct]],
+    load_snapshot: [null, [MapStruct, 'usize', 'u32']],
+    query_config: [null, ['usize']],
+    query_my_robots: ['usize', ['usize', 'usize']],
+    query_is_cell_free: ['bool', ['i32', 'i32']],
+    query_move_cost: ['u32', ['i32', 'i32', 'i32', 'i32']],
+    query_stations_in_range: ['usize', ['i32', 'i32', 'usize', 'usize']],
+    clone_robot: [null, ['u32']],
+    collect_energy: [null, []],
+    move_robot: [null, ['i32', 'i32']],
+    transfer_energy: [null, ['usize', 'u32']],
+  });
+
+  coreInstance = await coreWasi.instantiate(module, coreWrapper.imports(() => ({
+    robotchallenge: {
+      do_step: () => 0,
+      round_finished: () => undefined,
+    },
+  })));
+
+  coreWrapper.use(coreInstance);
+}
+
+// Applies an action to the query core right away, so the bot's next queries
+// see it like they do in the runner, and queues it for the game's core
+function queueAction<T extends Array<any>>(
+  apply: (...args: T) => void,
+  action: (...args: T) => unknown,
+) {
+  return (...args: T): void => {
+    apply(...args);
+    stepActions = stepActions.then(() => action(...args));
   };
 }
 
 const PlayerWorker = {
   doStep: async (map: GameMap, robotToMoveIndex: number, roundNo: number): Promise<void> => {
-    const robots = map.robots.map((robot) => new RobotStruct({
-      position: new PositionStruct({
-        q: robot.position.q,
-        r: robot.position.r,
-      }),
-      energy: robot.energy,
-      owner: robot.owner,
-    }));
-
-    const energyStations = map.energyStations.map((energyStation) => new EnergyStationStruct({
-      position: new PositionStruct({
-        q: energyStation.position.q,
-        r: energyStation.position.r,
-      }),
-      recovery_rate: energyStation.recoveryRate,
-      energy: energyStation.energy,
-    }));
+    coreWrapper.load_snapshot(mapToStruct(map), robotToMoveIndex, roundNo);
 
     try {
-      const actionDonePromise = new Promise<void>((resolve) => {
-        actionDoneResolver = resolve;
-      });
-      wrapper.do_step_ffi(new MapStruct({
-        robots_len: robots.length,
-        robots: new Pointer([RobotStruct, robots.length], robots),
-        energy_stations_len: energyStations.length,
-        energy_stations: new Pointer([EnergyStationStruct, energyStations.length], energyStations),
-      }), robotToMoveIndex, roundNo);
+      stepActions = Promise.resolve();
+      wrapper.do_step_ffi(mapToStruct(map), robotToMoveIndex, roundNo);
       addLogs();
 
-      return await actionDonePromise;
+      // A robot may act several times per turn, its step is over once the
+      // bot returns and the core got all of its actions
+      return await stepActions.then(() => undefined);
     } catch (e) {
       addLogs();
       // Do nothing
@@ -84,6 +182,9 @@ const PlayerWorker = {
   },
   initGame: async (gameConfig: GameConfig, owner: number): Promise<void> => {
     try {
+      // The map comes from `load_snapshot` before every step and is kept up
+      // to date with the bot's actions during it
+      coreWrapper.init_queries(gameConfigToStruct(gameConfig));
       // TODO timeout
       wrapper.init_game(gameConfigToStruct(gameConfig), owner);
       addLogs();
@@ -107,9 +208,11 @@ const PlayerWorker = {
     onMove: (x: number, y: number) => void,
     onCollectEnergy: () => void,
     onCloneRobot: (energy: number) => void,
+    onTransferEnergy: (targetRobot: number, amount: number) => void,
     _onLogUpdated: (log: string, errorLog: string) => void,
   ) => {
     await init();
+    await initCore();
 
     onLogUpdated = _onLogUpdated;
 
@@ -133,9 +236,43 @@ const PlayerWorker = {
       // TODO error handling
       instance = await wasi.instantiate(module, wrapper.imports((wrap) => ({
         robotchallenge: {
-          clone_robot: wrap([null, ['u32']], withPromiseResolver((energy) => onCloneRobot(energy))),
-          collect_energy: wrap([null, []], withPromiseResolver(() => onCollectEnergy())),
-          move_robot: wrap([null, ['i32', 'i32']], withPromiseResolver((q, r) => onMove(q, r))),
+          clone_robot: wrap([null, ['u32']], queueAction(
+            (energy) => coreWrapper.clone_robot(energy),
+            (energy) => onCloneRobot(energy),
+          )),
+          collect_energy: wrap([null, []], queueAction(
+            () => coreWrapper.collect_energy(),
+            () => onCollectEnergy(),
+          )),
+          move_robot: wrap([null, ['i32', 'i32']], queueAction(
+            (q, r) => coreWrapper.move_robot(q, r),
+            (q, r) => onMove(q, r),
+          )),
+          transfer_energy: wrap([null, ['usize', 'u32']], queueAction(
+            (targetRobot, amount) => coreWrapper.transfer_energy(targetRobot, amount),
+            (targetRobot, amount) => onTransferEnergy(targetRobot, amount),
+          )),
+          get_config: wrap([null, ['usize']], (out: number) => copyFromCore(
+            out,
+            new Pointer([GameConfigStruct, 1], undefined).type.width,
+            (corePtr) => coreWrapper.query_config(corePtr),
+          )),
+          get_my_robots: wrap(['usize', ['usize', 'usize']], (out: number, capacity: number) => copyIndicesFromCore(
+            out,
+            capacity,
+            (corePtr, length) => coreWrapper.query_my_robots(corePtr, length),
+          )),
+          is_cell_free: wrap(['bool', ['i32', 'i32']], (q: number, r: number) => coreWrapper.query_is_cell_free(q, r)),
+          move_cost: wrap(['u32', ['i32', 'i32', 'i32', 'i32']], (
+            fromQ: number, fromR: number, toQ: number, toR: number,
+          ) => coreWrapper.query_move_cost(fromQ, fromR, toQ, toR)),
+          stations_in_range: wrap(['usize', ['i32', 'i32', 'usize', 'usize']], (
+            q: number, r: number, out: number, capacity: number,
+          ) => copyIndicesFromCore(
+            out,
+            capacity,
+            (corePtr, length) => coreWrapper.query_stations_in_range(q, r, corePtr, length),
+          )),
         },
       })));
 
@@ -144,6 +281,15 @@ const PlayerWorker = {
       // console.error(e);
     }
 
+    // Bots built before the ABI was versioned don't export it at all
+    const abiVersion = (instance?.exports as RawExports | undefined)?.abi_version?.();
+    if (abiVersion !== coreWrapper.abi_version()) {
+      throw new Error(
+        `Algo was built for ABI version ${abiVersion ?? 'unknown'}, but the game needs `
+        + `version ${coreWrapper.abi_version()}. Rebuild it with the latest library.`,
+      );
+    }
+
     return true;
   },
 };
@@ -0,0 +1,43 @@
+//! The pointer carrying structs as a host reads them from wasm32 memory,
+//! where pointers and `usize` are 4 bytes.
+
+use crate::{EnergyStation, Robot};
+use wasmer::WasmPtr;
+use wasmer_derive::ValueType;
+
+/// Host copy of [`crate::MapFFI`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueType)]
+#[repr(C)]
+pub struct MapFFI {
+    pub robots_len: u32,
+    pub robots: WasmPtr<Robot>,
+    pub energy_stations_len: u32,
+    pub energy_stations: WasmPtr<EnergyStation>,
+}
+
+/// Host copy of [`crate::InternalLibInfo`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueType)]
+#[repr(C)]
+pub struct InternalLibInfo {
+    pub name: WasmPtr<u8>,
+    pub language: WasmPtr<u8>,
+    pub version: WasmPtr<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{offset_of, size_of};
+
+    #[test]
+    fn host_copies_have_the_wasm32_layout() {
+        assert_eq!(size_of::<MapFFI>(), 16);
+        assert_eq!(offset_of!(MapFFI, robots), 4);
+        assert_eq!(offset_of!(MapFFI, energy_stations_len), 8);
+        assert_eq!(offset_of!(MapFFI, energy_stations), 12);
+
+        assert_eq!(size_of::<InternalLibInfo>(), 12);
+        assert_eq!(offset_of!(InternalLibInfo, language), 4);
+        assert_eq!(offset_of!(InternalLibInfo, version), 8);
+    }
+}
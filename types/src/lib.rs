@@ -0,0 +1,267 @@
+//! `repr(C)` structs exchanged between the core, the bots and the hosts
+//! running them, plus the constants they're read with.
+//!
+//! Structs holding pointers are declared as the wasm modules see them, hosts
+//! read them from wasm memory through the copies in [`host`].
+
+use std::os::raw::c_char;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasmer")]
+use wasmer_derive::ValueType;
+
+#[cfg(feature = "wasmer")]
+pub mod host;
+
+/// Version of the structs and functions shared between the core and the bots.
+/// Bump it on every change to them, e.g. a new `GameConfig` field, and keep it
+/// in sync with `libs/common/ffi.h` and the C# library.
+pub const ABI_VERSION: u32 = 1;
+
+/// Hexagon of radius `width` around (0, 0)
+pub const BOARD_SHAPE_HEXAGON: u32 = 0;
+/// `width` x `height` rectangle of hexes centered around (0, 0), in "odd-r" layout
+pub const BOARD_SHAPE_RECTANGLE: u32 = 1;
+/// Same as the rectangle, but moving over an edge wraps around to the opposite one
+pub const BOARD_SHAPE_TORUS: u32 = 2;
+
+/// Robots act in the order they were created
+pub const TURN_ORDER_SEQUENTIAL: u32 = 0;
+/// One robot of each player at a time, players in ascending order
+pub const TURN_ORDER_INTERLEAVED: u32 = 1;
+/// Robots are shuffled every round
+pub const TURN_ORDER_RANDOM: u32 = 2;
+/// Like interleaved, but players go in descending order every other round
+pub const TURN_ORDER_REVERSE_ALTERNATING: u32 = 3;
+
+/// The default rules
+pub const GAME_MODE_CLASSIC: u32 = 0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "wasmer", derive(ValueType))]
+#[repr(C)]
+pub struct Position {
+    pub q: i32,
+    pub r: i32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "wasmer", derive(ValueType))]
+#[repr(C)]
+pub struct Robot {
+    pub position: Position,
+    pub energy: u32,
+    pub owner: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "wasmer", derive(ValueType))]
+#[repr(C)]
+pub struct EnergyStation {
+    pub position: Position,
+    pub recovery_rate: u32,
+    pub energy: u32,
+}
+
+/// Robots and energy stations passed to `do_step_ffi` and returned by the
+/// core's `get_map`. See [`host::MapFFI`] for the host's view of it.
+#[derive(Debug)]
+#[repr(C)]
+pub struct MapFFI {
+    pub robots_len: usize,
+    pub robots: *mut Robot,
+    pub energy_stations_len: usize,
+    pub energy_stations: *mut EnergyStation,
+}
+
+/// Read from the same camelCase JSON the frontend uses, missing fields fall
+/// back to the admin page defaults.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+#[cfg_attr(feature = "wasmer", derive(ValueType))]
+#[repr(C)]
+pub struct GameConfig {
+    pub width: i32,
+    pub rounds_count: u32,
+    /// Number of bots, hosts set it for every match
+    pub players_count: u32,
+    pub initial_robots_count: u32,
+    pub start_energy: u32,
+    pub rng_seed: u32,
+    pub energy_stations_per_robot: u32,
+    pub energy_loss_to_clone_robot: u32,
+    pub max_robots_count: u32,
+    pub energy_collect_distance: i32,
+    /// One of `BOARD_SHAPE_HEXAGON`, `BOARD_SHAPE_RECTANGLE` or `BOARD_SHAPE_TORUS`
+    pub board_shape: u32,
+    /// Number of rows for rectangle and torus boards, defaults to `width` when 0.
+    /// Has to be even on a torus
+    pub height: i32,
+    /// Action points each robot gets per turn. 0 means a single action per turn
+    pub action_points: u32,
+    /// Action points spent by each action type, 0 is treated as 1
+    pub move_action_points: u32,
+    pub collect_energy_action_points: u32,
+    pub clone_robot_action_points: u32,
+    /// 1 if the core checks energy conservation and position invariants
+    #[cfg_attr(feature = "serde", serde(with = "flag"))]
+    pub audit: u32,
+    /// One of the `TURN_ORDER_*` policies
+    pub turn_order: u32,
+    /// 1 if cloned robots only act starting from the round after they were created
+    #[cfg_attr(feature = "serde", serde(with = "flag"))]
+    pub clones_wait_a_round: u32,
+    /// 1 ends the game as soon as only one player has robots with energy left
+    #[cfg_attr(feature = "serde", serde(with = "flag"))]
+    pub end_when_last_player_standing: u32,
+    /// Ends the game once a player's robots have this much energy in total, 0 disables it
+    pub target_energy: u32,
+    /// 1 ends the game when every energy station is empty and doesn't recover
+    #[cfg_attr(feature = "serde", serde(with = "flag"))]
+    pub end_when_stations_exhausted: u32,
+    /// Ends the game after this many rounds without any robot changing, 0 disables it
+    pub stale_rounds_limit: u32,
+    /// Energy lost by the sender on top of the transferred amount
+    pub energy_transfer_fee: u32,
+    pub transfer_energy_action_points: u32,
+    /// One of the `GAME_MODE_*` rule sets
+    pub game_mode: u32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            width: 16,
+            rounds_count: 50,
+            players_count: 2,
+            initial_robots_count: 10,
+            start_energy: 50,
+            rng_seed: 123,
+            energy_stations_per_robot: 2,
+            energy_loss_to_clone_robot: 10,
+            max_robots_count: 50,
+            energy_collect_distance: 2,
+            board_shape: BOARD_SHAPE_HEXAGON,
+            height: 0,
+            action_points: 0,
+            move_action_points: 0,
+            collect_energy_action_points: 0,
+            clone_robot_action_points: 0,
+            audit: 0,
+            turn_order: TURN_ORDER_SEQUENTIAL,
+            clones_wait_a_round: 0,
+            end_when_last_player_standing: 0,
+            target_energy: 0,
+            end_when_stations_exhausted: 0,
+            stale_rounds_limit: 0,
+            energy_transfer_fee: 0,
+            transfer_energy_action_points: 0,
+            game_mode: GAME_MODE_CLASSIC,
+        }
+    }
+}
+
+/// Options the frontend stores as booleans but the core takes as integers.
+#[cfg(feature = "serde")]
+mod flag {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Number(u32),
+    }
+
+    pub fn serialize<S: Serializer>(value: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(*value != 0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        Ok(match Flag::deserialize(deserializer)? {
+            Flag::Bool(value) => value as u32,
+            Flag::Number(value) => value,
+        })
+    }
+}
+
+/// Returned by a bot's `get_lib_info`, the strings are nul terminated. See
+/// [`host::InternalLibInfo`] for the host's view of it.
+#[derive(Debug)]
+#[repr(C)]
+pub struct InternalLibInfo {
+    pub name: *mut c_char,
+    pub language: *mut c_char,
+    pub version: *mut c_char,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{align_of, offset_of, size_of};
+
+    /// Size of a pointer or `usize`, 4 bytes in wasm32 memory.
+    const WORD: usize = size_of::<usize>();
+
+    #[test]
+    fn plain_structs_have_the_wasm32_layout() {
+        assert_eq!(size_of::<Position>(), 8);
+        assert_eq!(align_of::<Position>(), 4);
+        assert_eq!(offset_of!(Position, r), 4);
+
+        assert_eq!(size_of::<Robot>(), 16);
+        assert_eq!(align_of::<Robot>(), 4);
+        assert_eq!(offset_of!(Robot, energy), 8);
+        assert_eq!(offset_of!(Robot, owner), 12);
+
+        assert_eq!(size_of::<EnergyStation>(), 16);
+        assert_eq!(align_of::<EnergyStation>(), 4);
+        assert_eq!(offset_of!(EnergyStation, recovery_rate), 8);
+        assert_eq!(offset_of!(EnergyStation, energy), 12);
+    }
+
+    #[test]
+    fn game_config_has_the_wasm32_layout() {
+        assert_eq!(size_of::<GameConfig>(), 26 * 4);
+        assert_eq!(align_of::<GameConfig>(), 4);
+        assert_eq!(offset_of!(GameConfig, width), 0);
+        assert_eq!(offset_of!(GameConfig, energy_collect_distance), 36);
+        assert_eq!(offset_of!(GameConfig, board_shape), 40);
+        assert_eq!(offset_of!(GameConfig, audit), 64);
+        assert_eq!(offset_of!(GameConfig, stale_rounds_limit), 88);
+        assert_eq!(offset_of!(GameConfig, game_mode), 100);
+    }
+
+    #[test]
+    fn pointer_structs_are_made_of_words() {
+        assert_eq!(size_of::<MapFFI>(), 4 * WORD);
+        assert_eq!(offset_of!(MapFFI, robots), WORD);
+        assert_eq!(offset_of!(MapFFI, energy_stations_len), 2 * WORD);
+        assert_eq!(offset_of!(MapFFI, energy_stations), 3 * WORD);
+
+        assert_eq!(size_of::<InternalLibInfo>(), 3 * WORD);
+        assert_eq!(offset_of!(InternalLibInfo, language), WORD);
+        assert_eq!(offset_of!(InternalLibInfo, version), 2 * WORD);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn game_config_reads_flags_as_booleans_or_numbers() {
+        let config: GameConfig =
+            serde_json::from_str(r#"{"width": 8, "audit": true, "clonesWaitARound": 1}"#).unwrap();
+        assert_eq!(config.width, 8);
+        assert_eq!(config.audit, 1);
+        assert_eq!(config.clones_wait_a_round, 1);
+        assert_eq!(config.rounds_count, GameConfig::default().rounds_count);
+
+        let json = serde_json::to_value(config).unwrap();
+        assert_eq!(json["audit"], true);
+        assert_eq!(json["endWhenStationsExhausted"], false);
+    }
+}